@@ -0,0 +1,212 @@
+//! An encoder and decoder of DVI-4 IMA ADPCM as used by the talk and the audio of the camera
+use crate::bc::xml::TalkConfig;
+use crate::{Error, Result};
+
+/// The step sizes of IMA ADPCM
+pub(crate) const STEPS: [i32; 89] = [
+    7, 8, 9, 10, 11, 12, 13, 14, 16, 17, 19, 21, 23, 25, 28, 31, 34, 37, 41, 45, 50, 55, 60, 66,
+    73, 80, 88, 97, 107, 118, 130, 143, 157, 173, 190, 209, 230, 253, 279, 307, 337, 371, 408, 449,
+    494, 544, 598, 658, 724, 796, 876, 963, 1060, 1166, 1282, 1411, 1552, 1707, 1878, 2066, 2272,
+    2499, 2749, 3024, 3327, 3660, 4026, 4428, 4871, 5358, 5894, 6484, 7132, 7845, 8630, 9493,
+    10442, 11487, 12635, 13899, 15289, 16818, 18500, 20350, 22385, 24623, 27086, 29794, 32767,
+];
+
+/// How the step index changes after each nibble
+pub(crate) const CHANGES: [i32; 16] = [-1, -1, -1, -1, 2, 4, 6, 8, -1, -1, -1, -1, 2, 4, 6, 8];
+
+/// The size of the header of each block which holds the first sample and the step index
+pub const BLOCK_HEADER_SIZE: usize = 4;
+
+/// Encodes 16 bit mono PCM into the ADPCM blocks that [`crate::bc_protocol::BcCamera::talk`]
+/// and [`crate::bc_protocol::BcCamera::talk_stream`] expect
+///
+/// Each block is the 4 byte DVI-4 header followed by `block_size` bytes with
+/// two samples in each, the high nibble first
+pub struct AdpcmEncoder {
+    block_size: usize,
+    index: i32,
+    pending: Vec<i16>,
+}
+
+impl AdpcmEncoder {
+    /// An encoder of blocks with `block_size` bytes of samples after the header
+    pub fn new(block_size: usize) -> Self {
+        Self {
+            block_size,
+            index: 0,
+            pending: vec![],
+        }
+    }
+
+    /// An encoder for the block size in the `<TalkConfig>`, which is half of its
+    /// `lengthPerEncoder`
+    pub fn from_talk_config(talk_config: &TalkConfig) -> Result<Self> {
+        if talk_config.audio_config.audio_type != "adpcm" {
+            return Err(Error::UnknownTalkEncoding);
+        }
+        let block_size = talk_config.audio_config.length_per_encoder as usize / 2;
+        if block_size == 0 {
+            return Err(Error::Other("The talk config has no ADPCM block size"));
+        }
+        Ok(Self::new(block_size))
+    }
+
+    /// The bytes of a whole block including its header
+    pub fn block_len(&self) -> usize {
+        self.block_size + BLOCK_HEADER_SIZE
+    }
+
+    /// The samples in each block. The first is stored in the header
+    pub fn samples_per_block(&self) -> usize {
+        self.block_size * 2 + 1
+    }
+
+    /// Encode the `samples` into whole blocks
+    ///
+    /// Samples that do not fill a block are kept for the next call or [`AdpcmEncoder::finish`]
+    pub fn encode(&mut self, samples: &[i16]) -> Vec<u8> {
+        self.pending.extend_from_slice(samples);
+        let per_block = self.samples_per_block();
+        let blocks = self.pending.len() / per_block;
+        let mut result = Vec::with_capacity(blocks * self.block_len());
+        for block in 0..blocks {
+            let start = block * per_block;
+            let samples = self.pending[start..start + per_block].to_vec();
+            self.encode_block(&samples, &mut result);
+        }
+        self.pending.drain(..blocks * per_block);
+        result
+    }
+
+    /// Encode the samples that are left, padding the last block with silence
+    pub fn finish(&mut self) -> Vec<u8> {
+        if self.pending.is_empty() {
+            return vec![];
+        }
+        let padding = self.samples_per_block() - self.pending.len();
+        self.encode(&vec![0; padding])
+    }
+
+    fn encode_block(&mut self, samples: &[i16], out: &mut Vec<u8>) {
+        let mut predictor = samples[0] as i32;
+        out.extend((predictor as i16).to_le_bytes());
+        out.push(self.index as u8);
+        out.push(0);
+
+        for pair in samples[1..].chunks(2) {
+            let high = self.encode_sample(&mut predictor, pair[0]);
+            let low = self.encode_sample(&mut predictor, pair[1]);
+            out.push(high << 4 | low);
+        }
+    }
+
+    fn encode_sample(&mut self, predictor: &mut i32, sample: i16) -> u8 {
+        let step = STEPS[self.index as usize];
+        let mut diff = sample as i32 - *predictor;
+        let mut nibble = 0;
+        if diff < 0 {
+            nibble = 0b1000;
+            diff = -diff;
+        }
+
+        // The same shifts as the decoder so that we track what it will hear
+        let mut delta = step >> 3;
+        if diff >= step {
+            nibble |= 0b0100;
+            diff -= step;
+            delta += step;
+        }
+        if diff >= step >> 1 {
+            nibble |= 0b0010;
+            diff -= step >> 1;
+            delta += step >> 1;
+        }
+        if diff >= step >> 2 {
+            nibble |= 0b0001;
+            delta += step >> 2;
+        }
+
+        if nibble & 0b1000 == 0b1000 {
+            *predictor -= delta;
+        } else {
+            *predictor += delta;
+        }
+        *predictor = (*predictor).clamp(i16::MIN as i32, i16::MAX as i32);
+        self.index = (self.index + CHANGES[nibble as usize]).clamp(0, STEPS.len() as i32 - 1);
+        nibble
+    }
+}
+
+/// Decode one DVI-4 IMA ADPCM block, such as the data of a [`crate::bcmedia::model::BcMediaAdpcm`],
+/// into 16 bit mono PCM
+///
+/// Each block starts with its own header so no state is kept between blocks
+pub fn decode_adpcm(block: &[u8]) -> Vec<i16> {
+    if block.len() < BLOCK_HEADER_SIZE {
+        return vec![];
+    }
+    let mut predictor = i16::from_le_bytes([block[0], block[1]]) as i32;
+    let mut index = (block[2] as i32).clamp(0, STEPS.len() as i32 - 1);
+    let mut result = Vec::with_capacity((block.len() - BLOCK_HEADER_SIZE) * 2 + 1);
+    result.push(predictor as i16);
+    for byte in &block[BLOCK_HEADER_SIZE..] {
+        for nibble in [byte >> 4, byte & 0x0f] {
+            let step = STEPS[index as usize];
+            let mut delta = step >> 3;
+            if nibble & 0b0100 != 0 {
+                delta += step;
+            }
+            if nibble & 0b0010 != 0 {
+                delta += step >> 1;
+            }
+            if nibble & 0b0001 != 0 {
+                delta += step >> 2;
+            }
+            if nibble & 0b1000 != 0 {
+                predictor -= delta;
+            } else {
+                predictor += delta;
+            }
+            predictor = predictor.clamp(i16::MIN as i32, i16::MAX as i32);
+            index = (index + CHANGES[nibble as usize]).clamp(0, STEPS.len() as i32 - 1);
+            result.push(predictor as i16);
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Decode the blocks in the same way as the camera
+    fn decode(adpcm: &[u8], block_len: usize) -> Vec<i16> {
+        adpcm.chunks(block_len).flat_map(decode_adpcm).collect()
+    }
+
+    #[test]
+    fn test_adpcm_round_trip() {
+        let samples = (0..16000)
+            .map(|i| ((i as f32 * 440.0 * std::f32::consts::TAU / 16000.0).sin() * 8000.0) as i16)
+            .collect::<Vec<_>>();
+        let mut encoder = AdpcmEncoder::new(512);
+        let mut adpcm = encoder.encode(&samples);
+        adpcm.extend(encoder.finish());
+        assert_eq!(adpcm.len() % encoder.block_len(), 0);
+
+        let decoded = decode(&adpcm, encoder.block_len());
+        assert_eq!(
+            decoded.len(),
+            adpcm.len() / encoder.block_len() * encoder.samples_per_block()
+        );
+        // Skip the start while the step size adapts
+        let error = samples
+            .iter()
+            .zip(decoded.iter())
+            .skip(100)
+            .map(|(a, b)| (*a as i32 - *b as i32).abs())
+            .max()
+            .unwrap();
+        assert!(error < 800, "The error was {}", error);
+    }
+}
@@ -0,0 +1,129 @@
+//! Measures the loudness of 16 bit PCM
+
+/// The loudness of some audio in dB relative to full scale
+///
+/// Full scale is 0dB and everything else is negative down to [`AudioLevel::SILENCE`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AudioLevel {
+    /// The root mean square of the samples, which is close to how loud it sounds
+    pub rms: f32,
+    /// The loudest sample
+    pub peak: f32,
+}
+
+impl AudioLevel {
+    /// The level of silence, which is about the quietest that 16 bit audio can be
+    pub const SILENCE: f32 = -96.0;
+
+    /// Measure the level of the `samples`
+    pub fn measure(samples: &[i16]) -> Self {
+        let mut meter = AudioLevelMeter::default();
+        meter.add(samples);
+        meter.level()
+    }
+}
+
+/// Measures the level of audio that arrives a bit at a time
+///
+/// Only the running sums are kept, so it does not grow with the audio
+#[derive(Debug, Clone, Default)]
+pub struct AudioLevelMeter {
+    sum_of_squares: f64,
+    count: u64,
+    peak: i32,
+}
+
+impl AudioLevelMeter {
+    /// Add the `samples` to the measurement
+    pub fn add(&mut self, samples: &[i16]) {
+        for sample in samples {
+            let sample = *sample as i32;
+            self.sum_of_squares += (sample as f64).powi(2);
+            self.peak = self.peak.max(sample.abs());
+        }
+        self.count += samples.len() as u64;
+    }
+
+    /// The level of all of the samples added so far
+    pub fn level(&self) -> AudioLevel {
+        if self.count == 0 {
+            return AudioLevel {
+                rms: AudioLevel::SILENCE,
+                peak: AudioLevel::SILENCE,
+            };
+        }
+        AudioLevel {
+            rms: to_db((self.sum_of_squares / self.count as f64).sqrt()),
+            peak: to_db(self.peak as f64),
+        }
+    }
+
+    /// The level of the samples added so far, starting again afterwards
+    pub fn take(&mut self) -> AudioLevel {
+        let level = self.level();
+        *self = Self::default();
+        level
+    }
+}
+
+/// The dB of an amplitude relative to the full scale of 16 bit audio
+fn to_db(amplitude: f64) -> f32 {
+    if amplitude <= 0.0 {
+        return AudioLevel::SILENCE;
+    }
+    ((20.0 * (amplitude / i16::MAX as f64).log10()) as f32).clamp(AudioLevel::SILENCE, 0.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_audio_level() {
+        let silence = AudioLevel::measure(&[0; 100]);
+        assert_eq!(silence.rms, AudioLevel::SILENCE);
+        assert_eq!(silence.peak, AudioLevel::SILENCE);
+
+        let full = AudioLevel::measure(&[i16::MAX, i16::MIN, i16::MAX, i16::MIN]);
+        assert!(full.rms.abs() < 0.01, "The RMS was {}", full.rms);
+        assert_eq!(full.peak, 0.0);
+
+        // A sine wave at half of full scale is 6dB down at its peak and 9dB down on average
+        let sine = (0..8000)
+            .map(|i| ((i as f32 * 440.0 * std::f32::consts::TAU / 8000.0).sin() * 16384.0) as i16)
+            .collect::<Vec<_>>();
+        let level = AudioLevel::measure(&sine);
+        assert!(
+            (level.peak + 6.0).abs() < 0.1,
+            "The peak was {}",
+            level.peak
+        );
+        assert!((level.rms + 9.0).abs() < 0.1, "The RMS was {}", level.rms);
+    }
+
+    #[test]
+    fn test_audio_level_meter() {
+        let samples = (0..8000)
+            .map(|i| ((i * 37 % 2000) as i16 - 1000) * 16)
+            .collect::<Vec<_>>();
+        let mut meter = AudioLevelMeter::default();
+        assert_eq!(meter.level(), AudioLevel::measure(&[]));
+        for chunk in samples.chunks(333) {
+            meter.add(chunk);
+        }
+        let level = meter.take();
+        let whole = AudioLevel::measure(&samples);
+        assert!(
+            (level.rms - whole.rms).abs() < 0.001,
+            "{:?} != {:?}",
+            level,
+            whole
+        );
+        assert_eq!(level.peak, whole.peak);
+
+        // Taking the level starts again
+        assert_eq!(meter.level().rms, AudioLevel::SILENCE);
+        meter.add(&[i16::MIN]);
+        assert_eq!(meter.level().peak, 0.0);
+    }
+}
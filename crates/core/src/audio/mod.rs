@@ -0,0 +1,48 @@
+//! Prepares audio for the talk of the camera without gstreamer
+//!
+//! A [`Wav`] is read, [`resample`]d to the `sampleRate` of the
+//! `<TalkConfig>` and encoded into ADPCM blocks with the [`AdpcmEncoder`]
+//!
+//! The ADPCM audio of the camera's streams can be decoded with [`decode_adpcm`]
+//! and its loudness measured with [`AudioLevel`], or with an [`AudioLevelMeter`]
+//! as it arrives
+mod adpcm;
+mod level;
+mod wav;
+
+pub use adpcm::{decode_adpcm, AdpcmEncoder, BLOCK_HEADER_SIZE};
+pub use level::{AudioLevel, AudioLevelMeter};
+pub use wav::Wav;
+
+/// Resample 16 bit mono audio from `from_rate` to `to_rate` by linear interpolation
+pub fn resample(samples: &[i16], from_rate: u32, to_rate: u32) -> Vec<i16> {
+    if from_rate == to_rate || samples.is_empty() || from_rate == 0 || to_rate == 0 {
+        return samples.to_vec();
+    }
+    let len = (samples.len() as u64 * to_rate as u64 / from_rate as u64) as usize;
+    let ratio = from_rate as f64 / to_rate as f64;
+    (0..len)
+        .map(|i| {
+            let at = i as f64 * ratio;
+            let index = at as usize;
+            let next = samples[(index + 1).min(samples.len() - 1)] as f64;
+            let current = samples[index] as f64;
+            (current + (next - current) * (at - index as f64)).round() as i16
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resample() {
+        let samples = [0, 100, 200, 300];
+        assert_eq!(
+            resample(&samples, 8000, 16000),
+            vec![0, 50, 100, 150, 200, 250, 300, 300]
+        );
+        assert_eq!(resample(&samples, 16000, 8000), vec![0, 200]);
+    }
+}
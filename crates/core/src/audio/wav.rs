@@ -0,0 +1,214 @@
+//! A reader of uncompressed WAV files
+use crate::{Error, Result};
+use std::io::Read;
+
+const FORMAT_PCM: u16 = 1;
+const FORMAT_FLOAT: u16 = 3;
+const FORMAT_EXTENSIBLE: u16 = 0xFFFE;
+/// The largest format chunk that is read, the extensible format is 40 bytes
+const MAX_FORMAT_SIZE: u32 = 1024;
+/// The data sizes of files that were streamed and did not know their size
+const UNKNOWN_SIZES: [u32; 2] = [0, u32::MAX];
+
+/// The audio of a WAV file mixed down to 16 bit mono
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Wav {
+    /// The samples per second
+    pub sample_rate: u32,
+    /// The samples with the channels averaged together
+    pub samples: Vec<i16>,
+}
+
+struct Format {
+    format: u16,
+    channels: u16,
+    sample_rate: u32,
+    bits: u16,
+}
+
+impl Wav {
+    /// Read a WAV file of 8, 16, 24 or 32 bit integer or 32 bit float samples
+    pub fn read<R: Read>(mut reader: R) -> Result<Self> {
+        let mut header = [0; 12];
+        reader.read_exact(&mut header)?;
+        if &header[0..4] != b"RIFF" || &header[8..12] != b"WAVE" {
+            return Err(Error::InvalidWav("This is not a WAV file".to_string()));
+        }
+
+        let mut format = None;
+        loop {
+            let mut chunk = [0; 8];
+            if let Err(e) = reader.read_exact(&mut chunk) {
+                return Err(match e.kind() {
+                    std::io::ErrorKind::UnexpectedEof => {
+                        Error::InvalidWav("There is no data chunk".to_string())
+                    }
+                    _ => e.into(),
+                });
+            }
+            let size = u32::from_le_bytes([chunk[4], chunk[5], chunk[6], chunk[7]]);
+            match &chunk[0..4] {
+                b"fmt " => {
+                    if size > MAX_FORMAT_SIZE {
+                        return Err(Error::InvalidWav(format!(
+                            "The format is {size} bytes, it should be less than {MAX_FORMAT_SIZE}"
+                        )));
+                    }
+                    let mut fmt = vec![0; size as usize + size as usize % 2];
+                    reader.read_exact(&mut fmt)?;
+                    format = Some(parse_format(&fmt)?);
+                }
+                b"data" => {
+                    let format = format.ok_or_else(|| {
+                        Error::InvalidWav("The data is before the format".to_string())
+                    })?;
+                    // Files that were streamed may not know the size so read to the end
+                    let mut data = vec![];
+                    if UNKNOWN_SIZES.contains(&size) {
+                        reader.read_to_end(&mut data)?;
+                    } else {
+                        reader.take(size as u64).read_to_end(&mut data)?;
+                    }
+                    return Ok(Self {
+                        sample_rate: format.sample_rate,
+                        samples: to_mono(&format, &data),
+                    });
+                }
+                _ => {
+                    let skip = size as u64 + size as u64 % 2;
+                    std::io::copy(&mut (&mut reader).take(skip), &mut std::io::sink())?;
+                }
+            }
+        }
+    }
+
+    /// Resample the audio to the `sample_rate`
+    pub fn resampled(self, sample_rate: u32) -> Self {
+        Self {
+            samples: super::resample(&self.samples, self.sample_rate, sample_rate),
+            sample_rate,
+        }
+    }
+}
+
+fn parse_format(fmt: &[u8]) -> Result<Format> {
+    if fmt.len() < 16 {
+        return Err(Error::InvalidWav("The format is too short".to_string()));
+    }
+    let u16_at = |at: usize| u16::from_le_bytes([fmt[at], fmt[at + 1]]);
+    let mut format = Format {
+        format: u16_at(0),
+        channels: u16_at(2),
+        sample_rate: u32::from_le_bytes([fmt[4], fmt[5], fmt[6], fmt[7]]),
+        bits: u16_at(14),
+    };
+    // The real format is the start of the sub format GUID
+    if format.format == FORMAT_EXTENSIBLE && fmt.len() >= 26 {
+        format.format = u16_at(24);
+    }
+
+    let supported = matches!(
+        (format.format, format.bits),
+        (FORMAT_PCM, 8)
+            | (FORMAT_PCM, 16)
+            | (FORMAT_PCM, 24)
+            | (FORMAT_PCM, 32)
+            | (FORMAT_FLOAT, 32)
+    );
+    if !supported {
+        return Err(Error::InvalidWav(format!(
+            "Only uncompressed WAV is supported, not format {} with {} bits",
+            format.format, format.bits
+        )));
+    }
+    if format.channels == 0 || format.sample_rate == 0 {
+        return Err(Error::InvalidWav(
+            "There are no channels or no sample rate".to_string(),
+        ));
+    }
+    Ok(format)
+}
+
+fn to_mono(format: &Format, data: &[u8]) -> Vec<i16> {
+    let width = format.bits as usize / 8;
+    let channels = format.channels as usize;
+    data.chunks_exact(width * channels)
+        .map(|frame| {
+            let sum = frame
+                .chunks_exact(width)
+                .map(|sample| match (format.format, width) {
+                    (FORMAT_FLOAT, _) => {
+                        let value =
+                            f32::from_le_bytes([sample[0], sample[1], sample[2], sample[3]]);
+                        (value.clamp(-1.0, 1.0) * i16::MAX as f32) as i32
+                    }
+                    (_, 1) => (sample[0] as i32 - 128) << 8,
+                    (_, 2) => i16::from_le_bytes([sample[0], sample[1]]) as i32,
+                    (_, 3) => i32::from_le_bytes([0, sample[0], sample[1], sample[2]]) >> 16,
+                    _ => i32::from_le_bytes([sample[0], sample[1], sample[2], sample[3]]) >> 16,
+                })
+                .sum::<i32>();
+            (sum / channels as i32) as i16
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A stereo 8 bit WAV with a `LIST` chunk before the format
+    fn stereo_8bit(fmt_size: u32, data_size: u32, data: &[u8]) -> Vec<u8> {
+        let mut wav = vec![];
+        wav.extend(b"RIFF");
+        wav.extend(0u32.to_le_bytes());
+        wav.extend(b"WAVE");
+        wav.extend(b"LIST");
+        wav.extend(3u32.to_le_bytes());
+        wav.extend([1, 2, 3, 0]);
+        wav.extend(b"fmt ");
+        wav.extend(fmt_size.to_le_bytes());
+        wav.extend(FORMAT_PCM.to_le_bytes());
+        wav.extend(2u16.to_le_bytes());
+        wav.extend(8000u32.to_le_bytes());
+        wav.extend(16000u32.to_le_bytes());
+        wav.extend(2u16.to_le_bytes());
+        wav.extend(8u16.to_le_bytes());
+        wav.extend(b"data");
+        wav.extend(data_size.to_le_bytes());
+        wav.extend(data);
+        wav
+    }
+
+    #[test]
+    fn test_wav_stereo_8bit() {
+        let wav = Wav::read(stereo_8bit(16, 4, &[128, 128, 255, 64]).as_slice()).unwrap();
+        assert_eq!(wav.sample_rate, 8000);
+        // The channels are averaged: ((127 << 8) + (-64 << 8)) / 2
+        assert_eq!(wav.samples, vec![0, 8064]);
+
+        // Anything after the data chunk is not audio
+        let mut file = stereo_8bit(16, 2, &[128, 128]);
+        file.extend(b"LIST");
+        let wav = Wav::read(file.as_slice()).unwrap();
+        assert_eq!(wav.samples, vec![0]);
+    }
+
+    #[test]
+    fn test_wav_unknown_size() {
+        for size in UNKNOWN_SIZES {
+            let wav = stereo_8bit(16, size, &[128, 128, 255, 64]);
+            let wav = Wav::read(wav.as_slice()).unwrap();
+            assert_eq!(wav.samples, vec![0, 8064]);
+        }
+    }
+
+    #[test]
+    fn test_wav_large_format() {
+        let wav = stereo_8bit(u32::MAX - 1, 4, &[128, 128, 255, 64]);
+        assert!(matches!(
+            Wav::read(wav.as_slice()),
+            Err(Error::InvalidWav(_))
+        ));
+    }
+}
@@ -40,14 +40,34 @@ pub const MSG_ID_GET_SERVICE_PORTS: u32 = 37;
 pub const MSG_ID_GET_EMAIL: u32 = 42;
 /// Set email settings
 pub const MSG_ID_SET_EMAIL: u32 = 43;
+/// Get the record config such as the pre and post record times
+pub const MSG_ID_GET_RECORD_CFG: u32 = 54;
+/// Set the record config
+pub const MSG_ID_SET_RECORD_CFG: u32 = 55;
 /// Get users and general system info
 pub const MSG_ID_GET_ABILITY_SUPPORT: u32 = 58;
 /// Update, create and remove users
 pub const MSG_ID_UPDATE_USER_LIST: u32 = 59;
+/// Get the FTP settings
+pub const MSG_ID_GET_FTP: u32 = 68;
+/// Set the FTP settings
+pub const MSG_ID_SET_FTP: u32 = 69;
+/// Get FTP Task
+pub const MSG_ID_GET_FTP_TASK: u32 = 70;
+/// Set FTP Task
+pub const MSG_ID_SET_FTP_TASK: u32 = 71;
 /// Version messages have this ID
 pub const MSG_ID_VERSION: u32 = 80;
+/// Get the record schedule
+pub const MSG_ID_GET_RECORD: u32 = 81;
+/// Set the record schedule
+pub const MSG_ID_SET_RECORD: u32 = 82;
 /// Ping messages have this ID
 pub const MSG_ID_PING: u32 = 93;
+/// Get the disks such as the SD card with their size and free space
+pub const MSG_ID_GET_HDD_INFO_LIST: u32 = 102;
+/// Format the disks
+pub const MSG_ID_HDD_INIT_LIST: u32 = 103;
 /// General system info messages have this ID
 pub const MSG_ID_GET_GENERAL: u32 = 104;
 /// Setting general system info (clock mostly) messages have this ID
@@ -66,6 +86,8 @@ pub const MSG_ID_STREAM_INFO_LIST: u32 = 146;
 pub const MSG_ID_ABILITY_INFO: u32 = 151;
 /// Get the available PTZ position presets
 pub const MSG_ID_GET_PTZ_PRESET: u32 = 190;
+/// Test the FTP settings by uploading a file
+pub const MSG_ID_TEST_FTP: u32 = 194;
 /// Get the support details (ptz, talk et)
 pub const MSG_ID_GET_SUPPORT: u32 = 199;
 /// Will send the talk config for talk back data to follow this msg
@@ -84,6 +106,10 @@ pub const MSG_ID_START_PIR_ALARM: u32 = 213;
 pub const MSG_ID_SET_EMAIL_TASK: u32 = 216;
 /// Get Email Task
 pub const MSG_ID_GET_EMAIL_TASK: u32 = 217;
+/// Set the sound detection of the camera
+pub const MSG_ID_SET_AUDIO_TASK: u32 = 231;
+/// Get the sound detection of the camera
+pub const MSG_ID_GET_AUDIO_TASK: u32 = 232;
 /// UDP Keep alive
 pub const MSG_ID_UDP_KEEP_ALIVE: u32 = 234;
 /// Battery message initiaed by the camera
@@ -92,6 +118,10 @@ pub const MSG_ID_BATTERY_INFO_LIST: u32 = 252;
 pub const MSG_ID_BATTERY_INFO: u32 = 253;
 /// Used for to play sounds like the siren
 pub const MSG_ID_PLAY_AUDIO: u32 = 263;
+/// Used to set the audio that the alarm plays and its volume
+pub const MSG_ID_SET_AUDIO_CFG: u32 = 264;
+/// Used to get the audio that the alarm plays, the same ID as setting it but without a payload
+pub const MSG_ID_GET_AUDIO_CFG: u32 = 264;
 /// Manual Floodlight Control
 pub const MSG_ID_FLOODLIGHT_MANUAL: u32 = 288;
 /// Set Floodlight tasks xml
@@ -114,6 +114,9 @@ pub struct BcXml {
     /// Play a sound
     #[serde(rename = "audioPlayInfo", skip_serializing_if = "Option::is_none")]
     pub audio_play_info: Option<AudioPlayInfo>,
+    /// The audio that the alarm plays
+    #[serde(rename = "audioCfg", skip_serializing_if = "Option::is_none")]
+    pub audio_cfg: Option<AudioCfg>,
     /// For changing baichaun server port
     #[serde(rename = "ServerPort", skip_serializing_if = "Option::is_none")]
     pub server_port: Option<ServerPort>,
@@ -138,6 +141,27 @@ pub struct BcXml {
     /// EmailTask for turning the email notifications on/off
     #[serde(rename = "EmailTask", skip_serializing_if = "Option::is_none")]
     pub email_task: Option<EmailTask>,
+    /// Ftp settings for uploading the recordings
+    #[serde(rename = "Ftp", skip_serializing_if = "Option::is_none")]
+    pub ftp: Option<Ftp>,
+    /// FtpTask for turning the FTP uploads on/off
+    #[serde(rename = "FtpTask", skip_serializing_if = "Option::is_none")]
+    pub ftp_task: Option<FtpTask>,
+    /// RecordCfg for the pre and post record times and overwriting
+    #[serde(rename = "RecordCfg", skip_serializing_if = "Option::is_none")]
+    pub record_cfg: Option<RecordCfg>,
+    /// Record for turning the recording on/off and its schedule
+    #[serde(rename = "Record", skip_serializing_if = "Option::is_none")]
+    pub record: Option<Record>,
+    /// The disks of the camera such as the SD card
+    #[serde(rename = "HddInfoList", skip_serializing_if = "Option::is_none")]
+    pub hdd_info_list: Option<HddInfoList>,
+    /// The disks to format
+    #[serde(rename = "HddInitList", skip_serializing_if = "Option::is_none")]
+    pub hdd_init_list: Option<HddInitList>,
+    /// AudioTask for the sound detection of the camera
+    #[serde(rename = "AudioTask", skip_serializing_if = "Option::is_none")]
+    pub audio_task: Option<AudioTask>,
     /// Read and write users
     #[serde(rename = "UserList", skip_serializing_if = "Option::is_none")]
     pub user_list: Option<UserList>,
@@ -555,9 +579,9 @@ pub struct AudioConfig {
     /// Unknown only sent during TalkAbility request from the camera
     #[serde(skip_serializing_if = "Option::is_none")]
     pub priority: Option<u32>,
-    /// Audio type known values are `"adpcm"`
+    /// Audio type known values are `"adpcm"` and `"aac"`
     ///
-    /// Do not expect camera to support anything else.
+    /// See [`crate::bc_protocol::TalkEncoding`] for the ones that can be sent
     #[serde(rename = "audioType")]
     pub audio_type: String,
     /// Audio sample rate known values are `16000`
@@ -636,7 +660,8 @@ pub struct AlarmEvent {
     /// The channel the event occured on. Usually zero unless from an NVR
     #[serde(rename = "channelId")]
     pub channel_id: u8,
-    /// Motion status. Known values are `"MD"` or `"none"`
+    /// Motion status. Known values are `"MD"`, `"visitor"`, `"audio"` or `"none"`.
+    /// Doorbells send several of these joined by commas such as `"MD,visitor"`
     pub status: String,
     /// AI status. Known values are `"people"` or `"none"`
     #[serde(rename = "AItype", skip_serializing_if = "Option::is_none")]
@@ -648,6 +673,33 @@ pub struct AlarmEvent {
     pub timeStamp: i32,
 }
 
+impl AlarmEvent {
+    /// The values of the status such as `"MD"` or `"visitor"`
+    fn statuses(&self) -> impl Iterator<Item = &str> {
+        self.status
+            .split(',')
+            .map(str::trim)
+            .filter(|status| !status.is_empty() && *status != "none")
+    }
+
+    /// If the event is for motion, which is any status other than `"visitor"`
+    /// or `"audio"`
+    pub fn is_motion(&self) -> bool {
+        self.statuses()
+            .any(|status| status != "visitor" && status != "audio")
+    }
+
+    /// If a visitor pressed the button of a doorbell
+    pub fn is_visitor(&self) -> bool {
+        self.statuses().any(|status| status == "visitor")
+    }
+
+    /// If the sound detection of the camera heard something
+    pub fn is_sound(&self) -> bool {
+        self.statuses().any(|status| status == "audio")
+    }
+}
+
 /// The Ptz messages used to move the camera
 #[derive(PartialEq, Default, Debug, Deserialize, Serialize)]
 pub struct PtzControl {
@@ -1404,6 +1456,46 @@ pub struct AudioPlayInfo {
     pub on_off: u32,
 }
 
+/// The audio that the alarm plays and how loud it is
+#[derive(PartialEq, Eq, Default, Debug, Deserialize, Serialize, Clone)]
+pub struct AudioCfg {
+    /// XML Version
+    #[serde(rename = "@version")]
+    pub version: String,
+    /// Channel ID
+    #[serde(rename = "channelId")]
+    pub channel_id: u8,
+    /// Timeout: 0
+    pub timeout: u32,
+    /// Which audio to play. Known values are `0` for the built in audio
+    /// and `1` for the custom audio recorded in the app
+    #[serde(rename = "audioSelect")]
+    pub audio_select: u32,
+    /// The volume from 0 to 100
+    pub volume: u32,
+    /// Pre Alarm: 0
+    #[serde(rename = "preAlarm")]
+    pub pre_alarm: u32,
+    /// Pause Alarm: 0
+    #[serde(rename = "pauseAlarm")]
+    pub pause_alarm: u32,
+    /// Pause Type: 0
+    #[serde(rename = "pauseType")]
+    pub pause_type: u32,
+    /// Pause Start Time: 0
+    #[serde(rename = "pauseStartTime")]
+    pub pause_start_time: u32,
+    /// Pause Time: 0
+    #[serde(rename = "pauseTime")]
+    pub pause_time: u32,
+    /// The built in audio to play when `audio_select` is `0`
+    #[serde(rename = "audioListId")]
+    pub audio_list_id: u32,
+    /// Linkage Control: 1
+    #[serde(rename = "linkageCtrlEnable")]
+    pub linkage_ctrl_enable: u32,
+}
+
 /// Server port for baichaun defaults 9000
 #[derive(PartialEq, Eq, Default, Debug, Deserialize, Serialize)]
 pub struct ServerPort {
@@ -1550,6 +1642,59 @@ pub struct EmailTask {
     pub schedule_list: Option<ScheduleList>,
 }
 
+/// FTP settings for uploading the recordings and snapshots
+#[derive(PartialEq, Eq, Default, Debug, Deserialize, Serialize, Clone)]
+pub struct Ftp {
+    /// XML Version
+    #[serde(rename = "@version")]
+    pub version: String,
+    /// FTP server address
+    pub server: String,
+    /// FTP port usually `21`
+    pub port: u16,
+    /// `1` to log in without a username and password
+    pub anonymous: u8,
+    /// FTP username
+    #[serde(rename = "userName")]
+    pub user_name: String,
+    /// FTP password
+    pub password: String,
+    /// The directory on the server to upload into
+    #[serde(rename = "remoteDir")]
+    pub remote_dir: String,
+    /// What to upload such as `picture`, `video` or `pictureAndVideo`,
+    /// not sent by all cameras
+    #[serde(rename = "uploadType", skip_serializing_if = "Option::is_none")]
+    pub upload_type: Option<String>,
+    /// `1` for passive mode, not sent by all cameras
+    #[serde(rename = "transportMode", skip_serializing_if = "Option::is_none")]
+    pub transport_mode: Option<u8>,
+    /// The stream that is uploaded such as `mainStream` or `subStream`,
+    /// not sent by all cameras
+    #[serde(rename = "streamType", skip_serializing_if = "Option::is_none")]
+    pub stream_type: Option<String>,
+    /// Seconds between the uploads of the same alarm, not sent by all cameras
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub interval: Option<u32>,
+}
+
+/// FtpTask settings that controls the times/enables the FTP uploads
+#[derive(PartialEq, Eq, Default, Debug, Deserialize, Serialize)]
+pub struct FtpTask {
+    /// XML Version
+    #[serde(rename = "@version")]
+    pub version: String,
+    /// Channel number
+    #[serde(rename = "channelId")]
+    pub channel_id: u8,
+    /// 1 for enable 0 for disable
+    #[serde(rename = "enable")]
+    pub enable: u8,
+    /// The list of schedule to turn on/off the FTP uploads
+    #[serde(rename = "ScheduleList", skip_serializing_if = "Option::is_none")]
+    pub schedule_list: Option<ScheduleList>,
+}
+
 /// List of schedule items for turning on/off the notifications
 #[derive(PartialEq, Eq, Default, Debug, Deserialize, Serialize)]
 pub struct ScheduleList {
@@ -1558,10 +1703,19 @@ pub struct ScheduleList {
     pub schedule: Schedule,
 }
 
+/// List of the schedules for the recording, one for each alarm type
+#[derive(PartialEq, Eq, Default, Debug, Deserialize, Serialize)]
+pub struct RecordScheduleList {
+    /// The schedules such as `MD` for on motion and `none` for always
+    #[serde(default, rename = "Schedule")]
+    pub schedule: Vec<Schedule>,
+}
+
 /// Schedule item for turning on/off the notifications
 #[derive(PartialEq, Eq, Default, Debug, Deserialize, Serialize)]
 pub struct Schedule {
-    /// The alarm type. Observed values: `MD`
+    /// The alarm type. Observed values: `MD` and `none` which is used by
+    /// the record schedule for recording all the time
     #[serde(rename = "alarmType")]
     pub alarm_type: String,
     /// The list of time blocks
@@ -1569,6 +1723,150 @@ pub struct Schedule {
     pub time_block_list: TimeBlockList,
 }
 
+impl Schedule {
+    /// The days of the week as the camera names them
+    pub const WEEK_DAYS: [&'static str; 7] = [
+        "Sunday",
+        "Monday",
+        "Tuesday",
+        "Wednesday",
+        "Thursday",
+        "Friday",
+        "Saturday",
+    ];
+
+    /// A schedule of the `alarm_type` for every hour of the week
+    pub fn all_week(alarm_type: &str) -> Self {
+        Self {
+            alarm_type: alarm_type.to_owned(),
+            time_block_list: TimeBlockList {
+                time_block: Self::WEEK_DAYS
+                    .iter()
+                    .map(|day| TimeBlock {
+                        enable: 1,
+                        week_day: day.to_string(),
+                        begin_hour: 0,
+                        end_hour: 23,
+                    })
+                    .collect(),
+            },
+        }
+    }
+}
+
+/// RecordCfg settings of the recordings on the SD card
+#[derive(PartialEq, Eq, Default, Debug, Deserialize, Serialize)]
+pub struct RecordCfg {
+    /// XML Version
+    #[serde(rename = "@version")]
+    pub version: String,
+    /// Channel number
+    #[serde(rename = "channelId", skip_serializing_if = "Option::is_none")]
+    pub channel_id: Option<u8>,
+    /// 1 to overwrite the oldest recordings when the disk is full
+    pub cycle: u8,
+    /// Seconds to keep recording after the motion stops
+    #[serde(rename = "recordDelayTime")]
+    pub record_delay_time: u32,
+    /// Seconds to record before the motion starts
+    #[serde(rename = "preRecordTime")]
+    pub pre_record_time: u32,
+    /// The length of each recorded file in minutes
+    #[serde(rename = "packageTime")]
+    pub package_time: u32,
+    /// The stream that is recorded such as `"mainStream"` or `"subStream"`,
+    /// not sent by all cameras
+    #[serde(rename = "streamType", skip_serializing_if = "Option::is_none")]
+    pub stream_type: Option<String>,
+}
+
+/// Record settings that control the times/enables the recording
+#[derive(PartialEq, Eq, Default, Debug, Deserialize, Serialize)]
+pub struct Record {
+    /// XML Version
+    #[serde(rename = "@version")]
+    pub version: String,
+    /// Channel number
+    #[serde(rename = "channelId")]
+    pub channel_id: u8,
+    /// 1 for enable 0 for disable
+    #[serde(rename = "enable")]
+    pub enable: u8,
+    /// The schedule of when to record, `MD` for on motion and `none` for always
+    #[serde(rename = "ScheduleList", skip_serializing_if = "Option::is_none")]
+    pub schedule_list: Option<RecordScheduleList>,
+}
+
+/// The list of disks of the camera, empty when there is no SD card
+#[derive(PartialEq, Eq, Default, Debug, Deserialize, Serialize)]
+pub struct HddInfoList {
+    /// XML Version
+    #[serde(rename = "@version")]
+    pub version: String,
+    /// The disks
+    #[serde(default, rename = "HddInfo")]
+    pub hdd_info: Vec<HddInfo>,
+}
+
+/// A disk of the camera such as the SD card
+#[derive(PartialEq, Eq, Default, Debug, Deserialize, Serialize)]
+pub struct HddInfo {
+    /// The number of the disk, starting at `0`
+    pub number: u8,
+    /// The size of the disk in MB
+    pub capacity: u64,
+    /// The free space of the disk in MB
+    #[serde(rename = "remainSize")]
+    pub remain_size: u64,
+    /// `1` if the disk is mounted and can be used
+    pub mount: u8,
+    /// `1` if the disk is formatted, not sent by all cameras
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub format: Option<u8>,
+    /// The kind of disk such as `"sd"` or `"hdd"`, not sent by all cameras
+    #[serde(rename = "storageType", skip_serializing_if = "Option::is_none")]
+    pub storage_type: Option<String>,
+}
+
+/// The list of disks to format
+#[derive(PartialEq, Eq, Default, Debug, Deserialize, Serialize)]
+pub struct HddInitList {
+    /// XML Version
+    #[serde(rename = "@version")]
+    pub version: String,
+    /// The disks to format
+    #[serde(default, rename = "HddInit")]
+    pub hdd_init: Vec<HddInit>,
+}
+
+/// A disk to format
+#[derive(PartialEq, Eq, Default, Debug, Deserialize, Serialize)]
+pub struct HddInit {
+    /// The number of the disk from [`HddInfo::number`]
+    #[serde(rename = "initId")]
+    pub init_id: u8,
+}
+
+/// AudioTask settings that control the sound detection of the camera
+#[derive(PartialEq, Eq, Default, Debug, Deserialize, Serialize)]
+pub struct AudioTask {
+    /// XML Version
+    #[serde(rename = "@version")]
+    pub version: String,
+    /// Channel number
+    #[serde(rename = "channelId")]
+    pub channel_id: u8,
+    /// 1 for enable 0 for disable
+    #[serde(rename = "enable")]
+    pub enable: u8,
+    /// How easily a sound raises the alarm from 1 to 100, not sent by all cameras
+    #[serde(rename = "sensitivity", skip_serializing_if = "Option::is_none")]
+    pub sensitivity: Option<u8>,
+    /// The list of schedule to turn on/off the sound detection
+    #[serde(rename = "ScheduleList", skip_serializing_if = "Option::is_none")]
+    pub schedule_list: Option<ScheduleList>,
+}
+
 /// List of users
 #[derive(PartialEq, Eq, Default, Debug, Deserialize, Serialize)]
 pub struct UserList {
@@ -1964,3 +2262,240 @@ fn test_empty_floodlight_status_list() {
         _ => panic!(),
     }
 }
+
+#[test]
+fn test_doorbell_visitor() {
+    let _ = env_logger::builder().is_test(true).try_init();
+    let sample = indoc!(
+        r#"<?xml version="1.0" encoding="UTF-8" ?>
+        <body>
+        <AlarmEventList version="1.1">
+        <AlarmEvent version="1.1">
+        <channelId>0</channelId>
+        <status>MD,visitor</status>
+        <AItype>none</AItype>
+        <recording>0</recording>
+        <timeStamp>0</timeStamp>
+        </AlarmEvent>
+        </AlarmEventList>
+        </body>
+        "#
+    );
+    let b = BcXml::try_parse(sample.as_bytes()).unwrap();
+    let mut event = b.alarm_event_list.unwrap().alarm_events.remove(0);
+    assert!(event.is_motion());
+    assert!(event.is_visitor());
+
+    event.status = "visitor".to_string();
+    assert!(!event.is_motion());
+    assert!(event.is_visitor());
+
+    event.status = "none".to_string();
+    assert!(!event.is_motion());
+    assert!(!event.is_visitor());
+}
+
+#[test]
+fn test_audio_task() {
+    let _ = env_logger::builder().is_test(true).try_init();
+    let sample = indoc!(
+        r#"<?xml version="1.0" encoding="UTF-8" ?>
+        <body>
+        <AudioTask version="1.1">
+        <channelId>0</channelId>
+        <enable>0</enable>
+        <ScheduleList>
+        <Schedule>
+        <alarmType>MD</alarmType>
+        <timeBlockList>
+        <timeBlock>
+        <enable>1</enable>
+        <weekDay>Sunday</weekDay>
+        <beginHour>0</beginHour>
+        <endHour>23</endHour>
+        </timeBlock>
+        </timeBlockList>
+        </Schedule>
+        </ScheduleList>
+        </AudioTask>
+        </body>
+        "#
+    );
+    let b = BcXml::try_parse(sample.as_bytes()).unwrap();
+    let audio_task = b.audio_task.unwrap();
+    assert_eq!(audio_task.enable, 0);
+    assert_eq!(audio_task.sensitivity, None);
+    let schedule = audio_task.schedule_list.unwrap().schedule;
+    assert_eq!(schedule.time_block_list.time_block.len(), 1);
+    assert_eq!(schedule.time_block_list.time_block[0].week_day, "Sunday");
+}
+
+#[test]
+fn test_audio_cfg() {
+    let _ = env_logger::builder().is_test(true).try_init();
+    let sample = indoc!(
+        r#"<?xml version="1.0" encoding="UTF-8" ?>
+        <body>
+        <audioCfg version="1.1">
+        <channelId>0</channelId>
+        <timeout>0</timeout>
+        <audioSelect>1</audioSelect>
+        <volume>70</volume>
+        <preAlarm>0</preAlarm>
+        <pauseAlarm>1</pauseAlarm>
+        <pauseType>2</pauseType>
+        <pauseStartTime>1700000000</pauseStartTime>
+        <pauseTime>3600</pauseTime>
+        <audioListId>3</audioListId>
+        <linkageCtrlEnable>1</linkageCtrlEnable>
+        </audioCfg>
+        </body>
+        "#
+    );
+
+    let b = BcXml {
+        audio_cfg: Some(AudioCfg {
+            version: "1.1".to_string(),
+            channel_id: 0,
+            timeout: 0,
+            audio_select: 1,
+            volume: 70,
+            pre_alarm: 0,
+            pause_alarm: 1,
+            pause_type: 2,
+            pause_start_time: 1700000000,
+            pause_time: 3600,
+            audio_list_id: 3,
+            linkage_ctrl_enable: 1,
+        }),
+        ..BcXml::default()
+    };
+
+    let b2 = BcXml::try_parse(sample.as_bytes()).unwrap();
+    let b3 = BcXml::try_parse(b.serialize(vec![]).unwrap().as_ref()).unwrap();
+    assert_eq!(b, b2);
+    assert_eq!(b, b3);
+}
+
+#[test]
+fn test_sound_alarm() {
+    let _ = env_logger::builder().is_test(true).try_init();
+    let event = AlarmEvent {
+        status: "audio".to_string(),
+        ..Default::default()
+    };
+    assert!(!event.is_motion());
+    assert!(event.is_sound());
+
+    let event = AlarmEvent {
+        status: "MD,audio".to_string(),
+        ..Default::default()
+    };
+    assert!(event.is_motion());
+    assert!(event.is_sound());
+}
+
+#[test]
+fn test_schedule_all_week() {
+    let schedule = Schedule::all_week("MD");
+    assert_eq!(schedule.alarm_type, "MD");
+    let blocks = schedule.time_block_list.time_block;
+    assert_eq!(
+        blocks
+            .iter()
+            .map(|block| block.week_day.as_str())
+            .collect::<Vec<_>>(),
+        Schedule::WEEK_DAYS
+    );
+    assert!(blocks
+        .iter()
+        .all(|block| block.enable == 1 && block.begin_hour == 0 && block.end_hour == 23));
+}
+
+#[test]
+fn test_record_schedule() {
+    let _ = env_logger::builder().is_test(true).try_init();
+    let sample = indoc!(
+        r#"<?xml version="1.0" encoding="UTF-8" ?>
+        <body>
+        <Record version="1.1">
+        <channelId>0</channelId>
+        <enable>1</enable>
+        <ScheduleList>
+        <Schedule>
+        <alarmType>MD</alarmType>
+        <timeBlockList>
+        <timeBlock>
+        <enable>1</enable>
+        <weekDay>Tuesday</weekDay>
+        <beginHour>0</beginHour>
+        <endHour>12</endHour>
+        </timeBlock>
+        <timeBlock>
+        <enable>1</enable>
+        <weekDay>Tuesday</weekDay>
+        <beginHour>14</beginHour>
+        <endHour>23</endHour>
+        </timeBlock>
+        </timeBlockList>
+        </Schedule>
+        <Schedule>
+        <alarmType>none</alarmType>
+        <timeBlockList>
+        <timeBlock>
+        <enable>1</enable>
+        <weekDay>Tuesday</weekDay>
+        <beginHour>13</beginHour>
+        <endHour>13</endHour>
+        </timeBlock>
+        </timeBlockList>
+        </Schedule>
+        </ScheduleList>
+        </Record>
+        </body>
+        "#
+    );
+    let b = BcXml::try_parse(sample.as_bytes()).unwrap();
+    let record = b.record.unwrap();
+    assert_eq!(record.enable, 1);
+    let schedules = record.schedule_list.unwrap().schedule;
+    assert_eq!(schedules.len(), 2);
+    assert_eq!(schedules[0].alarm_type, "MD");
+    assert_eq!(schedules[0].time_block_list.time_block.len(), 2);
+    assert_eq!(schedules[1].alarm_type, "none");
+}
+
+#[test]
+fn test_hdd_info_list() {
+    let _ = env_logger::builder().is_test(true).try_init();
+    let sample = indoc!(
+        r#"<?xml version="1.0" encoding="UTF-8" ?>
+        <body>
+        <HddInfoList version="1.1" />
+        </body>
+        "#
+    );
+    let b = BcXml::try_parse(sample.as_bytes()).unwrap();
+    assert!(b.hdd_info_list.unwrap().hdd_info.is_empty());
+
+    let sample = indoc!(
+        r#"<?xml version="1.0" encoding="UTF-8" ?>
+        <body>
+        <HddInfoList version="1.1">
+        <HddInfo>
+        <number>0</number>
+        <capacity>30436</capacity>
+        <remainSize>12016</remainSize>
+        <mount>1</mount>
+        </HddInfo>
+        </HddInfoList>
+        </body>
+        "#
+    );
+    let b = BcXml::try_parse(sample.as_bytes()).unwrap();
+    let hdd_info = b.hdd_info_list.unwrap().hdd_info;
+    assert_eq!(hdd_info.len(), 1);
+    assert_eq!(hdd_info[0].capacity, 30436);
+    assert_eq!(hdd_info[0].remain_size, 12016);
+    assert_eq!(hdd_info[0].format, None);
+}
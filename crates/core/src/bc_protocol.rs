@@ -16,9 +16,11 @@ mod abilityinfo;
 mod battery;
 mod connection;
 mod credentials;
+mod disk;
 mod email;
 mod errors;
 mod floodlight;
+mod ftp;
 mod keepalive;
 mod ledstate;
 mod link;
@@ -30,10 +32,12 @@ mod pirstate;
 mod ptz;
 mod pushinfo;
 mod reboot;
+mod record;
 mod resolution;
 mod services;
 mod siren;
 mod snap;
+mod soundalarm;
 mod stream;
 mod stream_info;
 mod support;
@@ -45,16 +49,20 @@ mod version;
 
 pub(crate) use connection::*;
 pub use credentials::*;
+pub use disk::DiskState;
 pub use errors::Error;
 pub use ledstate::LightState;
 pub use login::MaxEncryption;
-pub use motion::{MotionData, MotionStatus};
+pub use motion::{MotionData, MotionStatus, SoundStatus, VisitorStatus};
 pub use pirstate::PirState;
 pub use ptz::Direction;
 pub use pushinfo::PhoneType;
+pub use record::RecordTrigger;
 pub use resolution::*;
+pub use siren::AudioPlay;
 use std::sync::Arc;
 pub use stream::{StreamData, StreamKind};
+pub use talk::TalkEncoding;
 
 pub(crate) type Result<T> = std::result::Result<T, Error>;
 
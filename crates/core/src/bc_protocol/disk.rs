@@ -0,0 +1,208 @@
+//! Handles the disks of the camera such as the SD card
+//!
+//! There are two messages:
+//! - HddInfoList which has the size, free space and state of each disk
+//! - HddInitList which formats the disks
+//!
+use super::{BcCamera, Error, Result};
+use crate::bc::{model::*, xml::*};
+
+/// The state of a disk of the camera
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DiskState {
+    /// The disk is mounted and can record
+    Ok,
+    /// The disk needs to be formatted before it can be used
+    Unformatted,
+    /// The disk is in the camera but is not mounted, it may be failing
+    NotMounted,
+}
+
+impl DiskState {
+    /// The name of the state as used on MQTT
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DiskState::Ok => "ok",
+            DiskState::Unformatted => "unformatted",
+            DiskState::NotMounted => "not_mounted",
+        }
+    }
+}
+
+impl HddInfo {
+    /// The state of the disk
+    pub fn state(&self) -> DiskState {
+        if self.format == Some(0) {
+            DiskState::Unformatted
+        } else if self.mount == 0 {
+            DiskState::NotMounted
+        } else {
+            DiskState::Ok
+        }
+    }
+
+    /// The free space as a percentage of the size
+    pub fn free_percent(&self) -> f32 {
+        if self.capacity == 0 {
+            0.0
+        } else {
+            (self.remain_size as f64 / self.capacity as f64 * 100.0) as f32
+        }
+    }
+}
+
+impl BcCamera {
+    async fn has_disk(&self, requested: &str) -> Result<()> {
+        let support = self.get_support().await?;
+        if support.disk_num.unwrap_or(0) == 0 {
+            return Err(Error::MissingAbility {
+                name: "diskNum".to_string(),
+                requested: requested.to_string(),
+                actual: "none".to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Get the [HddInfoList] xml which has the size, free space and state
+    /// of each disk
+    pub async fn get_hdd_info_list(&self) -> Result<HddInfoList> {
+        self.has_disk("read").await?;
+        let connection = self.get_connection();
+        let msg_num = self.new_message_num();
+        let mut sub_get = connection
+            .subscribe(MSG_ID_GET_HDD_INFO_LIST, msg_num)
+            .await?;
+        let get = Bc {
+            meta: BcMeta {
+                msg_id: MSG_ID_GET_HDD_INFO_LIST,
+                channel_id: self.channel_id,
+                msg_num,
+                response_code: 0,
+                stream_type: 0,
+                class: 0x6414,
+            },
+            body: BcBody::ModernMsg(ModernMsg {
+                extension: None,
+                payload: None,
+            }),
+        };
+
+        sub_get.send(get).await?;
+        let msg = sub_get.recv().await?;
+        if msg.meta.response_code != 200 {
+            return Err(Error::CameraServiceUnavailable {
+                id: msg.meta.msg_id,
+                code: msg.meta.response_code,
+            });
+        }
+
+        if let BcBody::ModernMsg(ModernMsg {
+            payload:
+                Some(BcPayloads::BcXml(BcXml {
+                    hdd_info_list: Some(hdd_info_list),
+                    ..
+                })),
+            ..
+        }) = msg.body
+        {
+            Ok(hdd_info_list)
+        } else {
+            Err(Error::UnintelligibleReply {
+                reply: std::sync::Arc::new(Box::new(msg)),
+                why: "Expected HddInfoList xml but it was not recieved",
+            })
+        }
+    }
+
+    /// Format a disk, this erases all of its recordings
+    ///
+    /// The disk must be one of [`BcCamera::get_hdd_info_list`] so that a
+    /// typo cannot format some other disk of an NVR
+    pub async fn format_disk(&self, number: u8) -> Result<()> {
+        self.has_disk("write").await?;
+        let hdd_info_list = self.get_hdd_info_list().await?;
+        if !hdd_info_list
+            .hdd_info
+            .iter()
+            .any(|hdd_info| hdd_info.number == number)
+        {
+            return Err(Error::UnknownDisk(number));
+        }
+
+        let connection = self.get_connection();
+        let msg_num = self.new_message_num();
+        let mut sub_set = connection.subscribe(MSG_ID_HDD_INIT_LIST, msg_num).await?;
+        let set = Bc {
+            meta: BcMeta {
+                msg_id: MSG_ID_HDD_INIT_LIST,
+                channel_id: self.channel_id,
+                msg_num,
+                response_code: 0,
+                stream_type: 0,
+                class: 0x6414,
+            },
+            body: BcBody::ModernMsg(ModernMsg {
+                extension: None,
+                payload: Some(BcPayloads::BcXml(BcXml {
+                    hdd_init_list: Some(HddInitList {
+                        version: xml_ver(),
+                        hdd_init: vec![HddInit { init_id: number }],
+                    }),
+                    ..Default::default()
+                })),
+            }),
+        };
+
+        sub_set.send(set).await?;
+        let msg = sub_set.recv().await?;
+        if msg.meta.response_code != 200 {
+            return Err(Error::CameraServiceUnavailable {
+                id: msg.meta.msg_id,
+                code: msg.meta.response_code,
+            });
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn disk(capacity: u64, remain_size: u64, mount: u8, format: Option<u8>) -> HddInfo {
+        HddInfo {
+            capacity,
+            remain_size,
+            mount,
+            format,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_free_percent() {
+        assert_eq!(disk(30436, 12016, 1, None).free_percent(), 39.479565);
+        assert_eq!(disk(1000, 1000, 1, None).free_percent(), 100.0);
+        assert_eq!(disk(1000, 0, 1, None).free_percent(), 0.0);
+        // Unmounted disks report no size
+        assert_eq!(disk(0, 0, 0, None).free_percent(), 0.0);
+        // The sizes are in MB so large disks do not overflow
+        assert_eq!(disk(u64::MAX, u64::MAX / 2, 1, None).free_percent(), 50.0);
+    }
+
+    #[test]
+    fn test_disk_state() {
+        assert_eq!(disk(1000, 500, 1, None).state(), DiskState::Ok);
+        assert_eq!(disk(1000, 500, 1, Some(1)).state(), DiskState::Ok);
+        assert_eq!(disk(0, 0, 0, None).state(), DiskState::NotMounted);
+        assert_eq!(disk(0, 0, 0, Some(1)).state(), DiskState::NotMounted);
+        // Unformatted is reported before not mounted as it is what to fix
+        assert_eq!(disk(0, 0, 0, Some(0)).state(), DiskState::Unformatted);
+        assert_eq!(disk(1000, 500, 1, Some(0)).state(), DiskState::Unformatted);
+
+        assert_eq!(DiskState::Ok.as_str(), "ok");
+        assert_eq!(DiskState::Unformatted.as_str(), "unformatted");
+        assert_eq!(DiskState::NotMounted.as_str(), "not_mounted");
+    }
+}
@@ -229,34 +229,12 @@ impl BcCamera {
 
     /// Turn on Email notifications all the time
     pub async fn email_on_always(&self) -> Result<()> {
-        const DOW: [&str; 7] = [
-            "Sunday",
-            "Monday",
-            "Tuesday",
-            "Wednesday",
-            "Thursday",
-            "Friday",
-            "Saturday",
-        ];
         self.set_email_task(EmailTask {
             version: xml_ver(),
             channel_id: self.channel_id,
             enable: 1,
             schedule_list: Some(ScheduleList {
-                schedule: Schedule {
-                    alarm_type: "MD".to_owned(),
-                    time_block_list: TimeBlockList {
-                        time_block: DOW
-                            .iter()
-                            .map(|d| TimeBlock {
-                                enable: 1,
-                                week_day: d.to_string(),
-                                begin_hour: 0,
-                                end_hour: 23,
-                            })
-                            .collect(),
-                    },
-                },
+                schedule: Schedule::all_week("MD"),
             }),
         })
         .await
@@ -132,10 +132,14 @@ pub enum Error {
     #[error("Failed to translate camera address")]
     AddrResolutionError,
 
-    /// Raised non adpcm data is sent to the talk command
-    #[error("Talk data is not ADPCM")]
+    /// Raised when the talk config has an audio type that cannot be sent
+    #[error("Talk data is not ADPCM or AAC")]
     UnknownTalkEncoding,
 
+    /// Raised when a WAV file cannot be read
+    #[error("Invalid WAV: {0}")]
+    InvalidWav(String),
+
     /// Raised when dicovery times out waiting for a reply
     #[error("Timed out while waiting for camera reply")]
     DiscoveryTimeout,
@@ -223,6 +227,10 @@ pub enum Error {
         actual: String,
     },
 
+    /// Raised when a disk is asked for that the camera does not have
+    #[error("The camera has no disk number {0}")]
+    UnknownDisk(u8),
+
     /// Raised when a thread panics
     #[error("Thread panicked")]
     JoinError(#[from] std::sync::Arc<tokio::task::JoinError>),
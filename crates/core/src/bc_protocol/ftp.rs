@@ -0,0 +1,265 @@
+//! FTP controlling methods
+//!
+use super::{BcCamera, Error, Result};
+use crate::bc::{model::*, xml::*};
+
+impl BcCamera {
+    async fn has_ftp(&self, requested: &str) -> Result<()> {
+        let support = self.get_support().await?;
+        if support.ftp.unwrap_or(0) == 0 {
+            return Err(Error::MissingAbility {
+                name: "ftp".to_string(),
+                requested: requested.to_string(),
+                actual: "none".to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Get the current Ftp XML
+    pub async fn get_ftp(&self) -> Result<Ftp> {
+        self.has_ftp("read").await?;
+        let connection = self.get_connection();
+        let msg_num = self.new_message_num();
+        let mut sub_get = connection.subscribe(MSG_ID_GET_FTP, msg_num).await?;
+        let get = Bc {
+            meta: BcMeta {
+                msg_id: MSG_ID_GET_FTP,
+                channel_id: self.channel_id,
+                msg_num,
+                response_code: 0,
+                stream_type: 0,
+                class: 0x6414,
+            },
+            body: BcBody::ModernMsg(ModernMsg {
+                extension: None,
+                payload: None,
+            }),
+        };
+
+        sub_get.send(get).await?;
+        let msg = sub_get.recv().await?;
+        if msg.meta.response_code != 200 {
+            return Err(Error::CameraServiceUnavailable {
+                id: msg.meta.msg_id,
+                code: msg.meta.response_code,
+            });
+        }
+
+        if let BcBody::ModernMsg(ModernMsg {
+            payload: Some(BcPayloads::BcXml(BcXml { ftp: Some(ftp), .. })),
+            ..
+        }) = msg.body
+        {
+            Ok(ftp)
+        } else {
+            Err(Error::UnintelligibleReply {
+                reply: std::sync::Arc::new(Box::new(msg)),
+                why: "Expected Ftp xml but it was not recieved",
+            })
+        }
+    }
+
+    /// Set the Ftp XML
+    pub async fn set_ftp(&self, mut ftp: Ftp) -> Result<()> {
+        self.has_ftp("write").await?;
+        let connection = self.get_connection();
+        let msg_num = self.new_message_num();
+        let mut sub_set = connection.subscribe(MSG_ID_SET_FTP, msg_num).await?;
+        ftp.version = xml_ver();
+        let set = Bc {
+            meta: BcMeta {
+                msg_id: MSG_ID_SET_FTP,
+                channel_id: self.channel_id,
+                msg_num,
+                response_code: 0,
+                stream_type: 0,
+                class: 0x6414,
+            },
+            body: BcBody::ModernMsg(ModernMsg {
+                extension: None,
+                payload: Some(BcPayloads::BcXml(BcXml {
+                    ftp: Some(ftp),
+                    ..Default::default()
+                })),
+            }),
+        };
+
+        sub_set.send(set).await?;
+        let msg = sub_set.recv().await?;
+        if msg.meta.response_code != 200 {
+            return Err(Error::CameraServiceUnavailable {
+                id: msg.meta.msg_id,
+                code: msg.meta.response_code,
+            });
+        }
+        Ok(())
+    }
+
+    /// Test the Ftp with this XML by uploading a file to the server
+    pub async fn test_ftp(&self, mut ftp: Ftp) -> Result<()> {
+        let support = self.get_support().await?;
+        if support.ftp_test.unwrap_or(0) == 0 {
+            return Err(Error::MissingAbility {
+                name: "ftpTest".to_string(),
+                requested: "write".to_string(),
+                actual: "none".to_string(),
+            });
+        }
+        let connection = self.get_connection();
+        let msg_num = self.new_message_num();
+        let mut sub_test = connection.subscribe(MSG_ID_TEST_FTP, msg_num).await?;
+        ftp.version = xml_ver();
+        let test = Bc {
+            meta: BcMeta {
+                msg_id: MSG_ID_TEST_FTP,
+                channel_id: self.channel_id,
+                msg_num,
+                response_code: 0,
+                stream_type: 0,
+                class: 0x6414,
+            },
+            body: BcBody::ModernMsg(ModernMsg {
+                extension: None,
+                payload: Some(BcPayloads::BcXml(BcXml {
+                    ftp: Some(ftp),
+                    ..Default::default()
+                })),
+            }),
+        };
+
+        sub_test.send(test).await?;
+        let msg = sub_test.recv().await?;
+        if msg.meta.response_code != 200 {
+            return Err(Error::CameraServiceUnavailable {
+                id: msg.meta.msg_id,
+                code: msg.meta.response_code,
+            });
+        }
+        Ok(())
+    }
+
+    /// Get the current FtpTask XML
+    pub async fn get_ftp_task(&self) -> Result<FtpTask> {
+        self.has_ftp("read").await?;
+        let connection = self.get_connection();
+        let msg_num = self.new_message_num();
+        let mut sub_get = connection.subscribe(MSG_ID_GET_FTP_TASK, msg_num).await?;
+        let get = Bc {
+            meta: BcMeta {
+                msg_id: MSG_ID_GET_FTP_TASK,
+                channel_id: self.channel_id,
+                msg_num,
+                response_code: 0,
+                stream_type: 0,
+                class: 0x6414,
+            },
+            body: BcBody::ModernMsg(ModernMsg {
+                extension: Some(Extension {
+                    channel_id: Some(self.channel_id),
+                    ..Default::default()
+                }),
+                payload: None,
+            }),
+        };
+
+        sub_get.send(get).await?;
+        let msg = sub_get.recv().await?;
+        if msg.meta.response_code != 200 {
+            return Err(Error::CameraServiceUnavailable {
+                id: msg.meta.msg_id,
+                code: msg.meta.response_code,
+            });
+        }
+
+        if let BcBody::ModernMsg(ModernMsg {
+            payload:
+                Some(BcPayloads::BcXml(BcXml {
+                    ftp_task: Some(ftp_task),
+                    ..
+                })),
+            ..
+        }) = msg.body
+        {
+            Ok(ftp_task)
+        } else {
+            Err(Error::UnintelligibleReply {
+                reply: std::sync::Arc::new(Box::new(msg)),
+                why: "Expected FtpTask xml but it was not recieved",
+            })
+        }
+    }
+
+    /// Setup the Ftp Task
+    pub async fn set_ftp_task(&self, ftp_task: FtpTask) -> Result<()> {
+        self.has_ftp("write").await?;
+        let connection = self.get_connection();
+        let msg_num = self.new_message_num();
+        let mut sub_set = connection.subscribe(MSG_ID_SET_FTP_TASK, msg_num).await?;
+        let set = Bc {
+            meta: BcMeta {
+                msg_id: MSG_ID_SET_FTP_TASK,
+                channel_id: self.channel_id,
+                msg_num,
+                response_code: 0,
+                stream_type: 0,
+                class: 0x6414,
+            },
+            body: BcBody::ModernMsg(ModernMsg {
+                extension: Some(Extension {
+                    channel_id: Some(self.channel_id),
+                    ..Default::default()
+                }),
+                payload: Some(BcPayloads::BcXml(BcXml {
+                    ftp_task: Some(ftp_task),
+                    ..Default::default()
+                })),
+            }),
+        };
+
+        sub_set.send(set).await?;
+        let msg = sub_set.recv().await?;
+        if msg.meta.response_code != 200 {
+            return Err(Error::CameraServiceUnavailable {
+                id: msg.meta.msg_id,
+                code: msg.meta.response_code,
+            });
+        }
+        Ok(())
+    }
+
+    /// Turn on FTP uploads
+    pub async fn ftp_on(&self) -> Result<()> {
+        self.set_ftp_task(FtpTask {
+            version: xml_ver(),
+            channel_id: self.channel_id,
+            enable: 1,
+            schedule_list: None,
+        })
+        .await
+    }
+
+    /// Turn off FTP uploads
+    pub async fn ftp_off(&self) -> Result<()> {
+        self.set_ftp_task(FtpTask {
+            version: xml_ver(),
+            channel_id: self.channel_id,
+            enable: 0,
+            schedule_list: None,
+        })
+        .await
+    }
+
+    /// Turn on FTP uploads of the motion all the time
+    pub async fn ftp_on_always(&self) -> Result<()> {
+        self.set_ftp_task(FtpTask {
+            version: xml_ver(),
+            channel_id: self.channel_id,
+            enable: 1,
+            schedule_list: Some(ScheduleList {
+                schedule: Schedule::all_week("MD"),
+            }),
+        })
+        .await
+    }
+}
@@ -16,14 +16,41 @@ pub enum MotionStatus {
     NoChange(Instant),
 }
 
+/// Visitor Status of a doorbell that the callback can send
+#[derive(Clone, Copy, Debug)]
+pub enum VisitorStatus {
+    /// Sent when someone presses the button of the doorbell
+    Start(Instant),
+    /// Sent when the camera stops reporting the visitor
+    Stop(Instant),
+}
+
+/// Sound Status of the sound detection that the callback can send
+#[derive(Clone, Copy, Debug)]
+pub enum SoundStatus {
+    /// Sent when the camera hears a sound
+    Start(Instant),
+    /// Sent when the camera stops reporting the sound
+    Stop(Instant),
+}
+
+/// What one alarm message says: the motion, its AI types, if there is a
+/// visitor and if there is a sound
+type AlarmUpdate = (MotionStatus, Vec<String>, Option<bool>, Option<bool>);
+
 /// A handle on current motion related events comming from the camera
 ///
 /// When this object is dropped the motion events are stopped
 pub struct MotionData {
     handle: JoinSet<Result<()>>,
     cancel: CancellationToken,
-    rx: Receiver<Result<MotionStatus>>,
+    rx: Receiver<Result<AlarmUpdate>>,
     last_update: MotionStatus,
+    last_ai_types: Vec<String>,
+    visitor: bool,
+    visitor_events: Vec<VisitorStatus>,
+    sound: bool,
+    sound_events: Vec<SoundStatus>,
 }
 
 impl MotionData {
@@ -53,6 +80,72 @@ impl MotionData {
         })
     }
 
+    /// The AI types, such as `"people"` or `"vehicle"`, that were detected
+    /// in the last motion event. Empty if there were none or the camera
+    /// does not support AI detection
+    pub fn ai_types(&self) -> &[String] {
+        &self.last_ai_types
+    }
+
+    /// If a visitor is at the door, this is only ever true for doorbells
+    pub fn visitor(&self) -> bool {
+        self.visitor
+    }
+
+    /// Take the visitor events of a doorbell that came with the motion
+    /// events consumed so far
+    ///
+    /// These arrive on the same messages as the motion so they are
+    /// collected by [`MotionData::consume_motion_events`] and
+    /// [`MotionData::next_motion`]
+    pub fn take_visitor_events(&mut self) -> Vec<VisitorStatus> {
+        std::mem::take(&mut self.visitor_events)
+    }
+
+    /// If the sound detection of the camera hears something
+    pub fn sound(&self) -> bool {
+        self.sound
+    }
+
+    /// Take the sound detection events that came with the motion events
+    /// consumed so far
+    ///
+    /// Like the visitor these arrive on the same messages as the motion
+    pub fn take_sound_events(&mut self) -> Vec<SoundStatus> {
+        std::mem::take(&mut self.sound_events)
+    }
+
+    fn update(&mut self, (motion, ai_types, visitor, sound): AlarmUpdate) -> MotionStatus {
+        if !matches!(motion, MotionStatus::NoChange(_)) {
+            self.last_ai_types = ai_types;
+        }
+        match visitor {
+            Some(true) if !self.visitor => {
+                self.visitor = true;
+                self.visitor_events
+                    .push(VisitorStatus::Start(Instant::now()));
+            }
+            Some(false) if self.visitor => {
+                self.visitor = false;
+                self.visitor_events
+                    .push(VisitorStatus::Stop(Instant::now()));
+            }
+            _ => {}
+        }
+        match sound {
+            Some(true) if !self.sound => {
+                self.sound = true;
+                self.sound_events.push(SoundStatus::Start(Instant::now()));
+            }
+            Some(false) if self.sound => {
+                self.sound = false;
+                self.sound_events.push(SoundStatus::Stop(Instant::now()));
+            }
+            _ => {}
+        }
+        motion
+    }
+
     /// Consume the motion events diretly
     ///
     /// An error is raised if the motion connection to the camera is dropped
@@ -60,7 +153,10 @@ impl MotionData {
         let mut results: Vec<MotionStatus> = vec![];
         loop {
             match self.rx.try_recv() {
-                Ok(motion) => results.push(motion?),
+                Ok(motion) => {
+                    let motion = self.update(motion?);
+                    results.push(motion)
+                }
                 Err(TryRecvError::Empty) => break,
                 Err(e) => return Err(Error::from(e)),
             }
@@ -79,7 +175,7 @@ impl MotionData {
         if let Some(last) = motions.last() {
             Ok(*last)
         } else if let Some(moition) = self.rx.recv().await {
-            let moition = moition?;
+            let moition = self.update(moition?);
             self.last_update = moition;
             Ok(moition)
         } else {
@@ -236,27 +332,33 @@ impl BcCamera {
                                     ..
                                 }) = motion_msg.body
                                 {
-                                    let mut result = MotionStatus::NoChange(Instant::now());
+                                    let mut result = (MotionStatus::NoChange(Instant::now()), vec![], None, None);
                                     for alarm_event in &alarm_event_list.alarm_events {
                                         if alarm_event.channel_id == channel_id {
-                                            if alarm_event.status != "none"
-                                                || alarm_event
-                                                    .ai_type
-                                                    .as_ref()
-                                                    .map(|ai_type| ai_type != "none")
-                                                    .unwrap_or(false)
-                                            {
-                                                result = MotionStatus::Start(Instant::now());
+                                            // Such as "people" or "people,vehicle"
+                                            let ai_types = alarm_event
+                                                .ai_type
+                                                .as_deref()
+                                                .unwrap_or_default()
+                                                .split(',')
+                                                .map(str::trim)
+                                                .filter(|ai_type| !ai_type.is_empty() && *ai_type != "none")
+                                                .map(str::to_string)
+                                                .collect::<Vec<_>>();
+                                            let visitor = Some(alarm_event.is_visitor());
+                                            let sound = Some(alarm_event.is_sound());
+                                            if alarm_event.is_motion() || !ai_types.is_empty() {
+                                                result = (MotionStatus::Start(Instant::now()), ai_types, visitor, sound);
                                                 break;
                                             } else {
-                                                result = MotionStatus::Stop(Instant::now());
+                                                result = (MotionStatus::Stop(Instant::now()), vec![], visitor, sound);
                                                 break;
                                             }
                                         }
                                     }
                                     Ok(result)
                                 } else {
-                                    Ok(MotionStatus::NoChange(Instant::now()))
+                                    Ok((MotionStatus::NoChange(Instant::now()), vec![], None, None))
                                 }
                             }
                             // On connection drop we stop
@@ -278,6 +380,11 @@ impl BcCamera {
             cancel,
             rx,
             last_update: MotionStatus::NoChange(Instant::now()),
+            last_ai_types: vec![],
+            visitor: false,
+            visitor_events: vec![],
+            sound: false,
+            sound_events: vec![],
         })
     }
 }
@@ -0,0 +1,235 @@
+//! Control of the recordings that the camera makes to its SD card
+//!
+use super::{BcCamera, Error, Result};
+use crate::bc::{model::*, xml::*};
+
+/// What makes the camera record
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RecordTrigger {
+    /// Record when there is motion
+    Motion,
+    /// Record all the time
+    Continuous,
+}
+
+impl BcCamera {
+    async fn has_record_cfg(&self, requested: &str) -> Result<()> {
+        let support = self.get_support().await?;
+        if support.record_cfg.unwrap_or(0) == 0 {
+            return Err(Error::MissingAbility {
+                name: "recordCfg".to_string(),
+                requested: requested.to_string(),
+                actual: "none".to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Get the current RecordCfg XML
+    pub async fn get_record_cfg(&self) -> Result<RecordCfg> {
+        self.has_record_cfg("read").await?;
+        let connection = self.get_connection();
+        let msg_num = self.new_message_num();
+        let mut sub_get = connection.subscribe(MSG_ID_GET_RECORD_CFG, msg_num).await?;
+        let get = Bc {
+            meta: BcMeta {
+                msg_id: MSG_ID_GET_RECORD_CFG,
+                channel_id: self.channel_id,
+                msg_num,
+                response_code: 0,
+                stream_type: 0,
+                class: 0x6414,
+            },
+            body: BcBody::ModernMsg(ModernMsg {
+                extension: Some(Extension {
+                    channel_id: Some(self.channel_id),
+                    ..Default::default()
+                }),
+                payload: None,
+            }),
+        };
+
+        sub_get.send(get).await?;
+        let msg = sub_get.recv().await?;
+        if msg.meta.response_code != 200 {
+            return Err(Error::CameraServiceUnavailable {
+                id: msg.meta.msg_id,
+                code: msg.meta.response_code,
+            });
+        }
+
+        if let BcBody::ModernMsg(ModernMsg {
+            payload:
+                Some(BcPayloads::BcXml(BcXml {
+                    record_cfg: Some(record_cfg),
+                    ..
+                })),
+            ..
+        }) = msg.body
+        {
+            Ok(record_cfg)
+        } else {
+            Err(Error::UnintelligibleReply {
+                reply: std::sync::Arc::new(Box::new(msg)),
+                why: "Expected RecordCfg xml but it was not recieved",
+            })
+        }
+    }
+
+    /// Set the RecordCfg XML
+    pub async fn set_record_cfg(&self, mut record_cfg: RecordCfg) -> Result<()> {
+        self.has_record_cfg("write").await?;
+        let connection = self.get_connection();
+        let msg_num = self.new_message_num();
+        let mut sub_set = connection.subscribe(MSG_ID_SET_RECORD_CFG, msg_num).await?;
+        record_cfg.version = xml_ver();
+        let set = Bc {
+            meta: BcMeta {
+                msg_id: MSG_ID_SET_RECORD_CFG,
+                channel_id: self.channel_id,
+                msg_num,
+                response_code: 0,
+                stream_type: 0,
+                class: 0x6414,
+            },
+            body: BcBody::ModernMsg(ModernMsg {
+                extension: Some(Extension {
+                    channel_id: Some(self.channel_id),
+                    ..Default::default()
+                }),
+                payload: Some(BcPayloads::BcXml(BcXml {
+                    record_cfg: Some(record_cfg),
+                    ..Default::default()
+                })),
+            }),
+        };
+
+        sub_set.send(set).await?;
+        let msg = sub_set.recv().await?;
+        if msg.meta.response_code != 200 {
+            return Err(Error::CameraServiceUnavailable {
+                id: msg.meta.msg_id,
+                code: msg.meta.response_code,
+            });
+        }
+        Ok(())
+    }
+
+    /// Get the current Record XML which has the record schedule
+    pub async fn get_record(&self) -> Result<Record> {
+        self.has_record_cfg("read").await?;
+        let connection = self.get_connection();
+        let msg_num = self.new_message_num();
+        let mut sub_get = connection.subscribe(MSG_ID_GET_RECORD, msg_num).await?;
+        let get = Bc {
+            meta: BcMeta {
+                msg_id: MSG_ID_GET_RECORD,
+                channel_id: self.channel_id,
+                msg_num,
+                response_code: 0,
+                stream_type: 0,
+                class: 0x6414,
+            },
+            body: BcBody::ModernMsg(ModernMsg {
+                extension: Some(Extension {
+                    channel_id: Some(self.channel_id),
+                    ..Default::default()
+                }),
+                payload: None,
+            }),
+        };
+
+        sub_get.send(get).await?;
+        let msg = sub_get.recv().await?;
+        if msg.meta.response_code != 200 {
+            return Err(Error::CameraServiceUnavailable {
+                id: msg.meta.msg_id,
+                code: msg.meta.response_code,
+            });
+        }
+
+        if let BcBody::ModernMsg(ModernMsg {
+            payload:
+                Some(BcPayloads::BcXml(BcXml {
+                    record: Some(record),
+                    ..
+                })),
+            ..
+        }) = msg.body
+        {
+            Ok(record)
+        } else {
+            Err(Error::UnintelligibleReply {
+                reply: std::sync::Arc::new(Box::new(msg)),
+                why: "Expected Record xml but it was not recieved",
+            })
+        }
+    }
+
+    /// Setup the Record schedule
+    pub async fn set_record(&self, mut record: Record) -> Result<()> {
+        self.has_record_cfg("write").await?;
+        let connection = self.get_connection();
+        let msg_num = self.new_message_num();
+        let mut sub_set = connection.subscribe(MSG_ID_SET_RECORD, msg_num).await?;
+        record.version = xml_ver();
+        record.channel_id = self.channel_id;
+        let set = Bc {
+            meta: BcMeta {
+                msg_id: MSG_ID_SET_RECORD,
+                channel_id: self.channel_id,
+                msg_num,
+                response_code: 0,
+                stream_type: 0,
+                class: 0x6414,
+            },
+            body: BcBody::ModernMsg(ModernMsg {
+                extension: Some(Extension {
+                    channel_id: Some(self.channel_id),
+                    ..Default::default()
+                }),
+                payload: Some(BcPayloads::BcXml(BcXml {
+                    record: Some(record),
+                    ..Default::default()
+                })),
+            }),
+        };
+
+        sub_set.send(set).await?;
+        let msg = sub_set.recv().await?;
+        if msg.meta.response_code != 200 {
+            return Err(Error::CameraServiceUnavailable {
+                id: msg.meta.msg_id,
+                code: msg.meta.response_code,
+            });
+        }
+        Ok(())
+    }
+
+    /// Turn the recording on or off keeping its schedule
+    pub async fn record_set(&self, state: bool) -> Result<()> {
+        let mut record = self.get_record().await?;
+        record.enable = match state {
+            true => 1,
+            false => 0,
+        };
+        self.set_record(record).await
+    }
+
+    /// Turn on the recording all the time with this trigger
+    pub async fn record_on_always(&self, trigger: RecordTrigger) -> Result<()> {
+        let alarm_type = match trigger {
+            RecordTrigger::Motion => "MD",
+            RecordTrigger::Continuous => "none",
+        };
+        self.set_record(Record {
+            version: xml_ver(),
+            channel_id: self.channel_id,
+            enable: 1,
+            schedule_list: Some(RecordScheduleList {
+                schedule: vec![Schedule::all_week(alarm_type)],
+            }),
+        })
+        .await
+    }
+}
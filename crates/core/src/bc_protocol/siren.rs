@@ -1,11 +1,50 @@
-//! Trigger for the siren
+//! Trigger for the siren and the other audio alarms
 
 use super::{BcCamera, Error, Result};
 use crate::bc::{model::*, xml::*};
 
+/// How the camera should play its alarm audio
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AudioPlay {
+    /// Play the audio this many times
+    Times(u32),
+    /// Play the audio over and over for this many seconds
+    Seconds(u32),
+    /// Play the audio over and over until [`AudioPlay::Stop`]
+    Continuous,
+    /// Stop the audio that is playing
+    Stop,
+}
+
 impl BcCamera {
+    async fn has_audio_alarm(&self, requested: &str) -> Result<()> {
+        let support = self.get_support().await?;
+        if support.audio_alarm.unwrap_or(0) == 0 {
+            return Err(Error::MissingAbility {
+                name: "audioAlarm".to_string(),
+                requested: requested.to_string(),
+                actual: "none".to_string(),
+            });
+        }
+        Ok(())
+    }
+
     /// Trigger the siren
     pub async fn siren(&self) -> Result<()> {
+        self.play_audio(AudioPlay::Times(1)).await
+    }
+
+    /// Play the alarm audio of the camera
+    ///
+    /// Which audio is played and how loud is set with [`BcCamera::set_audio_cfg`]
+    pub async fn play_audio(&self, play: AudioPlay) -> Result<()> {
+        let (play_mode, play_duration, play_times, on_off) = match play {
+            AudioPlay::Times(times) => (0, 0, times, 0),
+            AudioPlay::Seconds(seconds) => (1, seconds, 0, 1),
+            AudioPlay::Continuous => (1, 0, 0, 1),
+            AudioPlay::Stop => (1, 0, 0, 0),
+        };
+
         let connection = self.get_connection();
         let msg_num = self.new_message_num();
         let mut sub_get = connection.subscribe(MSG_ID_PLAY_AUDIO, msg_num).await?;
@@ -26,10 +65,10 @@ impl BcCamera {
                 payload: Some(BcPayloads::BcXml(BcXml {
                     audio_play_info: Some(AudioPlayInfo {
                         channel_id: self.channel_id,
-                        play_mode: 0,
-                        play_duration: 0,
-                        play_times: 1,
-                        on_off: 0,
+                        play_mode,
+                        play_duration,
+                        play_times,
+                        on_off,
                     }),
                     ..Default::default()
                 })),
@@ -47,4 +86,99 @@ impl BcCamera {
 
         Ok(())
     }
+
+    /// Get the [AudioCfg] xml with the audio that the alarm plays and its volume
+    pub async fn get_audio_cfg(&self) -> Result<AudioCfg> {
+        self.has_audio_alarm("read").await?;
+        let connection = self.get_connection();
+        let msg_num = self.new_message_num();
+        let mut sub_get = connection.subscribe(MSG_ID_GET_AUDIO_CFG, msg_num).await?;
+        let get = Bc {
+            meta: BcMeta {
+                msg_id: MSG_ID_GET_AUDIO_CFG,
+                channel_id: self.channel_id,
+                msg_num,
+                response_code: 0,
+                stream_type: 0,
+                class: 0x6414,
+            },
+            body: BcBody::ModernMsg(ModernMsg {
+                extension: Some(Extension {
+                    channel_id: Some(self.channel_id),
+                    ..Default::default()
+                }),
+                payload: None,
+            }),
+        };
+
+        sub_get.send(get).await?;
+        let msg = sub_get.recv().await?;
+        if msg.meta.response_code != 200 {
+            return Err(Error::CameraServiceUnavailable {
+                id: msg.meta.msg_id,
+                code: msg.meta.response_code,
+            });
+        }
+
+        if let BcBody::ModernMsg(ModernMsg {
+            payload:
+                Some(BcPayloads::BcXml(BcXml {
+                    audio_cfg: Some(audio_cfg),
+                    ..
+                })),
+            ..
+        }) = msg.body
+        {
+            Ok(audio_cfg)
+        } else {
+            Err(Error::UnintelligibleReply {
+                reply: std::sync::Arc::new(Box::new(msg)),
+                why: "Expected AudioCfg xml but it was not recieved",
+            })
+        }
+    }
+
+    /// Set the audio that the alarm plays and its volume
+    ///
+    /// This replaces all of the settings, so change the [AudioCfg] from
+    /// [`BcCamera::get_audio_cfg`] to keep the others
+    pub async fn set_audio_cfg(&self, mut audio_cfg: AudioCfg) -> Result<()> {
+        self.has_audio_alarm("write").await?;
+        let connection = self.get_connection();
+        let msg_num = self.new_message_num();
+        let mut sub_set = connection.subscribe(MSG_ID_SET_AUDIO_CFG, msg_num).await?;
+        audio_cfg.version = "1.1".to_string();
+        audio_cfg.channel_id = self.channel_id;
+        let set = Bc {
+            meta: BcMeta {
+                msg_id: MSG_ID_SET_AUDIO_CFG,
+                channel_id: self.channel_id,
+                msg_num,
+                response_code: 0,
+                stream_type: 0,
+                class: 0x6414,
+            },
+            body: BcBody::ModernMsg(ModernMsg {
+                extension: Some(Extension {
+                    channel_id: Some(self.channel_id),
+                    ..Default::default()
+                }),
+                payload: Some(BcPayloads::BcXml(BcXml {
+                    audio_cfg: Some(audio_cfg),
+                    ..Default::default()
+                })),
+            }),
+        };
+
+        sub_set.send(set).await?;
+        let msg = sub_set.recv().await?;
+        if msg.meta.response_code != 200 {
+            return Err(Error::CameraServiceUnavailable {
+                id: msg.meta.msg_id,
+                code: msg.meta.response_code,
+            });
+        }
+
+        Ok(())
+    }
 }
@@ -0,0 +1,147 @@
+//! Control of the sound detection built into the camera
+//!
+//! When it hears something the camera raises an `"audio"` alarm on the
+//! same messages as the motion, see [`BcCamera::listen_on_motion`]
+use super::{BcCamera, Error, Result};
+use crate::bc::{model::*, xml::*};
+
+impl BcCamera {
+    async fn has_sound_alarm(&self, requested: &str) -> Result<()> {
+        let support = self.get_support().await?;
+        if support.audio_alarm.unwrap_or(0) == 0 {
+            return Err(Error::MissingAbility {
+                name: "audioAlarm".to_string(),
+                requested: requested.to_string(),
+                actual: "none".to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Get the [AudioTask] xml which contains the sound detection settings
+    pub async fn get_audio_task(&self) -> Result<AudioTask> {
+        self.has_sound_alarm("read").await?;
+        let connection = self.get_connection();
+        let msg_num = self.new_message_num();
+        let mut sub_get = connection.subscribe(MSG_ID_GET_AUDIO_TASK, msg_num).await?;
+        let get = Bc {
+            meta: BcMeta {
+                msg_id: MSG_ID_GET_AUDIO_TASK,
+                channel_id: self.channel_id,
+                msg_num,
+                response_code: 0,
+                stream_type: 0,
+                class: 0x6414,
+            },
+            body: BcBody::ModernMsg(ModernMsg {
+                extension: Some(Extension {
+                    channel_id: Some(self.channel_id),
+                    ..Default::default()
+                }),
+                payload: None,
+            }),
+        };
+
+        sub_get.send(get).await?;
+        let msg = sub_get.recv().await?;
+        if msg.meta.response_code != 200 {
+            return Err(Error::CameraServiceUnavailable {
+                id: msg.meta.msg_id,
+                code: msg.meta.response_code,
+            });
+        }
+
+        if let BcBody::ModernMsg(ModernMsg {
+            payload:
+                Some(BcPayloads::BcXml(BcXml {
+                    audio_task: Some(audio_task),
+                    ..
+                })),
+            ..
+        }) = msg.body
+        {
+            Ok(audio_task)
+        } else {
+            Err(Error::UnintelligibleReply {
+                reply: std::sync::Arc::new(Box::new(msg)),
+                why: "Expected AudioTask xml but it was not recieved",
+            })
+        }
+    }
+
+    /// Set the sound detection using the [AudioTask] xml
+    pub async fn set_audio_task(&self, mut audio_task: AudioTask) -> Result<()> {
+        self.has_sound_alarm("write").await?;
+        let connection = self.get_connection();
+        let msg_num = self.new_message_num();
+        let mut sub_set = connection.subscribe(MSG_ID_SET_AUDIO_TASK, msg_num).await?;
+        audio_task.version = xml_ver();
+        audio_task.channel_id = self.channel_id;
+        let set = Bc {
+            meta: BcMeta {
+                msg_id: MSG_ID_SET_AUDIO_TASK,
+                channel_id: self.channel_id,
+                msg_num,
+                response_code: 0,
+                stream_type: 0,
+                class: 0x6414,
+            },
+            body: BcBody::ModernMsg(ModernMsg {
+                extension: Some(Extension {
+                    channel_id: Some(self.channel_id),
+                    ..Default::default()
+                }),
+                payload: Some(BcPayloads::BcXml(BcXml {
+                    audio_task: Some(audio_task),
+                    ..Default::default()
+                })),
+            }),
+        };
+
+        sub_set.send(set).await?;
+        let msg = sub_set.recv().await?;
+        if msg.meta.response_code != 200 {
+            return Err(Error::CameraServiceUnavailable {
+                id: msg.meta.msg_id,
+                code: msg.meta.response_code,
+            });
+        }
+        Ok(())
+    }
+
+    /// This is a convience function to turn the sound detection on or off
+    /// keeping its schedule
+    pub async fn sound_alarm_set(&self, state: bool) -> Result<()> {
+        let mut audio_task = self.get_audio_task().await?;
+        audio_task.enable = match state {
+            true => 1,
+            false => 0,
+        };
+        self.set_audio_task(audio_task).await
+    }
+
+    /// Turn on the sound detection for every hour of the week
+    ///
+    /// The sensitivity is kept and so is the alarm type of the current schedule,
+    /// which is `audio` if there is none
+    pub async fn sound_alarm_on_always(&self) -> Result<()> {
+        let mut audio_task = self.get_audio_task().await?;
+        let alarm_type = audio_task
+            .schedule_list
+            .take()
+            .map(|list| list.schedule.alarm_type)
+            .unwrap_or_else(|| "audio".to_string());
+        audio_task.enable = 1;
+        audio_task.schedule_list = Some(ScheduleList {
+            schedule: Schedule::all_week(&alarm_type),
+        });
+        self.set_audio_task(audio_task).await
+    }
+
+    /// Set how easily a sound raises the alarm from 1 to 100
+    pub async fn sound_alarm_sensitivity(&self, sensitivity: u8) -> Result<()> {
+        let mut audio_task = self.get_audio_task().await?;
+        audio_task.sensitivity = Some(sensitivity.clamp(1, 100));
+        self.set_audio_task(audio_task).await
+    }
+}
@@ -1,10 +1,59 @@
 use super::{BcCamera, Error, Result};
 use crate::{bc::model::*, bc::xml::*, bcmedia::model::*};
-use crossbeam_channel::Receiver;
+use crossbeam_channel::{unbounded, Receiver};
 use std::io::{BufRead, Error as IoError, ErrorKind, Read};
+use std::time::Duration;
 
 type IoResult<T> = std::result::Result<T, IoError>;
 
+/// The audio encodings that can be sent to the camera for talk
+///
+/// G.711 is not one of them, how the camera expects it to be framed as
+/// [`BcMedia`] is not known
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TalkEncoding {
+    /// ADPCM in DVI-4 layout, sent in blocks of half of `lengthPerEncoder`
+    Adpcm,
+    /// AAC in ADTS frames
+    Aac,
+}
+
+impl TalkEncoding {
+    /// The encoding of the `audioType` of the config, if it is one that can be sent
+    pub fn of(audio_config: &AudioConfig) -> Option<Self> {
+        match audio_config.audio_type.to_lowercase().as_str() {
+            "adpcm" => Some(TalkEncoding::Adpcm),
+            "aac" => Some(TalkEncoding::Aac),
+            _ => None,
+        }
+    }
+}
+
+impl TalkAbility {
+    /// The [`TalkConfig`] for the first of the advertised audio configs
+    /// that has a [`TalkEncoding`]
+    ///
+    /// None if the camera cannot talk or only advertises other encodings
+    pub fn talk_config(&self, channel_id: u8) -> Option<TalkConfig> {
+        let audio_config = self
+            .audio_config_list
+            .iter()
+            .map(|list| &list.audio_config)
+            .find(|audio_config| TalkEncoding::of(audio_config).is_some())?;
+        Some(TalkConfig {
+            channel_id,
+            duplex: self.duplex_list.first()?.duplex.clone(),
+            audio_stream_mode: self
+                .audio_stream_mode_list
+                .first()?
+                .audio_stream_mode
+                .clone(),
+            audio_config: audio_config.clone(),
+            ..Default::default()
+        })
+    }
+}
+
 impl BcCamera {
     ///
     /// Finish Talk
@@ -107,136 +156,19 @@ impl BcCamera {
     /// This method assumes that you have set up the data in the desired format
     /// in the `<TalkAbility>` xml
     ///
-    /// It also checks that it is ADPCM as the code is written to accept only that
-    ///
     /// # Parameters
     ///
-    /// * `adpcm` - Data must be adpcm in DVI-4 format
+    /// * `data` - Data must be in the [`TalkEncoding`] of the `audio_type`, so
+    ///   adpcm in DVI-4 format or AAC in ADTS frames
     ///
-    /// * `talk_config` - The talk config that describes the adpcm data
+    /// * `talk_config` - The talk config that describes the data
     ///
     ///
-    pub async fn talk(&self, adpcm: &[u8], talk_config: TalkConfig) -> Result<()> {
-        let connection = self.get_connection();
-
-        let msg_num = self.new_message_num();
-        let mut sub = connection.subscribe(MSG_ID_TALKCONFIG, msg_num).await?;
-
-        if &talk_config.audio_config.audio_type != "adpcm" {
-            return Err(Error::UnknownTalkEncoding);
-        }
-
-        let block_size = talk_config.audio_config.length_per_encoder / 2;
-        let sample_rate = talk_config.audio_config.sample_rate;
-
-        let msg = Bc {
-            meta: BcMeta {
-                msg_id: MSG_ID_TALKCONFIG,
-                channel_id: self.channel_id,
-                msg_num,
-                stream_type: 0,
-                response_code: 0,
-                class: 0x6414,
-            },
-            body: BcBody::ModernMsg(ModernMsg {
-                extension: Some(Extension {
-                    channel_id: Some(self.channel_id),
-                    ..Default::default()
-                }),
-                payload: Some(BcPayloads::BcXml(BcXml {
-                    talk_config: Some(talk_config),
-                    ..Default::default()
-                })),
-            }),
-        };
-
-        sub.send(msg).await?;
-        let mut msg = sub.recv().await?;
-
-        // If another client is already talking OR if we crashed before sending
-        // msgid 11 the camera will reply 422
-        //
-        // The official client in this case just sends msgid 11 and retries so
-        // we will do that too
-        if let BcMeta {
-            response_code: 422, ..
-        } = msg.meta
-        {
-            self.talk_stop().await?;
-            // Retry
-            sub.send(msg).await?;
-            msg = sub.recv().await?;
-        }
-
-        if let BcMeta {
-            response_code: 200, ..
-        } = msg.meta
-        {
-        } else {
-            return Err(Error::UnintelligibleReply {
-                reply: std::sync::Arc::new(Box::new(msg)),
-                why:
-                    "The camera did not accept the TalkConfig xml. Audio format is likely incorrect",
-            });
-        }
-
-        let full_block_size = block_size + 4; // Block size + predictor state
-        let msg_num = self.new_message_num();
-        let sub = connection.subscribe(MSG_ID_TALK, msg_num).await?;
-
-        const BLOCK_PER_PAYLOAD: usize = 4;
-        const BLOCK_HEADER_SIZE: usize = 4;
-        const SAMPLES_PER_BYTE: usize = 2;
-
-        for payload_bytes in adpcm.chunks(full_block_size as usize * BLOCK_PER_PAYLOAD) {
-            let mut payload = vec![];
-            for bytes in payload_bytes.chunks(full_block_size as usize) {
-                let bcmedia_adpcm = BcMedia::Adpcm(BcMediaAdpcm {
-                    data: bytes.to_vec(),
-                });
-                payload = bcmedia_adpcm.serialize(payload)?;
-            }
-
-            let msg = Bc {
-                meta: BcMeta {
-                    msg_id: MSG_ID_TALK,
-                    channel_id: self.channel_id,
-                    msg_num,
-                    stream_type: 0,
-                    response_code: 0,
-                    class: 0x6414,
-                },
-                body: BcBody::ModernMsg(ModernMsg {
-                    extension: Some(Extension {
-                        channel_id: Some(self.channel_id),
-                        binary_data: Some(1),
-                        ..Default::default()
-                    }),
-                    payload: Some(BcPayloads::Binary(payload)),
-                }),
-            };
-
-            sub.send(msg).await?;
-
-            let adpcm_len = payload_bytes.len();
-            // There are two samples per byte
-            //
-            // To calculate the bytes we subtract the block headers from the len
-            //
-            // There is 1 initial sample stored in the block header so we add that in the end
-            //
-            let samples_sent = (adpcm_len - BLOCK_HEADER_SIZE * BLOCK_PER_PAYLOAD)
-                * SAMPLES_PER_BYTE
-                + BLOCK_PER_PAYLOAD;
-
-            // Time to play the sample in seconds
-            let play_length = samples_sent as f32 / sample_rate as f32;
-            std::thread::sleep(std::time::Duration::from_secs_f32(play_length));
-        }
-
-        self.talk_stop().await?;
-
-        Ok(())
+    pub async fn talk(&self, data: &[u8], talk_config: TalkConfig) -> Result<()> {
+        let (tx, rx) = unbounded();
+        let _ = tx.send(data.to_vec());
+        drop(tx);
+        self.talk_stream(rx, talk_config).await
     }
 
     ///
@@ -248,13 +180,12 @@ impl BcCamera {
     /// This method assumes that you have set up the data in the desired format
     /// in the `<TalkAbility>` xml
     ///
-    /// It also checks that it is ADPCM as the code is written to accept only that
-    ///
     /// # Parameters
     ///
-    /// * `adpcm` - Data must be adpcm in DVI-4 format
+    /// * `rx` - Data must be in the [`TalkEncoding`] of the `audio_type`, so
+    ///   adpcm in DVI-4 format or AAC in ADTS frames
     ///
-    /// * `talk_config` - The talk config that describes the adpcm data
+    /// * `talk_config` - The talk config that describes the data
     ///
     ///
     pub async fn talk_stream(&self, rx: Receiver<Vec<u8>>, talk_config: TalkConfig) -> Result<()> {
@@ -263,9 +194,8 @@ impl BcCamera {
         let msg_num = self.new_message_num();
         let mut sub = connection.subscribe(MSG_ID_TALKCONFIG, msg_num).await?;
 
-        if &talk_config.audio_config.audio_type != "adpcm" {
-            return Err(Error::UnknownTalkEncoding);
-        }
+        let encoding =
+            TalkEncoding::of(&talk_config.audio_config).ok_or(Error::UnknownTalkEncoding)?;
 
         let block_size = talk_config.audio_config.length_per_encoder / 2;
         let sample_rate = talk_config.audio_config.sample_rate;
@@ -325,57 +255,20 @@ impl BcCamera {
         let msg_num = self.new_message_num();
         let mut sub = connection.subscribe(MSG_ID_TALK, msg_num).await?;
 
-        const BLOCK_PER_PAYLOAD: usize = 1;
-        const BLOCK_HEADER_SIZE: usize = 4;
-        const SAMPLES_PER_BYTE: usize = 2;
-
         let mut buffered_recv = BufferedStream::from_rx(rx);
 
-        let target_chunks = full_block_size as usize * BLOCK_PER_PAYLOAD;
-
-        let mut end_of_stream = false;
         let mut expected_stream_end = std::time::Instant::now();
-        while !end_of_stream {
-            let mut payload_bytes = vec![];
-            while payload_bytes.len() < target_chunks {
-                let mut buffer = vec![255; target_chunks - payload_bytes.len()];
-                if let Ok(read) = buffered_recv.read(&mut buffer) {
-                    payload_bytes.extend(&buffer[..read]);
-                } else {
-                    // Error should occur if the channel is dropped
-                    // and all bytes are consumed
-                    end_of_stream = true;
+        loop {
+            let frame = match encoding {
+                TalkEncoding::Adpcm => {
+                    next_adpcm(&mut buffered_recv, full_block_size as usize, sample_rate)
                 }
-                if end_of_stream {
-                    break;
-                }
-            }
-
-            let mut payload = vec![];
-            for block_bytes in payload_bytes.chunks(full_block_size as usize) {
-                let bytes: Vec<u8> = block_bytes.to_vec();
-                let bcmedia_adpcm = BcMedia::Adpcm(BcMediaAdpcm { data: bytes });
-                payload = bcmedia_adpcm.serialize(payload)?;
-            }
-
-            let adpcm_len = payload_bytes.len();
-
-            // There are two samples per byte
-            //
-            // To calculate the bytes we subtract the block headers from the len
-            //
-            // There is 1 initial sample stored in the block header so we add that in the end
-            //
-            let samples_sent = if adpcm_len >= BLOCK_HEADER_SIZE * BLOCK_PER_PAYLOAD {
-                (adpcm_len - BLOCK_HEADER_SIZE * BLOCK_PER_PAYLOAD) * SAMPLES_PER_BYTE
-                    + BLOCK_PER_PAYLOAD
-            } else {
-                // Zero samples in this block
+                TalkEncoding::Aac => next_aac(&mut buffered_recv),
+            };
+            let Some((media, play_length)) = frame else {
                 break;
             };
-
-            // Time to play the sample in seconds
-            let play_length = samples_sent as f32 / sample_rate as f32;
+            let payload = media.serialize(vec![])?;
 
             let msg = Bc {
                 meta: BcMeta {
@@ -398,7 +291,6 @@ impl BcCamera {
 
             let time_sent = std::time::Instant::now();
             sub.send(msg).await?;
-            let play_length = std::time::Duration::from_secs_f32(play_length);
             if time_sent > expected_stream_end {
                 expected_stream_end = time_sent + play_length;
             } else {
@@ -409,9 +301,9 @@ impl BcCamera {
 
         // Chunks are still being played, while talk_stop will interrupt them. Wait until we expect
         // the stream to end (+ and extra 100ms) before issuing talk_stop.
-        let remaining_stream_duration = expected_stream_end - std::time::Instant::now();
-        tokio::time::sleep(remaining_stream_duration + std::time::Duration::from_secs_f32(0.1))
-            .await;
+        let remaining_stream_duration =
+            expected_stream_end.saturating_duration_since(std::time::Instant::now());
+        tokio::time::sleep(remaining_stream_duration + Duration::from_secs_f32(0.1)).await;
 
         self.talk_stop().await?;
 
@@ -419,6 +311,83 @@ impl BcCamera {
     }
 }
 
+/// Read the next block of ADPCM with how long it plays for
+///
+/// None once the stream has ended
+fn next_adpcm(
+    stream: &mut BufferedStream,
+    full_block_size: usize,
+    sample_rate: u16,
+) -> Option<(BcMedia, Duration)> {
+    const BLOCK_HEADER_SIZE: usize = 4;
+    const SAMPLES_PER_BYTE: usize = 2;
+
+    let mut block = vec![];
+    while block.len() < full_block_size {
+        let mut buffer = vec![255; full_block_size - block.len()];
+        match stream.read(&mut buffer) {
+            Ok(read) => block.extend(&buffer[..read]),
+            // Error should occur if the channel is dropped
+            // and all bytes are consumed
+            Err(_) => break,
+        }
+    }
+
+    // There are two samples per byte
+    //
+    // To calculate the bytes we subtract the block header from the len
+    //
+    // There is 1 initial sample stored in the block header so we add that in the end
+    //
+    if block.len() < BLOCK_HEADER_SIZE || sample_rate == 0 {
+        // Zero samples in this block
+        return None;
+    }
+    let samples_sent = (block.len() - BLOCK_HEADER_SIZE) * SAMPLES_PER_BYTE + 1;
+
+    // Time to play the sample in seconds
+    let play_length = Duration::from_secs_f32(samples_sent as f32 / sample_rate as f32);
+    Some((BcMedia::Adpcm(BcMediaAdpcm { data: block }), play_length))
+}
+
+/// Read the next ADTS frame of AAC with how long it plays for
+///
+/// None once the stream has ended
+fn next_aac(stream: &mut BufferedStream) -> Option<(BcMedia, Duration)> {
+    const ADTS_HEADER_SIZE: usize = 7;
+
+    loop {
+        let mut header = [0; ADTS_HEADER_SIZE];
+        // Find the sync word, with the layer that is always 0, in case we
+        // started in the middle of a frame
+        let mut previous = 0;
+        loop {
+            stream.read_exact(&mut header[1..2]).ok()?;
+            if previous == 0xFF && header[1] & 0xF6 == 0xF0 {
+                header[0] = previous;
+                break;
+            }
+            previous = header[1];
+        }
+        stream.read_exact(&mut header[2..]).ok()?;
+
+        let frame_length = ((header[3] as usize & 0b11) << 11)
+            | ((header[4] as usize) << 3)
+            | ((header[5] as usize) >> 5);
+        if frame_length < ADTS_HEADER_SIZE {
+            log::debug!("Skipping an AAC frame with a length of {frame_length}");
+            continue;
+        }
+        let mut data = header.to_vec();
+        data.resize(frame_length, 0);
+        stream.read_exact(&mut data[ADTS_HEADER_SIZE..]).ok()?;
+
+        let aac = BcMediaAac { data };
+        let play_length = Duration::from_micros(aac.duration().unwrap_or(0) as u64);
+        return Some((BcMedia::Aac(aac), play_length));
+    }
+}
+
 struct BufferedStream {
     rx: Receiver<Vec<u8>>,
     buffer: Vec<u8>,
@@ -480,3 +449,117 @@ impl BufRead for BufferedStream {
         self.consumed += amt;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stream_of(chunks: Vec<Vec<u8>>) -> BufferedStream {
+        let (tx, rx) = unbounded();
+        for chunk in chunks {
+            tx.send(chunk).unwrap();
+        }
+        BufferedStream::from_rx(rx)
+    }
+
+    /// An ADTS frame of 16kHz mono AAC with `payload` after the header
+    fn adts(payload: &[u8]) -> Vec<u8> {
+        adts_of_length(payload.len() + 7, payload)
+    }
+
+    fn adts_of_length(frame_length: usize, payload: &[u8]) -> Vec<u8> {
+        let mut frame = vec![
+            0xFF,
+            0xF1,
+            0b01 << 6 | 8 << 2,
+            0b01 << 6 | (frame_length >> 11) as u8 & 0b11,
+            (frame_length >> 3) as u8,
+            ((frame_length & 0b111) as u8) << 5 | 0x1F,
+            0xFC,
+        ];
+        frame.extend(payload);
+        frame
+    }
+
+    fn aac_data(frame: Option<(BcMedia, Duration)>) -> Vec<u8> {
+        match frame {
+            Some((BcMedia::Aac(aac), _)) => aac.data,
+            other => panic!("Expected an AAC frame, not {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_aac_frames() {
+        let first = adts(&[1, 2, 3]);
+        let second = adts(&[4, 5]);
+        // Frames can be split across the chunks
+        let mut data = first.clone();
+        data.extend(&second);
+        let (head, tail) = data.split_at(5);
+        let mut stream = stream_of(vec![head.to_vec(), tail.to_vec()]);
+
+        let frame = next_aac(&mut stream);
+        assert!(matches!(frame, Some((_, length)) if length == Duration::from_millis(64)));
+        assert_eq!(aac_data(frame), first);
+        assert_eq!(aac_data(next_aac(&mut stream)), second);
+        assert!(next_aac(&mut stream).is_none());
+    }
+
+    #[test]
+    fn test_aac_sync() {
+        let frame = adts(&[1, 2, 3]);
+        // A lone 0xFF is skipped and the byte after it is checked for the sync word
+        let mut data = vec![0x00, 0xFF, 0x12, 0xFF, 0x0F, 0xFF];
+        data.extend(&frame[..1]);
+        // The sync word can be split across the chunks
+        let mut stream = stream_of(vec![data, frame[1..].to_vec()]);
+        assert_eq!(aac_data(next_aac(&mut stream)), frame);
+        assert!(next_aac(&mut stream).is_none());
+    }
+
+    #[test]
+    fn test_aac_bad_lengths() {
+        // Many frames that are too short to have a header
+        let mut data = vec![];
+        for _ in 0..100_000 {
+            data.extend(adts_of_length(3, &[]));
+        }
+        let frame = adts(&[1]);
+        data.extend(&frame);
+        let mut stream = stream_of(vec![data]);
+        assert_eq!(aac_data(next_aac(&mut stream)), frame);
+
+        // A frame that is cut off
+        let mut stream = stream_of(vec![adts_of_length(100, &[1, 2])]);
+        assert!(next_aac(&mut stream).is_none());
+    }
+
+    #[test]
+    fn test_adpcm_blocks() {
+        // Two full blocks of 4 bytes of header and 4 of samples then a partial one
+        let data = (0..20).collect::<Vec<u8>>();
+        let mut stream = stream_of(vec![data[..3].to_vec(), data[3..].to_vec()]);
+
+        for expected in [&data[0..8], &data[8..16], &data[16..20]] {
+            match next_adpcm(&mut stream, 8, 8000) {
+                Some((BcMedia::Adpcm(adpcm), length)) => {
+                    assert_eq!(adpcm.data, expected);
+                    let samples = (expected.len() - 4) * 2 + 1;
+                    assert_eq!(length, Duration::from_secs_f32(samples as f32 / 8000.0));
+                }
+                other => panic!("Expected an ADPCM block, not {:?}", other),
+            }
+        }
+        assert!(next_adpcm(&mut stream, 8, 8000).is_none());
+    }
+
+    #[test]
+    fn test_adpcm_too_short() {
+        // Less than the block header is not a block
+        let mut stream = stream_of(vec![vec![1, 2, 3]]);
+        assert!(next_adpcm(&mut stream, 8, 8000).is_none());
+        // Nor is any block without a sample rate
+        let mut stream = stream_of(vec![vec![0; 8]]);
+        assert!(next_adpcm(&mut stream, 8, 0).is_none());
+    }
+}
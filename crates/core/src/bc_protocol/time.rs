@@ -120,8 +120,7 @@ impl BcCamera {
                     version: xml_ver(),
                     //osd_format: Some("MDY".to_string()),
                     time_format: Some(0),
-                    // Reolink uses positive seconds to indicate a negative UTC offset:
-                    time_zone: Some(-timestamp.offset().whole_seconds()),
+                    time_zone: Some(camera_time_zone(timestamp.offset())),
                     year: Some(timestamp.year()),
                     month: Some(timestamp.month().into()),
                     day: Some(timestamp.day()),
@@ -178,7 +177,93 @@ fn try_build_timestamp(
             .with_second(second)
             .ok_or(Error::TimeParse)?,
     )?;
-    let offset = UtcOffset::from_whole_seconds(timezone)?;
 
-    Ok(PrimitiveDateTime::new(date, time).assume_offset(offset))
+    Ok(PrimitiveDateTime::new(date, time).assume_offset(camera_offset(timezone)?))
+}
+
+/// The camera's `timeZone` for an `offset`
+///
+/// The camera stores the seconds west of UTC, like a POSIX TZ, which is the
+/// opposite sign of a UTC offset: UTC+7 is -25200 and UTC-5 is 18000
+fn camera_time_zone(offset: UtcOffset) -> i32 {
+    -offset.whole_seconds()
+}
+
+/// The offset of a camera's `timeZone`, see [`camera_time_zone`]
+fn camera_offset(time_zone: i32) -> std::result::Result<UtcOffset, crate::Error> {
+    Ok(UtcOffset::from_whole_seconds(-time_zone)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use indoc::indoc;
+    use time::macros::{datetime, offset};
+
+    #[test]
+    fn test_time_zone_sign() {
+        // UTC-5 such as New York is stored as 5 hours west
+        assert_eq!(camera_time_zone(offset!(-5)), 18000);
+        let time = try_build_timestamp(18000, 2024, 1, 15, 9, 30, 0).unwrap();
+        assert_eq!(time, datetime!(2024-01-15 9:30 -5));
+        assert_eq!(time, datetime!(2024-01-15 14:30 UTC));
+
+        // UTC+1 such as Paris is stored as 1 hour east
+        assert_eq!(camera_time_zone(offset!(+1)), -3600);
+        let time = try_build_timestamp(-3600, 2024, 1, 15, 9, 30, 0).unwrap();
+        assert_eq!(time, datetime!(2024-01-15 9:30 +1));
+        assert_eq!(time, datetime!(2024-01-15 8:30 UTC));
+
+        // What set_time writes is what get_time reads back
+        for offset in [offset!(-9:30), offset!(UTC), offset!(+5:45)] {
+            let time = try_build_timestamp(camera_time_zone(offset), 2024, 6, 1, 12, 0, 0).unwrap();
+            assert_eq!(time.offset(), offset);
+        }
+    }
+
+    #[test]
+    fn test_system_general_time() {
+        // The reply of a camera from the dissector docs
+        let sample = indoc!(
+            r#"<?xml version="1.0" encoding="UTF-8" ?>
+            <body>
+            <SystemGeneral version="1.1">
+            <timeZone>-25200</timeZone>
+            <osdFormat>DMY</osdFormat>
+            <year>2020</year>
+            <month>10</month>
+            <day>6</day>
+            <hour>18</hour>
+            <minute>36</minute>
+            <second>34</second>
+            <deviceId>0</deviceId>
+            <timeFormat>0</timeFormat>
+            <language>English</language>
+            <deviceName>Cammy02</deviceName>
+            </SystemGeneral>
+            <Norm version="1.1">
+            <norm>NTSC</norm>
+            </Norm>
+            </body>"#
+        );
+        let xml = BcXml::try_parse(sample.as_bytes()).unwrap();
+        let Some(SystemGeneral {
+            time_zone: Some(time_zone),
+            year: Some(year),
+            month: Some(month),
+            day: Some(day),
+            hour: Some(hour),
+            minute: Some(minute),
+            second: Some(second),
+            ..
+        }) = xml.system_general
+        else {
+            panic!("Expected the time in {:?}", xml);
+        };
+        let time = try_build_timestamp(time_zone, year, month, day, hour, minute, second).unwrap();
+        // 18:36 on a camera that is 7 hours east of UTC
+        assert_eq!(time, datetime!(2020-10-06 18:36:34 +7));
+        assert_eq!(time, datetime!(2020-10-06 11:36:34 UTC));
+        assert_eq!(camera_time_zone(time.offset()), time_zone);
+    }
 }
@@ -57,6 +57,8 @@
 //! For further commands see the [`bc_protocol::BcCamera`] struct.
 //!
 
+/// Contains the audio encoding for talk
+pub mod audio;
 /// Contains low level BC structures and formats
 pub mod bc;
 /// Contains high level interfaces for the camera
@@ -180,6 +180,7 @@ local message_types = {
   [228]="<Crop>",
   [229]="<Crop> (write)",
   [230]="<cropSnap>",
+  [231]="<AudioTask> (write)",
   [232]="<AudioTask>",
   [234]="UDP Keep Alive",
   [252]="<BatteryInfoList>",
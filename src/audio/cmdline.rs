@@ -0,0 +1,44 @@
+use clap::Parser;
+
+/// The audio command plays the alarm audio of the camera and chooses
+/// which audio it is
+#[derive(Parser, Debug)]
+pub struct Opt {
+    /// The name of the camera to play the audio on. Must be a name in the config
+    pub camera: String,
+
+    #[command(subcommand)]
+    pub cmd: AudioCommand,
+}
+
+#[derive(Parser, Debug)]
+pub enum AudioCommand {
+    /// Play the alarm audio, once if nothing else is given
+    Play {
+        /// Play it this many times
+        #[arg(long, conflicts_with_all = ["seconds", "continuous"])]
+        times: Option<u32>,
+        /// Play it over and over for this many seconds
+        #[arg(long, conflicts_with = "continuous")]
+        seconds: Option<u32>,
+        /// Play it over and over until `neolink audio CameraName stop`
+        #[arg(long)]
+        continuous: bool,
+    },
+    /// Stop the alarm audio that is playing
+    Stop,
+    /// Choose the audio that the alarm plays and its volume
+    ///
+    /// What is not given is left as it is on the camera
+    Select {
+        /// Play the custom audio that was recorded in the Reolink app
+        #[arg(long, conflicts_with = "tone")]
+        custom: bool,
+        /// Play this built in audio
+        #[arg(long)]
+        tone: Option<u32>,
+        /// The volume from 0 to 100
+        #[arg(long, value_parser = clap::value_parser!(u32).range(0..=100))]
+        volume: Option<u32>,
+    },
+}
@@ -0,0 +1,106 @@
+///
+/// # Neolink Audio
+///
+/// This module plays the alarm audio of the camera, such as the siren or
+/// the custom audio recorded in the Reolink app, and chooses which one
+/// it is
+///
+/// Uploading a custom audio is not supported yet, the message that the
+/// Reolink app uses for it is not known. Adding it needs a capture of the
+/// app uploading one
+///
+/// # Usage
+///
+/// ```bash
+/// # Play the alarm audio three times
+/// neolink audio --config=config.toml CameraName play --times 3
+/// # Play it for 30 seconds
+/// neolink audio --config=config.toml CameraName play --seconds 30
+/// # Stop it
+/// neolink audio --config=config.toml CameraName stop
+/// # Use the custom audio at 70% volume
+/// neolink audio --config=config.toml CameraName select --custom --volume 70
+/// ```
+///
+use anyhow::{Context, Result};
+use neolink_core::bc_protocol::AudioPlay;
+
+mod cmdline;
+
+use crate::common::NeoReactor;
+pub(crate) use cmdline::*;
+
+/// Entry point for the audio subcommand
+///
+/// Opt is the command line options
+pub(crate) async fn main(opt: Opt, reactor: NeoReactor) -> Result<()> {
+    let camera = reactor.get(&opt.camera).await?;
+
+    match opt.cmd {
+        AudioCommand::Play {
+            times,
+            seconds,
+            continuous,
+        } => {
+            let play = match (times, seconds, continuous) {
+                (_, _, true) => AudioPlay::Continuous,
+                (_, Some(seconds), _) => AudioPlay::Seconds(seconds),
+                (times, None, false) => AudioPlay::Times(times.unwrap_or(1)),
+            };
+            camera
+                .run_task(|cam| {
+                    Box::pin(async move {
+                        cam.play_audio(play)
+                            .await
+                            .context("Unable to play the audio")?;
+                        Ok(())
+                    })
+                })
+                .await?;
+        }
+        AudioCommand::Stop => {
+            camera
+                .run_task(|cam| {
+                    Box::pin(async move {
+                        cam.play_audio(AudioPlay::Stop)
+                            .await
+                            .context("Unable to stop the audio")?;
+                        Ok(())
+                    })
+                })
+                .await?;
+        }
+        AudioCommand::Select {
+            custom,
+            tone,
+            volume,
+        } => {
+            camera
+                .run_task(|cam| {
+                    Box::pin(async move {
+                        let mut audio_cfg = cam
+                            .get_audio_cfg()
+                            .await
+                            .context("Unable to get the audio")?;
+                        if custom {
+                            audio_cfg.audio_select = 1;
+                        }
+                        if let Some(tone) = tone {
+                            audio_cfg.audio_select = 0;
+                            audio_cfg.audio_list_id = tone;
+                        }
+                        if let Some(volume) = volume {
+                            audio_cfg.volume = volume;
+                        }
+                        cam.set_audio_cfg(audio_cfg)
+                            .await
+                            .context("Unable to set the audio")?;
+                        Ok(())
+                    })
+                })
+                .await?;
+        }
+    }
+
+    Ok(())
+}
@@ -11,6 +11,9 @@ use std::str::FromStr;
 pub struct Opt {
     #[arg(short, long, global = true, value_parser = PathBuf::from_str)]
     pub config: Option<PathBuf>,
+    /// Refuse to load a config file with unknown keys
+    #[arg(long, global = true)]
+    pub strict: bool,
     #[structopt(subcommand)]
     pub cmd: Option<Command>,
 }
@@ -21,9 +24,10 @@ pub enum Command {
     Rtsp(super::rtsp::Opt),
     StatusLight(super::statusled::Opt),
     Reboot(super::reboot::Opt),
+    RecordConfig(super::recordcfg::Opt),
     Pir(super::pir::Opt),
     Ptz(super::ptz::Opt),
-    #[cfg(feature = "gstreamer")]
+    Audio(super::audio::Opt),
     Talk(super::talk::Opt),
     Mqtt(super::mqtt::Opt),
     #[cfg(feature = "gstreamer")]
@@ -31,6 +35,10 @@ pub enum Command {
     #[cfg(feature = "gstreamer")]
     Image(super::image::Opt),
     Battery(super::battery::Opt),
+    Disk(super::disk::Opt),
     Services(super::services::Opt),
+    Ftp(super::ftp::Opt),
     Users(super::users::Opt),
+    Events(super::events::Opt),
+    Config(super::configtool::Opt),
 }
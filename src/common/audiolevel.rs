@@ -0,0 +1,172 @@
+//! Measures the sound that the camera hears
+//!
+//! With `[cameras.audio_level]` the audio of the sub stream is decoded and
+//! measured. The level is shared every `report_interval` so that it can be
+//! reported over MQTT. The sound is loud from when it goes over the
+//! `threshold` until it has stayed under it for `hold` seconds.
+//!
+//! ADPCM is decoded by neolink, AAC needs gstreamer
+use anyhow::anyhow;
+use tokio::{
+    sync::watch::Sender as WatchSender,
+    time::{interval, sleep, sleep_until, Duration, Instant, MissedTickBehavior},
+};
+
+use super::NeoInstance;
+use crate::{config::AudioLevelConfig, AnyResult};
+use neolink_core::{
+    audio::{decode_adpcm, AudioLevel, AudioLevelMeter},
+    bc_protocol::StreamKind,
+    bcmedia::model::BcMedia,
+};
+
+#[cfg(feature = "gstreamer")]
+mod gst;
+
+/// How long to wait before listening again after the stream fails
+const RETRY_DELAY: Duration = Duration::from_secs(30);
+
+/// The sound that the camera heard
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct AudioLevelState {
+    /// The level since the last report, or of the sound that made it loud
+    pub(crate) level: AudioLevel,
+    /// If there is a loud noise
+    pub(crate) loud: bool,
+}
+
+/// Measure the audio of the camera while the config has `audio_level`
+///
+/// This only returns if the config is dropped
+pub(crate) async fn run_audio_level(
+    instance: NeoInstance,
+    state: WatchSender<Option<AudioLevelState>>,
+) -> AnyResult<()> {
+    let mut config = instance.config().await?;
+    loop {
+        let (name, audio_level) = {
+            let config = config.borrow_and_update();
+            (config.name.clone(), config.audio_level.clone())
+        };
+        state.send_replace(None);
+        let Some(audio_level) = audio_level else {
+            config.changed().await?;
+            continue;
+        };
+
+        let failed = tokio::select! {
+            v = config.wait_for(|config| config.audio_level.as_ref() != Some(&audio_level)) => {
+                v?;
+                None
+            },
+            e = listen(&instance, &name, &audio_level, &state) => Some(e),
+        };
+        if let Some(e) = failed {
+            log::warn!("{name}: Stopped measuring the audio: {e:?}");
+            sleep(RETRY_DELAY).await;
+        }
+    }
+}
+
+/// Measure the audio of the sub stream until it fails
+async fn listen(
+    instance: &NeoInstance,
+    name: &str,
+    config: &AudioLevelConfig,
+    state: &WatchSender<Option<AudioLevelState>>,
+) -> anyhow::Error {
+    let mut stream = match instance.stream(StreamKind::Sub).await {
+        Ok(stream) => stream,
+        Err(e) => return e,
+    };
+    let mut decoder = Decoder::default();
+    let mut report = interval(Duration::from_secs(config.report_interval));
+    report.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    let hold = Duration::from_secs(config.hold);
+    let mut heard = AudioLevelMeter::default();
+    let mut last_loud: Option<Instant> = None;
+
+    log::info!("{name}: Measuring the audio");
+    loop {
+        tokio::select! {
+            media = stream.recv() => {
+                let Some(media) = media else {
+                    return anyhow!("The stream ended");
+                };
+                let samples = match decoder.decode(media) {
+                    Ok(samples) => samples,
+                    Err(e) => {
+                        // Stop streaming until the config changes as it will not get better
+                        drop(stream);
+                        log::warn!("{name}: Cannot measure the audio: {e:?}");
+                        return futures::future::pending().await;
+                    }
+                };
+                if samples.is_empty() {
+                    continue;
+                }
+                let level = AudioLevel::measure(&samples);
+                if level.rms >= config.threshold {
+                    if last_loud.is_none() {
+                        log::debug!("{name}: Loud noise at {:.1}dB", level.rms);
+                        state.send_replace(Some(AudioLevelState { level, loud: true }));
+                    }
+                    last_loud = Some(Instant::now());
+                }
+                heard.add(&samples);
+            },
+            // The noise ends as soon as it has been quiet for `hold`, not at the next report
+            _ = sleep_until(last_loud.map(|at| at + hold).unwrap_or_else(Instant::now)), if last_loud.is_some() => {
+                log::debug!("{name}: Quiet for {:?}", hold);
+                last_loud = None;
+                state.send_modify(|state| {
+                    if let Some(state) = state.as_mut() {
+                        state.loud = false;
+                    }
+                });
+            },
+            _ = report.tick() => {
+                state.send_replace(Some(AudioLevelState {
+                    level: heard.take(),
+                    loud: last_loud.is_some(),
+                }));
+            },
+        }
+    }
+}
+
+/// Decodes the audio of the stream into 16 bit PCM
+#[derive(Default)]
+struct Decoder {
+    #[cfg(feature = "gstreamer")]
+    aac: Option<gst::AacDecoder>,
+}
+
+impl Decoder {
+    /// The samples of the media, which are empty for video
+    fn decode(&mut self, media: BcMedia) -> AnyResult<Vec<i16>> {
+        match media {
+            BcMedia::Adpcm(adpcm) => Ok(decode_adpcm(&adpcm.data)),
+            BcMedia::Aac(aac) => self.decode_aac(aac.data),
+            _ => Ok(vec![]),
+        }
+    }
+
+    #[cfg(feature = "gstreamer")]
+    fn decode_aac(&mut self, data: Vec<u8>) -> AnyResult<Vec<i16>> {
+        if self.aac.is_none() {
+            self.aac = Some(gst::AacDecoder::new()?);
+        }
+        self.aac
+            .as_mut()
+            .expect("The decoder was created above")
+            .decode(data)
+    }
+
+    #[cfg(not(feature = "gstreamer"))]
+    fn decode_aac(&mut self, _data: Vec<u8>) -> AnyResult<Vec<i16>> {
+        Err(anyhow!(
+            "The camera sends AAC which needs neolink to be built with the gstreamer feature"
+        ))
+    }
+}
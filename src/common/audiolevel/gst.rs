@@ -0,0 +1,76 @@
+//! Decodes AAC with gstreamer
+use anyhow::{anyhow, Context};
+use gstreamer::{parse::launch, prelude::*, Buffer, ClockTime, Pipeline, State};
+use gstreamer_app::{AppSink, AppSrc};
+
+use crate::AnyResult;
+
+/// Decodes the ADTS AAC of the camera into 16 bit mono PCM
+///
+/// The decoder works in the background so the samples of a frame come out
+/// with a later one
+pub(super) struct AacDecoder {
+    pipeline: Pipeline,
+    appsrc: AppSrc,
+    appsink: AppSink,
+}
+
+impl AacDecoder {
+    pub(super) fn new() -> AnyResult<Self> {
+        gstreamer::init()
+            .context("Unable to start gstreamer ensure it and all plugins are installed")?;
+        let pipeline = launch(
+            "appsrc name=thesrc is-live=true format=time do-timestamp=true \
+            caps=\"audio/mpeg,mpegversion=4,stream-format=adts\" \
+            ! aacparse \
+            ! decodebin \
+            ! audioconvert \
+            ! audio/x-raw,format=S16LE,channels=1 \
+            ! appsink name=thesink sync=false",
+        )
+        .context("Failed to create the AAC decoder, check your gstreamer plugins")?
+        .dynamic_cast::<Pipeline>()
+        .map_err(|_| anyhow!("The AAC decoder should be a pipeline"))?;
+        let appsrc = pipeline
+            .by_name("thesrc")
+            .and_then(|src| src.dynamic_cast::<AppSrc>().ok())
+            .ok_or_else(|| anyhow!("Cannot find appsrc in gstreamer"))?;
+        let appsink = pipeline
+            .by_name("thesink")
+            .and_then(|sink| sink.dynamic_cast::<AppSink>().ok())
+            .ok_or_else(|| anyhow!("Cannot find appsink in gstreamer"))?;
+        pipeline.set_state(State::Playing)?;
+        Ok(Self {
+            pipeline,
+            appsrc,
+            appsink,
+        })
+    }
+
+    /// Send a frame to the decoder and take the samples that it has decoded
+    pub(super) fn decode(&mut self, data: Vec<u8>) -> AnyResult<Vec<i16>> {
+        self.appsrc
+            .push_buffer(Buffer::from_mut_slice(data))
+            .context("The AAC decoder stopped")?;
+        let mut samples = vec![];
+        while let Some(sample) = self.appsink.try_pull_sample(ClockTime::ZERO) {
+            if let Some(buffer) = sample.buffer() {
+                let map = buffer
+                    .map_readable()
+                    .map_err(|_| anyhow!("Failed to map the decoded audio"))?;
+                samples.extend(
+                    map.as_slice()
+                        .chunks_exact(2)
+                        .map(|bytes| i16::from_le_bytes([bytes[0], bytes[1]])),
+                );
+            }
+        }
+        Ok(samples)
+    }
+}
+
+impl Drop for AacDecoder {
+    fn drop(&mut self) {
+        let _ = self.pipeline.set_state(State::Null);
+    }
+}
@@ -5,6 +5,7 @@ use tokio::{
 };
 use tokio_util::sync::CancellationToken;
 
+use super::{maintain_settings, maintain_time, update_camera_time};
 use crate::{config::CameraConfig, utils::connect_and_login, AnyResult};
 use neolink_core::bc_protocol::BcCamera;
 
@@ -19,6 +20,7 @@ pub(crate) struct NeoCamThread {
     config: WatchReceiver<CameraConfig>,
     cancel: CancellationToken,
     camera_watch: WatchSender<Weak<BcCamera>>,
+    time_drift: WatchSender<Option<i64>>,
 }
 
 impl NeoCamThread {
@@ -26,6 +28,7 @@ impl NeoCamThread {
         watch_state_rx: WatchReceiver<NeoCamThreadState>,
         watch_config_rx: WatchReceiver<CameraConfig>,
         camera_watch_tx: WatchSender<Weak<BcCamera>>,
+        time_drift_tx: WatchSender<Option<i64>>,
         cancel: CancellationToken,
     ) -> Self {
         Self {
@@ -33,6 +36,7 @@ impl NeoCamThread {
             config: watch_config_rx,
             cancel,
             camera_watch: camera_watch_tx,
+            time_drift: time_drift_tx,
         }
     }
     async fn run_camera(&mut self, config: &CameraConfig) -> AnyResult<()> {
@@ -42,7 +46,7 @@ impl NeoCamThread {
         log::trace!("  - Connected");
 
         sleep(Duration::from_secs(2)).await; // Delay a little since some calls will error if camera is waking up
-        if let Err(e) = update_camera_time(&camera, &name, config.update_time).await {
+        if let Err(e) = update_camera_time(&camera, &name, config).await {
             log::warn!("Could not set camera time, (perhaps missing on this camera of your login in not an admin): {e:?}");
         }
         sleep(Duration::from_secs(2)).await; // Delay a little since some calls will error if camera is waking up
@@ -59,6 +63,8 @@ impl NeoCamThread {
                 v?;
                 Ok(())
             },
+            v = maintain_settings(&camera, self.config.clone()) => v,
+            v = maintain_time(&camera, self.config.clone(), &self.time_drift) => v,
             v = async {
                 let mut interval = interval(Duration::from_secs(5));
                 let mut missed_pings = 0;
@@ -124,7 +130,9 @@ impl NeoCamThread {
             let mut state = self.state.clone();
 
             let res = tokio::select! {
-                Ok(_) = config_rec.changed() => {
+                // Only reconnect if the change affects how we connect
+                Ok(_) = config_rec.wait_for(|new_config| !new_config.same_connection(&config)) => {
+                    log::info!("{name}: Connection settings changed, reconnecting");
                     None
                 }
                 Ok(_) = state.wait_for(|state| matches!(state, NeoCamThreadState::Disconnected)) => {
@@ -193,39 +201,3 @@ impl Drop for NeoCamThread {
         self.cancel.cancel();
     }
 }
-
-async fn update_camera_time(camera: &BcCamera, name: &str, update_time: bool) -> AnyResult<()> {
-    let cam_time = camera.get_time().await?;
-    let mut update = false;
-    if let Some(time) = cam_time {
-        log::info!("{}: Camera time is already set: {}", name, time);
-        if update_time {
-            update = true;
-        }
-    } else {
-        update = true;
-        log::warn!("{}: Camera has no time set, Updating", name);
-    }
-    if update {
-        use std::time::SystemTime;
-        let new_time = SystemTime::now();
-
-        log::info!("{}: Setting time to {:?}", name, new_time);
-        match camera.set_time(new_time.into()).await {
-            Ok(_) => {
-                let cam_time = camera.get_time().await?;
-                if let Some(time) = cam_time {
-                    log::info!("{}: Camera time is now set: {}", name, time);
-                }
-            }
-            Err(e) => {
-                log::error!(
-                    "{}: Camera did not accept new time (is user an admin?): Error: {:?}",
-                    name,
-                    e
-                );
-            }
-        }
-    }
-    Ok(())
-}
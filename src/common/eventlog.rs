@@ -0,0 +1,104 @@
+//! Keeps the camera events on disk
+//!
+//! With `[event_log]` every [`CameraEvent`] is appended as a line of JSON
+//! to the `path`. When the file grows past `max_size` it is renamed to
+//! `path.1`, the older files move up one and only `keep` of them are kept.
+//! `neolink events query` searches them.
+use anyhow::{anyhow, Context};
+use tokio::{
+    fs::{self, File, OpenOptions},
+    io::AsyncWriteExt,
+    sync::broadcast::{error::RecvError, Sender as BroadcastSender},
+};
+
+use super::{CameraEvent, NeoReactor};
+use crate::{config::EventLogConfig, AnyResult};
+
+/// Append the camera `events` to the event log in the config
+///
+/// This only returns if the reactor is dropped
+pub(crate) async fn run_event_log(
+    reactor: NeoReactor,
+    events: BroadcastSender<CameraEvent>,
+) -> AnyResult<()> {
+    let mut config = reactor.config().await?;
+    loop {
+        let Some(log_config) = config.borrow_and_update().event_log.clone() else {
+            config.changed().await?;
+            continue;
+        };
+
+        let mut events = events.subscribe();
+        let r = tokio::select! {
+            v = config.wait_for(|new| new.event_log.as_ref() != Some(&log_config)) => v.map(|_| ()).context("Event Log Config Watcher"),
+            v = async {
+                let (mut file, mut size) = open(&log_config).await?;
+                log::info!("Logging the events to {:?}", log_config.path);
+                loop {
+                    let event = match events.recv().await {
+                        Ok(event) => event,
+                        Err(RecvError::Lagged(missed)) => {
+                            log::warn!("The event log missed {missed} events");
+                            continue;
+                        }
+                        Err(RecvError::Closed) => return Err(anyhow!("The events have stopped")),
+                    };
+                    if !log_config.events.is_empty() && !log_config.events.contains(&event.event) {
+                        continue;
+                    }
+
+                    if size >= log_config.max_size * 1024 * 1024 {
+                        drop(file);
+                        rotate(&log_config).await?;
+                        (file, size) = open(&log_config).await?;
+                    }
+                    let mut line = serde_json::to_vec(&event)?;
+                    line.push(b'\n');
+                    file.write_all(&line).await?;
+                    file.flush().await?;
+                    size += line.len() as u64;
+                }
+            } => v.with_context(|| format!("Could not write the event log {:?}", log_config.path)),
+        };
+        if let Err(e) = r {
+            log::error!("{e:?}");
+            // Try again if the config is changed
+            config.changed().await?;
+        }
+    }
+}
+
+/// Open the log for appending, with its size
+async fn open(config: &EventLogConfig) -> AnyResult<(File, u64)> {
+    if let Some(dir) = config
+        .path
+        .parent()
+        .filter(|dir| !dir.as_os_str().is_empty())
+    {
+        fs::create_dir_all(dir).await?;
+    }
+    let file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&config.path)
+        .await?;
+    let size = file.metadata().await?.len();
+    Ok((file, size))
+}
+
+/// Move each file up one, dropping the oldest
+async fn rotate(config: &EventLogConfig) -> AnyResult<()> {
+    if config.keep == 0 {
+        fs::remove_file(&config.path).await?;
+        return Ok(());
+    }
+    let _ = fs::remove_file(config.rotated(config.keep)).await;
+    for index in (1..config.keep).rev() {
+        let from = config.rotated(index);
+        if fs::try_exists(&from).await.unwrap_or(false) {
+            fs::rename(&from, config.rotated(index + 1)).await?;
+        }
+    }
+    fs::rename(&config.path, config.rotated(1)).await?;
+    Ok(())
+}
@@ -0,0 +1,390 @@
+//! The events of the cameras
+//!
+//! A watcher on each camera turns motion and the AI from [`MdState`], the
+//! rings of a doorbell, the sound alarms of the camera, the push
+//! notifications, the battery level, whether the camera is connected and the
+//! loud noises that it hears into [`CameraEvent`]s. They are broadcast so
+//! that the webhooks, the event log and `neolink events` see the same events.
+use anyhow::anyhow;
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+use tokio::{
+    sync::{broadcast::Sender as BroadcastSender, mpsc::channel as mpsc},
+    task::JoinSet,
+    time::{interval, timeout, Duration, Instant, MissedTickBehavior},
+};
+
+use super::{MdState, NeoInstance, NeoReactor, SoundState, VisitorState};
+use crate::{
+    config::{Config, EventKind},
+    AnyResult,
+};
+
+/// How often the battery is checked
+const BATTERY_CHECK: Duration = Duration::from_secs(300);
+
+/// How long to wait for the UID before sending an event without it
+const UID_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub(crate) struct CameraEvent {
+    pub(crate) camera: String,
+    pub(crate) uid: Option<String>,
+    pub(crate) event: EventKind,
+    #[serde(with = "time::serde::rfc3339")]
+    pub(crate) timestamp: OffsetDateTime,
+    /// When the motion started for `motion_stop` and `ai`
+    #[serde(
+        default,
+        with = "time::serde::rfc3339::option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub(crate) started: Option<OffsetDateTime>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) ai_types: Vec<String>,
+    /// The text of the push notification
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) message: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) battery_percent: Option<u32>,
+    /// If the floodlight is on
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) floodlight: Option<bool>,
+    /// The RMS level in dB below full scale of a loud noise
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) rms_db: Option<f32>,
+    /// The peak level in dB below full scale of a loud noise
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) peak_db: Option<f32>,
+}
+
+impl CameraEvent {
+    pub(crate) fn new(camera: &str, event: EventKind) -> Self {
+        Self {
+            camera: camera.to_string(),
+            uid: None,
+            event,
+            timestamp: OffsetDateTime::now_utc(),
+            started: None,
+            ai_types: vec![],
+            message: None,
+            battery_percent: None,
+            floodlight: None,
+            rms_db: None,
+            peak_db: None,
+        }
+    }
+}
+
+/// What [`watch_events`] watches
+pub(crate) struct EventSources {
+    /// Only watch the cameras while this is true for the config so that
+    /// nothing is polled when there is no one to tell
+    pub(crate) active: fn(&Config) -> bool,
+    /// Only these cameras, all of the enabled ones if empty
+    pub(crate) cameras: Vec<String>,
+    /// Report the floodlight status as well. This takes over the camera's
+    /// floodlight messages so it cannot be used alongside MQTT
+    pub(crate) floodlight: bool,
+}
+
+/// Broadcast the events of the cameras on `events`
+///
+/// This only returns if the reactor is dropped
+pub(crate) async fn watch_events(
+    reactor: NeoReactor,
+    events: BroadcastSender<CameraEvent>,
+    sources: EventSources,
+) -> AnyResult<()> {
+    let mut config = reactor.config().await?;
+    let current = |config: &Config| {
+        (sources.active)(config).then(|| {
+            config
+                .cameras
+                .iter()
+                .filter(|camera| camera.enabled)
+                .filter(|camera| {
+                    sources.cameras.is_empty() || sources.cameras.contains(&camera.name)
+                })
+                .map(|camera| camera.name.clone())
+                .collect::<Vec<_>>()
+        })
+    };
+    loop {
+        let cameras = current(&config.borrow_and_update());
+        let mut watchers = JoinSet::new();
+        for name in cameras.iter().flatten() {
+            let instance = reactor.get(name).await?;
+            let name = name.clone();
+            let events = events.clone();
+            let floodlight = sources.floodlight;
+            watchers.spawn(async move {
+                if let Err(e) = watch_camera(&instance, &name, &events, floodlight).await {
+                    log::warn!("{name}: Stopped watching for events: {e:?}");
+                }
+            });
+        }
+
+        let r = config.wait_for(|new| current(new) != cameras).await;
+        watchers.abort_all();
+        r?;
+    }
+}
+
+/// Send the events of the camera to `events`
+async fn watch_camera(
+    instance: &NeoInstance,
+    name: &str,
+    events: &BroadcastSender<CameraEvent>,
+    floodlight: bool,
+) -> AnyResult<()> {
+    let uid = tokio::sync::OnceCell::new();
+    let send = |mut event: CameraEvent| {
+        let uid = &uid;
+        async move {
+            event.uid = uid
+                .get_or_try_init(|| async {
+                    timeout(UID_TIMEOUT, instance.uid())
+                        .await
+                        .map_err(|_| anyhow!("Timed out"))?
+                })
+                .await
+                .ok()
+                .cloned();
+            // It is fine if no one is listening yet
+            let _ = events.send(event);
+            AnyResult::Ok(())
+        }
+    };
+
+    let motion = async {
+        let mut md = instance.motion().await?;
+        // The state when we start is not an event
+        let (mut started, mut seen) = match &*md.borrow_and_update() {
+            MdState::Start(at, ai_types) => (Some(*at), ai_types.clone()),
+            _ => (None, vec![]),
+        };
+        loop {
+            md.changed().await?;
+            let state = md.borrow_and_update().clone();
+            match state {
+                MdState::Start(at, ai_types) => {
+                    if started.is_none() {
+                        let mut event = CameraEvent::new(name, EventKind::MotionStart);
+                        event.ai_types = ai_types.clone();
+                        send(event).await?;
+                    }
+                    let since = *started.get_or_insert(at);
+                    // Each AI class only counts once until the motion stops
+                    if ai_types.iter().any(|ai_type| !seen.contains(ai_type)) {
+                        let mut event = CameraEvent::new(name, EventKind::Ai);
+                        event.started = Some(wall_clock(since));
+                        event.ai_types = ai_types.clone();
+                        send(event).await?;
+                    }
+                    for ai_type in ai_types {
+                        if !seen.contains(&ai_type) {
+                            seen.push(ai_type);
+                        }
+                    }
+                }
+                MdState::Stop(_) => {
+                    if let Some(since) = started {
+                        let mut event = CameraEvent::new(name, EventKind::MotionStop);
+                        event.started = Some(wall_clock(since));
+                        event.ai_types = seen.clone();
+                        send(event).await?;
+                    }
+                    started = None;
+                    seen.clear();
+                }
+                MdState::Unknown => {}
+            }
+        }
+    };
+
+    let visitor = async {
+        let mut visitor = instance.visitor().await?;
+        visitor.borrow_and_update();
+        loop {
+            visitor.changed().await?;
+            let ring = matches!(*visitor.borrow_and_update(), VisitorState::Start(_));
+            if ring {
+                send(CameraEvent::new(name, EventKind::Visitor)).await?;
+            }
+        }
+    };
+
+    let sound = async {
+        let mut sound = instance.sound().await?;
+        sound.borrow_and_update();
+        loop {
+            sound.changed().await?;
+            let heard = matches!(*sound.borrow_and_update(), SoundState::Start(_));
+            if heard {
+                send(CameraEvent::new(name, EventKind::Sound)).await?;
+            }
+        }
+    };
+
+    let loud_noise = async {
+        let mut audio_level = instance.audio_level().await?;
+        let mut loud = audio_level
+            .borrow_and_update()
+            .map(|state| state.loud)
+            .unwrap_or(false);
+        loop {
+            audio_level.changed().await?;
+            let state = *audio_level.borrow_and_update();
+            let now_loud = state.map(|state| state.loud).unwrap_or(false);
+            if let Some(state) = state.filter(|_| now_loud && !loud) {
+                let mut event = CameraEvent::new(name, EventKind::LoudNoise);
+                event.rms_db = Some(state.level.rms);
+                event.peak_db = Some(state.level.peak);
+                send(event).await?;
+            }
+            loud = now_loud;
+        }
+    };
+
+    let connection = async {
+        let mut camera_watch = instance.camera();
+        let mut online = camera_watch.borrow_and_update().upgrade().is_some();
+        loop {
+            camera_watch.changed().await?;
+            let now_online = camera_watch.borrow_and_update().upgrade().is_some();
+            let event = match (online, now_online) {
+                (false, true) => EventKind::Connected,
+                (true, false) => EventKind::Disconnected,
+                _ => continue,
+            };
+            online = now_online;
+            send(CameraEvent::new(name, event)).await?;
+        }
+    };
+
+    let battery = async {
+        let mut ticker = interval(BATTERY_CHECK);
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        let mut last = None;
+        loop {
+            ticker.tick().await;
+            let level = instance
+                .run_passive_task(|cam| {
+                    Box::pin(async move { Ok(cam.battery_info().await?.battery_percent) })
+                })
+                .await;
+            match level {
+                Ok(level) if last != Some(level) => {
+                    last = Some(level);
+                    let mut event = CameraEvent::new(name, EventKind::Battery);
+                    event.battery_percent = Some(level);
+                    send(event).await?;
+                }
+                Ok(_) => {}
+                Err(e) => match e.downcast_ref::<neolink_core::Error>() {
+                    Some(neolink_core::Error::MissingAbility { .. })
+                    | Some(neolink_core::Error::CameraServiceUnavailable { .. }) => {
+                        log::debug!("{name}: No battery for the events: {e}");
+                        return futures::future::pending().await;
+                    }
+                    _ => log::debug!("{name}: Could not check the battery: {e:?}"),
+                },
+            }
+        }
+    };
+
+    let floodlight = async {
+        if !floodlight {
+            return futures::future::pending().await;
+        }
+        let (tx, mut rx) = mpsc(100);
+        let listen = async {
+            loop {
+                let r = instance
+                    .run_passive_task(|cam| {
+                        let tx = tx.clone();
+                        Box::pin(async move {
+                            let mut statuses = cam.listen_on_flightlight().await?;
+                            while let Some(list) = statuses.recv().await {
+                                for status in list.floodlight_status_list.iter() {
+                                    tx.send(status.status != 0).await?;
+                                }
+                            }
+                            AnyResult::Ok(())
+                        })
+                    })
+                    .await;
+                if let Err(e) = r {
+                    break e;
+                }
+            }
+        };
+        let forward = async {
+            while let Some(on) = rx.recv().await {
+                let mut event = CameraEvent::new(name, EventKind::Floodlight);
+                event.floodlight = Some(on);
+                send(event).await?;
+            }
+            AnyResult::Ok(())
+        };
+        tokio::select! {
+            e = listen => match e.downcast::<neolink_core::Error>() {
+                Ok(neolink_core::Error::UnintelligibleReply { .. }) => {
+                    log::debug!("{name}: No floodlight for the events");
+                    futures::future::pending().await
+                }
+                Ok(e) => Err(e.into()),
+                Err(e) => Err(e),
+            },
+            v = forward => v,
+        }
+    };
+
+    tokio::select! {
+        v = motion => v,
+        v = visitor => v,
+        v = sound => v,
+        v = loud_noise => v,
+        v = floodlight => v,
+        v = connection => v,
+        v = battery => v,
+        v = watch_push(instance, name, send) => v,
+    }
+}
+
+#[cfg(feature = "pushnoti")]
+async fn watch_push<F, Fut>(instance: &NeoInstance, name: &str, send: F) -> AnyResult<()>
+where
+    F: Fn(CameraEvent) -> Fut,
+    Fut: std::future::Future<Output = AnyResult<()>>,
+{
+    let mut pn = instance.push_notifications().await?;
+    pn.borrow_and_update();
+    loop {
+        pn.changed().await?;
+        let message = pn
+            .borrow_and_update()
+            .as_ref()
+            .map(|noti| noti.message.clone());
+        if let Some(message) = message {
+            let mut event = CameraEvent::new(name, EventKind::PushNotification);
+            event.message = Some(message);
+            send(event).await?;
+        }
+    }
+}
+
+#[cfg(not(feature = "pushnoti"))]
+async fn watch_push<F, Fut>(_instance: &NeoInstance, _name: &str, _send: F) -> AnyResult<()>
+where
+    F: Fn(CameraEvent) -> Fut,
+    Fut: std::future::Future<Output = AnyResult<()>>,
+{
+    futures::future::pending().await
+}
+
+/// The wall clock time of an `Instant`
+fn wall_clock(instant: Instant) -> OffsetDateTime {
+    OffsetDateTime::now_utc() - instant.elapsed()
+}
@@ -0,0 +1,456 @@
+//! Reports the health of neolink
+//!
+//! `/healthz` is for liveness: it fails if the reactor or any camera
+//! thread stops responding. `/readyz` is for readiness: it also fails while
+//! a server is not running or a camera that should be connected is not.
+//!
+//! When run under systemd it can also notify READY and ping the WATCHDOG.
+//! The pings stop as soon as neolink is no longer live so that systemd
+//! restarts it.
+use anyhow::{anyhow, Context};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use tokio::{
+    io::{AsyncRead, AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+    time::{interval, timeout, Duration, MissedTickBehavior},
+};
+
+use super::{NeoCamThreadState, NeoReactor, ServiceState};
+use crate::{config::HealthConfig, AnyResult};
+
+/// The largest request we will read
+const MAX_REQUEST: usize = 8 * 1024;
+
+#[derive(Serialize, Debug)]
+struct HealthReport {
+    live: bool,
+    ready: bool,
+    reactor: &'static str,
+    services: BTreeMap<&'static str, String>,
+    cameras: BTreeMap<String, CameraHealth>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+struct CameraHealth {
+    /// The state the camera thread wants to be in, unset if it did not respond
+    state: Option<&'static str>,
+    /// True if we are currently logged in to the camera
+    online: bool,
+    responding: bool,
+}
+
+/// Serve the health endpoints and notify systemd, as set in the `config`
+///
+/// This only returns on error
+pub(crate) async fn serve_health(config: HealthConfig, reactor: NeoReactor) -> AnyResult<()> {
+    let max_delay = Duration::from_millis(config.timeout);
+    tokio::try_join!(
+        async {
+            if config.enable_http {
+                serve_http(&config.bind_addr, config.bind_port, &reactor, max_delay).await
+            } else {
+                futures::future::pending().await
+            }
+        },
+        async {
+            if config.sd_notify {
+                notify_systemd(&reactor, max_delay).await
+            } else {
+                futures::future::pending().await
+            }
+        },
+    )?;
+    Ok(())
+}
+
+/// What a health request asks for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Route {
+    Healthz,
+    Readyz,
+    NotFound,
+    MethodNotAllowed,
+}
+
+impl HealthReport {
+    /// The report of the `services` and the `cameras`, None if the reactor stalled
+    fn new(
+        services: BTreeMap<&'static str, ServiceState>,
+        cameras: Option<BTreeMap<String, CameraHealth>>,
+    ) -> Self {
+        let services = services
+            .into_iter()
+            .map(|(name, state)| {
+                let state = match state {
+                    ServiceState::Starting => "starting".to_string(),
+                    ServiceState::Running => "running".to_string(),
+                    ServiceState::Failed(e) => format!("failed: {e}"),
+                };
+                (name, state)
+            })
+            .collect::<BTreeMap<_, _>>();
+        let services_ready = services.values().all(|state| state == "running");
+
+        let Some(cameras) = cameras else {
+            return HealthReport {
+                live: false,
+                ready: false,
+                reactor: "stalled",
+                services,
+                cameras: Default::default(),
+            };
+        };
+        let live = cameras.values().all(|camera| camera.responding);
+        // A camera that was disconnected on purpose, such as by idle disconnect, is still ready
+        let cameras_ready = cameras
+            .values()
+            .all(|camera| camera.online || camera.state == Some("disconnected"));
+        HealthReport {
+            live,
+            ready: live && services_ready && cameras_ready,
+            reactor: "ok",
+            services,
+            cameras,
+        }
+    }
+
+    /// The HTTP status of `/healthz` or `/readyz` for this report
+    fn status(&self, route: Route) -> &'static str {
+        let ok = match route {
+            Route::Healthz => self.live,
+            _ => self.ready,
+        };
+        if ok {
+            "200 OK"
+        } else {
+            "503 Service Unavailable"
+        }
+    }
+}
+
+async fn check(reactor: &NeoReactor, max_delay: Duration) -> HealthReport {
+    let services = reactor.services().get();
+    let instances = match timeout(max_delay, reactor.instances()).await {
+        Ok(Ok(instances)) => instances,
+        _ => return HealthReport::new(services, None),
+    };
+
+    let mut cameras = BTreeMap::new();
+    for (name, instance) in instances {
+        let state = timeout(max_delay, instance.get_state())
+            .await
+            .ok()
+            .and_then(Result::ok);
+        cameras.insert(
+            name,
+            CameraHealth {
+                state: state.map(|state| match state {
+                    NeoCamThreadState::Connected => "connected",
+                    NeoCamThreadState::Disconnected => "disconnected",
+                }),
+                online: instance.camera().borrow().upgrade().is_some(),
+                responding: state.is_some(),
+            },
+        );
+    }
+
+    HealthReport::new(services, Some(cameras))
+}
+
+async fn serve_http(
+    bind_addr: &str,
+    bind_port: u16,
+    reactor: &NeoReactor,
+    max_delay: Duration,
+) -> AnyResult<()> {
+    let listener = TcpListener::bind((bind_addr, bind_port))
+        .await
+        .with_context(|| format!("Could not bind the health server to {bind_addr}:{bind_port}"))?;
+    log::info!("Starting health server at {bind_addr}:{bind_port}");
+
+    loop {
+        let (stream, _) = listener.accept().await?;
+        let reactor = reactor.clone();
+        tokio::task::spawn(async move {
+            if let Err(e) = handle_request(stream, &reactor, max_delay).await {
+                log::debug!("Health request failed: {e:?}");
+            }
+        });
+    }
+}
+
+async fn handle_request(
+    mut stream: TcpStream,
+    reactor: &NeoReactor,
+    max_delay: Duration,
+) -> AnyResult<()> {
+    let request = timeout(max_delay, read_request(&mut stream))
+        .await
+        .map_err(|_| anyhow!("Timed out reading the request"))??;
+
+    let (status, body) = match route(&request) {
+        route @ (Route::Healthz | Route::Readyz) => {
+            let report = check(reactor, max_delay).await;
+            (report.status(route), serde_json::to_string(&report)?)
+        }
+        Route::NotFound => ("404 Not Found", r#"{"error":"not found"}"#.to_string()),
+        Route::MethodNotAllowed => (
+            "405 Method Not Allowed",
+            r#"{"error":"method not allowed"}"#.to_string(),
+        ),
+    };
+
+    let response = format!(
+        "HTTP/1.1 {status}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await?;
+    Ok(())
+}
+
+/// Read the request up to the end of its headers or [`MAX_REQUEST`] bytes
+async fn read_request<R: AsyncRead + Unpin>(stream: &mut R) -> AnyResult<Vec<u8>> {
+    let mut request = Vec::new();
+    let mut buf = [0u8; 1024];
+    while !request.windows(4).any(|w| w == b"\r\n\r\n") && request.len() < MAX_REQUEST {
+        let read = stream.read(&mut buf).await?;
+        if read == 0 {
+            break;
+        }
+        request.extend_from_slice(&buf[..read]);
+    }
+    Ok(request)
+}
+
+/// Find the route from the request line
+fn route(request: &[u8]) -> Route {
+    let request = String::from_utf8_lossy(request);
+    let mut parts = request
+        .lines()
+        .next()
+        .unwrap_or_default()
+        .split_whitespace();
+    let (method, path) = (parts.next(), parts.next());
+    // Ignore any query
+    let path = path.map(|path| path.split('?').next().unwrap_or_default());
+
+    match (method, path) {
+        (Some("GET"), Some("/healthz")) => Route::Healthz,
+        (Some("GET"), Some("/readyz")) => Route::Readyz,
+        (Some("GET"), _) => Route::NotFound,
+        _ => Route::MethodNotAllowed,
+    }
+}
+
+/// Tell systemd when we are ready and keep pinging its watchdog while we are live
+async fn notify_systemd(reactor: &NeoReactor, max_delay: Duration) -> AnyResult<()> {
+    if std::env::var_os("NOTIFY_SOCKET").is_none() {
+        log::warn!(
+            "sd_notify is enabled but NOTIFY_SOCKET is not set, is the service Type=notify?"
+        );
+        return futures::future::pending().await;
+    }
+
+    // Wait for the reactor to respond before we are ready
+    while !check(reactor, max_delay).await.live {
+        tokio::time::sleep(max_delay).await;
+    }
+    sd_notify("READY=1\nSTATUS=Running")?;
+    log::debug!("Notified systemd that we are ready");
+
+    let Some(period) = watchdog_period(
+        std::env::var("WATCHDOG_USEC").ok().as_deref(),
+        std::env::var("WATCHDOG_PID").ok().as_deref(),
+        std::process::id(),
+    ) else {
+        return futures::future::pending().await;
+    };
+    let mut ticker = interval(period);
+    ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    loop {
+        ticker.tick().await;
+        let report = check(reactor, max_delay).await;
+        if report.live {
+            sd_notify("WATCHDOG=1")?;
+        } else {
+            log::error!("Not pinging the systemd watchdog, neolink is not responding: {report:?}");
+        }
+    }
+}
+
+/// Half of the `WATCHDOG_USEC` that systemd gave us, if its `WATCHDOG_PID` is our `pid`
+fn watchdog_period(usec: Option<&str>, watchdog_pid: Option<&str>, pid: u32) -> Option<Duration> {
+    if let Some(watchdog_pid) = watchdog_pid.and_then(|p| p.parse::<u32>().ok()) {
+        if watchdog_pid != pid {
+            return None;
+        }
+    }
+    let usec = usec?.parse::<u64>().ok().filter(|usec| *usec > 0)?;
+    Some(Duration::from_micros(usec / 2))
+}
+
+#[cfg(unix)]
+fn sd_notify(message: &str) -> AnyResult<()> {
+    let socket_path =
+        std::env::var_os("NOTIFY_SOCKET").ok_or_else(|| anyhow!("NOTIFY_SOCKET is not set"))?;
+    sd_notify_to(&socket_path, message)
+}
+
+#[cfg(unix)]
+fn sd_notify_to(socket_path: &std::ffi::OsStr, message: &str) -> AnyResult<()> {
+    use std::os::unix::net::UnixDatagram;
+
+    let socket = UnixDatagram::unbound()?;
+    let path = socket_path.to_string_lossy();
+    if let Some(abstract_name) = path.strip_prefix('@') {
+        #[cfg(target_os = "linux")]
+        {
+            use std::os::linux::net::SocketAddrExt;
+            let addr = std::os::unix::net::SocketAddr::from_abstract_name(abstract_name)?;
+            socket.send_to_addr(message.as_bytes(), &addr)?;
+        }
+        #[cfg(not(target_os = "linux"))]
+        return Err(anyhow!(
+            "Abstract NOTIFY_SOCKET {abstract_name} is only supported on linux"
+        ));
+    } else {
+        socket.send_to(message.as_bytes(), socket_path)?;
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn sd_notify(_: &str) -> AnyResult<()> {
+    Err(anyhow!("sd_notify is only supported on unix"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn camera(state: Option<&'static str>, online: bool) -> CameraHealth {
+        CameraHealth {
+            state,
+            online,
+            responding: state.is_some(),
+        }
+    }
+
+    #[test]
+    fn test_route() {
+        assert_eq!(route(b"GET /healthz HTTP/1.1\r\n\r\n"), Route::Healthz);
+        assert_eq!(route(b"GET /readyz?verbose HTTP/1.1\r\n"), Route::Readyz);
+        assert_eq!(route(b"GET /metrics HTTP/1.1\r\n\r\n"), Route::NotFound);
+        assert_eq!(route(b"GET\r\n\r\n"), Route::NotFound);
+        assert_eq!(
+            route(b"POST /healthz HTTP/1.1\r\n\r\n"),
+            Route::MethodNotAllowed
+        );
+        for malformed in [&b""[..], b"\r\n\r\n", b"\xff\xfe /healthz", b"get /healthz"] {
+            assert_eq!(
+                route(malformed),
+                Route::MethodNotAllowed,
+                "{:?}",
+                String::from_utf8_lossy(malformed)
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_read_request() {
+        let (mut client, mut server) = tokio::io::duplex(64 * 1024);
+        client
+            .write_all(b"GET /healthz HTTP/1.1\r\nHost: neolink\r\n\r\n")
+            .await
+            .unwrap();
+        let request = read_request(&mut server).await.unwrap();
+        assert_eq!(route(&request), Route::Healthz);
+
+        // A request without an end stops at the limit rather than reading forever
+        let (mut client, mut server) = tokio::io::duplex(64 * 1024);
+        client.write_all(b"GET /readyz HTTP/1.1\r\n").await.unwrap();
+        client.write_all(&[b'a'; 4 * MAX_REQUEST]).await.unwrap();
+        let request = read_request(&mut server).await.unwrap();
+        assert!(request.len() >= MAX_REQUEST && request.len() < MAX_REQUEST + 1024);
+        assert_eq!(route(&request), Route::Readyz);
+
+        // The client closing early is not an error
+        let (mut client, mut server) = tokio::io::duplex(1024);
+        client.write_all(b"GET /heal").await.unwrap();
+        drop(client);
+        let request = read_request(&mut server).await.unwrap();
+        assert_eq!(route(&request), Route::NotFound);
+    }
+
+    #[test]
+    fn test_status() {
+        let running = BTreeMap::from([("rtsp", ServiceState::Running)]);
+        let cameras = BTreeMap::from([
+            ("Garage".to_string(), camera(Some("connected"), true)),
+            // Disconnected on purpose
+            ("Porch".to_string(), camera(Some("disconnected"), false)),
+        ]);
+
+        let report = HealthReport::new(running.clone(), Some(cameras.clone()));
+        assert_eq!(report.status(Route::Healthz), "200 OK");
+        assert_eq!(report.status(Route::Readyz), "200 OK");
+
+        // Live but not ready while a service starts
+        let starting = BTreeMap::from([("rtsp", ServiceState::Starting)]);
+        let report = HealthReport::new(starting, Some(cameras.clone()));
+        assert_eq!(report.status(Route::Healthz), "200 OK");
+        assert_eq!(report.status(Route::Readyz), "503 Service Unavailable");
+
+        // Or while a camera that should be connected is not
+        let mut connecting = cameras.clone();
+        connecting.insert("Garage".to_string(), camera(Some("connected"), false));
+        let report = HealthReport::new(running.clone(), Some(connecting));
+        assert_eq!(report.status(Route::Healthz), "200 OK");
+        assert_eq!(report.status(Route::Readyz), "503 Service Unavailable");
+
+        // A camera thread that does not respond is not live
+        let mut stuck = cameras;
+        stuck.insert("Garage".to_string(), camera(None, true));
+        let report = HealthReport::new(running.clone(), Some(stuck));
+        assert_eq!(report.status(Route::Healthz), "503 Service Unavailable");
+        assert_eq!(report.status(Route::Readyz), "503 Service Unavailable");
+
+        let report = HealthReport::new(running, None);
+        assert_eq!(report.reactor, "stalled");
+        assert_eq!(report.status(Route::Healthz), "503 Service Unavailable");
+    }
+
+    #[test]
+    fn test_watchdog_period() {
+        assert_eq!(
+            watchdog_period(Some("30000000"), None, 42),
+            Some(Duration::from_secs(15))
+        );
+        assert_eq!(
+            watchdog_period(Some("30000000"), Some("42"), 42),
+            Some(Duration::from_secs(15))
+        );
+        // The watchdog is for another process
+        assert_eq!(watchdog_period(Some("30000000"), Some("7"), 42), None);
+        assert_eq!(watchdog_period(None, Some("42"), 42), None);
+        assert_eq!(watchdog_period(Some("0"), None, 42), None);
+        assert_eq!(watchdog_period(Some("thirty"), None, 42), None);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_sd_notify() {
+        use std::os::{linux::net::SocketAddrExt, unix::net::*};
+
+        let name = format!("neolink-test-{}", uuid::Uuid::new_v4());
+        let addr = SocketAddr::from_abstract_name(&name).unwrap();
+        let systemd = UnixDatagram::bind_addr(&addr).unwrap();
+        sd_notify_to(format!("@{name}").as_ref(), "READY=1\nSTATUS=Running").unwrap();
+
+        let mut buf = [0u8; 64];
+        let len = systemd.recv(&mut buf).unwrap();
+        assert_eq!(&buf[..len], b"READY=1\nSTATUS=Running");
+    }
+}
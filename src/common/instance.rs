@@ -9,15 +9,22 @@ use futures::TryFutureExt;
 use std::sync::{Arc, Weak};
 use tokio::{
     sync::{
-        mpsc::Sender as MpscSender, oneshot::channel as oneshot, watch::Receiver as WatchReceiver,
+        mpsc::{Receiver as MpscReceiver, Sender as MpscSender},
+        oneshot::channel as oneshot,
+        watch::Receiver as WatchReceiver,
     },
     time::{sleep, Duration},
 };
 use tokio_util::sync::CancellationToken;
 
-use super::{MdState, NeoCamCommand, NeoCamThreadState, Permit};
+use super::{
+    AudioLevelState, MdState, NeoCamCommand, NeoCamThreadState, Permit, SoundState, VisitorState,
+};
 use crate::{config::CameraConfig, AnyResult, Result};
-use neolink_core::bc_protocol::BcCamera;
+use neolink_core::{
+    bc_protocol::{BcCamera, StreamKind},
+    bcmedia::model::BcMedia,
+};
 
 #[cfg(feature = "gstreamer")]
 mod gst;
@@ -238,6 +245,24 @@ impl NeoInstance {
         Ok(instance_rx.await?)
     }
 
+    /// The visitors of a doorbell, this never changes for other cameras
+    pub(crate) async fn visitor(&self) -> Result<WatchReceiver<VisitorState>> {
+        let (instance_tx, instance_rx) = oneshot();
+        self.camera_control
+            .send(NeoCamCommand::Visitor(instance_tx))
+            .await?;
+        Ok(instance_rx.await?)
+    }
+
+    /// The sounds heard by the sound detection of the camera
+    pub(crate) async fn sound(&self) -> Result<WatchReceiver<SoundState>> {
+        let (instance_tx, instance_rx) = oneshot();
+        self.camera_control
+            .send(NeoCamCommand::Sound(instance_tx))
+            .await?;
+        Ok(instance_rx.await?)
+    }
+
     pub(crate) async fn config(&self) -> Result<WatchReceiver<CameraConfig>> {
         let (instance_tx, instance_rx) = oneshot();
         self.camera_control
@@ -246,6 +271,64 @@ impl NeoInstance {
         Ok(instance_rx.await?)
     }
 
+    /// The last measured drift of the camera's clock in seconds, positive if it is ahead
+    pub(crate) async fn time_drift(&self) -> Result<WatchReceiver<Option<i64>>> {
+        let (instance_tx, instance_rx) = oneshot();
+        self.camera_control
+            .send(NeoCamCommand::TimeDrift(instance_tx))
+            .await?;
+        Ok(instance_rx.await?)
+    }
+
+    /// The sound that the camera hears, if `audio_level` is set and it has been measured
+    pub(crate) async fn audio_level(&self) -> Result<WatchReceiver<Option<AudioLevelState>>> {
+        let (instance_tx, instance_rx) = oneshot();
+        self.camera_control
+            .send(NeoCamCommand::AudioLevel(instance_tx))
+            .await?;
+        Ok(instance_rx.await?)
+    }
+
+    /// Streams a camera source
+    pub(crate) async fn stream(&self, stream: StreamKind) -> AnyResult<MpscReceiver<BcMedia>> {
+        let (media_tx, media_rx) = tokio::sync::mpsc::channel(100);
+        let config = self.config().await?.borrow().clone();
+        let strict = config.strict;
+        let thread_camera = self.clone();
+        tokio::task::spawn(
+            tokio::task::spawn(async move {
+                thread_camera
+                    .run_task(move |cam| {
+                        let media_tx = media_tx.clone();
+                        Box::pin(async move {
+                            let mut media_stream = cam.start_video(stream, 0, strict).await?;
+                            log::trace!("Camera started");
+                            while let Ok(media) = media_stream.get_data().await? {
+                                media_tx.send(media).await?;
+                            }
+                            AnyResult::Ok(())
+                        })
+                    })
+                    .await
+            })
+            .and_then(|res| async move {
+                log::debug!("Camera finished streaming: {res:?}");
+                Ok(())
+            }),
+        );
+
+        Ok(media_rx)
+    }
+
+    /// The UID of the camera, this waits until it has connected once
+    pub(crate) async fn uid(&self) -> Result<String> {
+        let (reply_tx, reply_rx) = oneshot();
+        self.camera_control
+            .send(NeoCamCommand::GetUid(reply_tx))
+            .await?;
+        Ok(reply_rx.await?)
+    }
+
     pub(crate) fn camera(&self) -> WatchReceiver<Weak<BcCamera>> {
         self.camera_watch.clone()
     }
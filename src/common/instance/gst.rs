@@ -41,7 +41,7 @@ impl NeoInstance {
             let mut md_permit = {
                 let md_state = md.borrow_and_update().clone();
                 match md_state {
-                    MdState::Start(_) => {
+                    MdState::Start(..) => {
                         log::info!("{name}::{stream:?}: Starting with Motion");
                         counter.create_activated().await?
                     }
@@ -60,7 +60,7 @@ impl NeoInstance {
                             Ok(_) => {
                                 let md_state: MdState = md.borrow_and_update().clone();
                                 match md_state {
-                                    MdState::Start(_) => {
+                                    MdState::Start(..) => {
                                         log::info!("{thread_name}::{stream:?}: Motion Started");
                                         md_permit.activate().await?;
                                     }
@@ -173,35 +173,4 @@ impl NeoInstance {
 
         Ok(media_rx)
     }
-
-    /// Streams a camera source
-    pub(crate) async fn stream(&self, stream: StreamKind) -> AnyResult<MpscReceiver<BcMedia>> {
-        let (media_tx, media_rx) = tokio::sync::mpsc::channel(100);
-        let config = self.config().await?.borrow().clone();
-        let strict = config.strict;
-        let thread_camera = self.clone();
-        tokio::task::spawn(
-            tokio::task::spawn(async move {
-                thread_camera
-                    .run_task(move |cam| {
-                        let media_tx = media_tx.clone();
-                        Box::pin(async move {
-                            let mut media_stream = cam.start_video(stream, 0, strict).await?;
-                            log::trace!("Camera started");
-                            while let Ok(media) = media_stream.get_data().await? {
-                                media_tx.send(media).await?;
-                            }
-                            AnyResult::Ok(())
-                        })
-                    })
-                    .await
-            })
-            .and_then(|res| async move {
-                log::debug!("Camera finished streaming: {res:?}");
-                Ok(())
-            }),
-        );
-
-        Ok(media_rx)
-    }
 }
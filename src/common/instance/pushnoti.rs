@@ -4,14 +4,6 @@ use crate::common::PushNoti;
 use tokio::sync::watch::channel as watch;
 
 impl NeoInstance {
-    pub(crate) async fn uid(&self) -> Result<String> {
-        let (reply_tx, reply_rx) = oneshot();
-        self.camera_control
-            .send(NeoCamCommand::GetUid(reply_tx))
-            .await?;
-        Ok(reply_rx.await?)
-    }
-
     pub(crate) async fn push_notifications(&self) -> Result<WatchReceiver<Option<PushNoti>>> {
         let uid = self.uid().await?;
         let (instance_tx, instance_rx) = oneshot();
@@ -1,5 +1,9 @@
 //! This thread will listen to motion messages
 //! from the camera.
+//!
+//! Doorbells also report a visitor on the same messages, it is kept on a
+//! watch of its own so that a ring is not mistaken for motion. The same goes
+//! for the sound detection of the camera.
 
 use anyhow::Context;
 use std::sync::Arc;
@@ -15,11 +19,30 @@ use tokio_util::sync::CancellationToken;
 
 use super::NeoInstance;
 use crate::{AnyResult, Result};
-use neolink_core::bc_protocol::MotionStatus;
+use neolink_core::bc_protocol::{MotionStatus, SoundStatus, VisitorStatus};
 
 #[derive(Clone, Debug)]
 #[allow(dead_code)]
 pub(crate) enum MdState {
+    /// Motion started, with any AI types such as `"people"` that were detected
+    Start(Instant, Vec<String>),
+    Stop(Instant),
+    Unknown,
+}
+
+#[derive(Clone, Debug)]
+#[allow(dead_code)]
+pub(crate) enum VisitorState {
+    /// Someone pressed the button of the doorbell
+    Start(Instant),
+    Stop(Instant),
+    Unknown,
+}
+
+#[derive(Clone, Debug)]
+#[allow(dead_code)]
+pub(crate) enum SoundState {
+    /// The sound detection of the camera heard something
     Start(Instant),
     Stop(Instant),
     Unknown,
@@ -27,6 +50,8 @@ pub(crate) enum MdState {
 
 pub(crate) struct NeoCamMdThread {
     md_watcher: Arc<WatchSender<MdState>>,
+    visitor_watcher: Arc<WatchSender<VisitorState>>,
+    sound_watcher: Arc<WatchSender<SoundState>>,
     md_request_rx: MpscReceiver<MdRequest>,
     cancel: CancellationToken,
     instance: NeoInstance,
@@ -39,8 +64,14 @@ impl NeoCamMdThread {
     ) -> Result<Self> {
         let (md_watcher, _) = watch(MdState::Unknown);
         let md_watcher = Arc::new(md_watcher);
+        let (visitor_watcher, _) = watch(VisitorState::Unknown);
+        let visitor_watcher = Arc::new(visitor_watcher);
+        let (sound_watcher, _) = watch(SoundState::Unknown);
+        let sound_watcher = Arc::new(sound_watcher);
         Ok(Self {
             md_watcher,
+            visitor_watcher,
+            sound_watcher,
             md_request_rx,
             cancel: CancellationToken::new(),
             instance,
@@ -50,6 +81,8 @@ impl NeoCamMdThread {
     pub(crate) async fn run(&mut self) -> Result<()> {
         let thread_cancel = self.cancel.clone();
         let watcher = self.md_watcher.clone();
+        let visitor_watcher = self.visitor_watcher.clone();
+        let sound_watcher = self.sound_watcher.clone();
         let md_instance = self.instance.clone();
         tokio::select! {
             _ = thread_cancel.cancelled() => {
@@ -63,6 +96,16 @@ impl NeoCamMdThread {
                         } => {
                           let _ = sender.send(self.md_watcher.subscribe());
                         },
+                        MdRequest::Visitor {
+                            sender
+                        } => {
+                          let _ = sender.send(self.visitor_watcher.subscribe());
+                        },
+                        MdRequest::Sound {
+                            sender
+                        } => {
+                          let _ = sender.send(self.sound_watcher.subscribe());
+                        },
                     }
                 }
                 Ok(())
@@ -71,6 +114,8 @@ impl NeoCamMdThread {
                 loop {
                     let r: AnyResult<()> = md_instance.run_passive_task(|cam| {
                         let watcher = watcher.clone();
+                        let visitor_watcher = visitor_watcher.clone();
+                        let sound_watcher = sound_watcher.clone();
                         Box::pin(
                         async move {
                             let mut md = cam.listen_on_motion().await.with_context(|| "Error in getting MD listen_on_motion")?;
@@ -79,7 +124,7 @@ impl NeoCamMdThread {
                                 match event {
                                     MotionStatus::Start(at) => {
                                         watcher.send_replace(
-                                            MdState::Start(at.into())
+                                            MdState::Start(at.into(), md.ai_types().to_vec())
                                         );
                                     }
                                     MotionStatus::Stop(at) => {
@@ -89,6 +134,18 @@ impl NeoCamMdThread {
                                     }
                                     MotionStatus::NoChange(_) => {},
                                 }
+                                for visitor in md.take_visitor_events() {
+                                    visitor_watcher.send_replace(match visitor {
+                                        VisitorStatus::Start(at) => VisitorState::Start(at.into()),
+                                        VisitorStatus::Stop(at) => VisitorState::Stop(at.into()),
+                                    });
+                                }
+                                for sound in md.take_sound_events() {
+                                    sound_watcher.send_replace(match sound {
+                                        SoundStatus::Start(at) => SoundState::Start(at.into()),
+                                        SoundStatus::Stop(at) => SoundState::Stop(at.into()),
+                                    });
+                                }
                             }
                         }
                     )}).await;
@@ -113,4 +170,10 @@ pub(crate) enum MdRequest {
     Get {
         sender: OneshotSender<WatchReceiver<MdState>>,
     },
+    Visitor {
+        sender: OneshotSender<WatchReceiver<VisitorState>>,
+    },
+    Sound {
+        sender: OneshotSender<WatchReceiver<SoundState>>,
+    },
 }
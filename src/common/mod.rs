@@ -1,17 +1,40 @@
+mod audiolevel;
 mod camthread;
+mod eventlog;
+mod events;
+mod health;
 mod instance;
 mod mdthread;
 mod neocam;
 #[cfg(feature = "pushnoti")]
 mod pushnoti;
 mod reactor;
+mod reload;
+mod rules;
+mod schedule;
+mod services;
+mod settings;
+mod sun;
+mod timesync;
 mod usecounter;
+mod webhooks;
 
+pub(crate) use audiolevel::*;
 pub(crate) use camthread::*;
+pub(crate) use eventlog::*;
+pub(crate) use events::*;
+pub(crate) use health::*;
 pub(crate) use instance::*;
 pub(crate) use mdthread::*;
 pub(crate) use neocam::*;
 #[cfg(feature = "pushnoti")]
 pub(crate) use pushnoti::*;
 pub(crate) use reactor::*;
+pub(crate) use reload::*;
+pub(crate) use rules::*;
+pub(crate) use schedule::*;
+pub(crate) use services::*;
+pub(crate) use settings::*;
+pub(crate) use timesync::*;
 pub(crate) use usecounter::*;
+pub(crate) use webhooks::*;
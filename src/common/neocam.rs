@@ -20,8 +20,8 @@ use tokio_stream::wrappers::ReceiverStream;
 use tokio_util::sync::CancellationToken;
 
 use super::{
-    MdRequest, MdState, NeoCamMdThread, NeoCamThread, NeoCamThreadState, NeoInstance, Permit,
-    UseCounter,
+    run_audio_level, run_schedule, AudioLevelState, MdRequest, MdState, NeoCamMdThread,
+    NeoCamThread, NeoCamThreadState, NeoInstance, Permit, SoundState, UseCounter, VisitorState,
 };
 #[cfg(feature = "pushnoti")]
 use super::{PnRequest, PushNoti};
@@ -33,6 +33,8 @@ pub(crate) enum NeoCamCommand {
     HangUp,
     Instance(OneshotSender<Result<NeoInstance>>),
     Motion(OneshotSender<WatchReceiver<MdState>>),
+    Visitor(OneshotSender<WatchReceiver<VisitorState>>),
+    Sound(OneshotSender<WatchReceiver<SoundState>>),
     Config(OneshotSender<WatchReceiver<CameraConfig>>),
     Disconnect(OneshotSender<()>),
     Connect(OneshotSender<()>),
@@ -41,6 +43,8 @@ pub(crate) enum NeoCamCommand {
     #[cfg(feature = "pushnoti")]
     PushNoti(OneshotSender<WatchReceiver<Option<PushNoti>>>),
     GetUid(OneshotSender<String>),
+    TimeDrift(OneshotSender<WatchReceiver<Option<i64>>>),
+    AudioLevel(OneshotSender<WatchReceiver<Option<AudioLevelState>>>),
 }
 /// The underlying camera binding
 pub(crate) struct NeoCam {
@@ -62,6 +66,8 @@ impl NeoCam {
         let (md_request_tx, md_request_rx) = mpsc(100);
         let (state_tx, state_rx) = watch(NeoCamThreadState::Connected);
         let (uid_tx, uid_rx) = watch(config.camera_uid.clone());
+        let (time_drift_tx, time_drift_rx) = watch(None);
+        let (audio_level_tx, audio_level_rx) = watch(None);
 
         let set = JoinSet::new();
         let users = UseCounter::new().await;
@@ -114,6 +120,20 @@ impl NeoCam {
                                     }
                                 ).await?;
                             },
+                            NeoCamCommand::Visitor(sender) => {
+                                md_request_tx.send(
+                                    MdRequest::Visitor {
+                                        sender,
+                                    }
+                                ).await?;
+                            },
+                            NeoCamCommand::Sound(sender) => {
+                                md_request_tx.send(
+                                    MdRequest::Sound {
+                                        sender,
+                                    }
+                                ).await?;
+                            },
                             NeoCamCommand::Config(sender) => {
                                 let _ = sender.send(thread_watch_config_rx.clone());
                             },
@@ -151,6 +171,12 @@ impl NeoCam {
                                     AnyResult::Ok(())
                                 });
                             },
+                            NeoCamCommand::TimeDrift(sender) => {
+                                let _ = sender.send(time_drift_rx.clone());
+                            },
+                            NeoCamCommand::AudioLevel(sender) => {
+                                let _ = sender.send(audio_level_rx.clone());
+                            },
                         }
                     }
                     Ok(())
@@ -176,6 +202,7 @@ impl NeoCam {
             state_rx,
             thread_watch_config_rx,
             camera_watch_tx,
+            time_drift_tx,
             me.cancel.clone(),
         )
         .await;
@@ -228,6 +255,26 @@ impl NeoCam {
             }
         });
 
+        // This thread switches the lights on the schedules in the config
+        let schedule_instance = instance.subscribe().await?;
+        let schedule_cancel = me.cancel.clone();
+        me.set.spawn(async move {
+            tokio::select! {
+                _ = schedule_cancel.cancelled() => AnyResult::Ok(()),
+                v = run_schedule(schedule_instance) => v,
+            }
+        });
+
+        // This thread measures the audio when `audio_level` is set
+        let audio_level_instance = instance.subscribe().await?;
+        let audio_level_cancel = me.cancel.clone();
+        me.set.spawn(async move {
+            tokio::select! {
+                _ = audio_level_cancel.cancelled() => AnyResult::Ok(()),
+                v = run_audio_level(audio_level_instance, audio_level_tx) => v,
+            }
+        });
+
         // This thread will update the UID by asking the camera.
         // We cache this in the uid_rx
         let uid_instance = instance.clone();
@@ -312,7 +359,7 @@ impl NeoCam {
                 v = async {
                     let mut md = md_permit_instance.motion().await.with_context(|| "Unable to acquire motion watcher")?;
                     loop{
-                        md.wait_for(|md| matches!(md, MdState::Start(_))).await.with_context(|| "MD Watcher lost")?;
+                        md.wait_for(|md| matches!(md, MdState::Start(..))).await.with_context(|| "MD Watcher lost")?;
                         let _permit = md_permit_instance.permit().await.with_context(|| "Unuable to acquire motion permit")?;
                         md.wait_for(|md| matches!(md, MdState::Stop(_))).await.with_context(|| "MD Watcher lost")?;
                         // Try waiting for 30s
@@ -320,7 +367,7 @@ impl NeoCam {
                         // loop early to reaquire the permit
                         tokio::select!{
                             _ = sleep(Duration::from_secs(30)) => {},
-                            v = md.wait_for(|md| matches!(md, MdState::Start(_))) => {v.with_context(|| "MD Watcher lost")?;},
+                            v = md.wait_for(|md| matches!(md, MdState::Start(..))) => {v.with_context(|| "MD Watcher lost")?;},
                         }
                     }
                 } => {
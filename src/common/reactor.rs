@@ -17,7 +17,7 @@ use tokio::{
 };
 use tokio_util::sync::CancellationToken;
 
-use super::{NeoCam, NeoInstance};
+use super::{NeoCam, NeoInstance, Services};
 #[cfg(feature = "pushnoti")]
 use crate::common::PushNotiThread;
 use crate::{config::Config, AnyResult, Result};
@@ -28,6 +28,7 @@ enum NeoReactorCommand {
     Config(OneshotSender<WatchReceiver<Config>>),
     UpdateConfig(Config, OneshotSender<Result<()>>),
     Get(String, OneshotSender<Result<Option<NeoInstance>>>),
+    Instances(OneshotSender<Result<Vec<(String, NeoInstance)>>>),
 }
 
 /// Reactor handles the collection of cameras
@@ -35,6 +36,7 @@ enum NeoReactorCommand {
 pub(crate) struct NeoReactor {
     cancel: CancellationToken,
     commander: MpscSender<NeoReactorCommand>,
+    services: Services,
     set: Option<Arc<JoinSet<AnyResult<()>>>>,
 }
 
@@ -96,11 +98,24 @@ impl NeoReactor {
                                 };
                                 let _ = sender.send(new);
                             },
+                            NeoReactorCommand::Instances(sender) => {
+                                let mut running = vec![];
+                                for (name, instance) in instances.iter() {
+                                    running.push((name.clone(), instance.subscribe().await?));
+                                }
+                                let _ = sender.send(Ok(running));
+                            },
                             NeoReactorCommand::UpdateConfig(new_conf, reply) => {
                                 // Shutdown or Notify instances of a change
                                 let mut names = new_conf.cameras.iter().filter(|cam_conf| cam_conf.enabled).map(|cam_conf| (cam_conf.name.clone(), cam_conf.clone())).collect::<HashMap<_,_>>();
                                 // Remove those no longer in the config
-                                instances.retain(|name, _| names.contains_key(name));
+                                instances.retain(|name, _| {
+                                    let keep = names.contains_key(name);
+                                    if !keep {
+                                        log::info!("{name}: Removed from config");
+                                    }
+                                    keep
+                                });
                                 for (name, instance) in instances.iter() {
                                     if let Some(conf) = names.remove(name) {
                                         let _ = instance.update_config(conf).await;
@@ -153,6 +168,7 @@ impl NeoReactor {
         Self {
             cancel,
             commander: commad_tx,
+            services: Services::new(),
             set: Some(Arc::new(set)),
         }
     }
@@ -169,6 +185,21 @@ impl NeoReactor {
             .ok_or(anyhow!("Camera `{name}` not found in config"))
     }
 
+    /// Get the cameras that have been started, without starting any others
+    pub(crate) async fn instances(&self) -> Result<Vec<(String, NeoInstance)>> {
+        let (sender_tx, sender_rx) = oneshot();
+        self.commander
+            .send(NeoReactorCommand::Instances(sender_tx))
+            .await?;
+
+        sender_rx.await?
+    }
+
+    /// The states of the servers such as RTSP and MQTT
+    pub(crate) fn services(&self) -> Services {
+        self.services.clone()
+    }
+
     pub(crate) async fn config(&self) -> Result<WatchReceiver<Config>> {
         let (sender_tx, sender_rx) = oneshot();
         self.commander
@@ -0,0 +1,88 @@
+//! Reloads the config from disk
+//!
+//! The config file is polled for changes and also re-read
+//! on SIGHUP. A new config is only passed on to the
+//! [`NeoReactor`] if it parses and validates, otherwise
+//! the error is logged and the current config is kept
+use std::path::PathBuf;
+use tokio::time::{interval, Duration, MissedTickBehavior};
+
+use super::NeoReactor;
+use crate::{config::Config, AnyResult};
+
+/// How often the config file is checked for changes
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Watch the config file at `path` and apply any changes to the reactor
+///
+/// This only returns if the reactor is shutting down
+pub(crate) async fn watch_config_file(
+    path: PathBuf,
+    reactor: NeoReactor,
+    strict: bool,
+) -> AnyResult<()> {
+    let mut config_rx = reactor.config().await?;
+    let mut last_contents = std::fs::read_to_string(&path).ok();
+
+    let mut poll = interval(POLL_INTERVAL);
+    poll.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+    #[cfg(unix)]
+    let mut hangup = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::hangup())?;
+
+    loop {
+        #[cfg(unix)]
+        let forced = tokio::select! {
+            _ = poll.tick() => false,
+            _ = hangup.recv() => {
+                log::info!("Received SIGHUP: Reloading config from {:?}", path);
+                true
+            },
+        };
+        #[cfg(not(unix))]
+        let forced = {
+            poll.tick().await;
+            false
+        };
+
+        let contents = match std::fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(e) => {
+                if forced {
+                    log::error!("Could not read the config file {:?}: {e}", path);
+                }
+                continue;
+            }
+        };
+        if !forced && last_contents.as_ref() == Some(&contents) {
+            continue;
+        }
+        let new_config = Config::parse(&contents, &path, strict);
+        last_contents = Some(contents);
+
+        let new_config = match new_config {
+            Ok(config) => config,
+            Err(e) => {
+                log::error!("Keeping the current config, the new one was rejected: {e:?}");
+                continue;
+            }
+        };
+
+        let curr_config = config_rx.borrow_and_update().clone();
+        if curr_config == new_config {
+            log::debug!("Config file {:?} unchanged", path);
+            continue;
+        }
+        if curr_config.bind_addr != new_config.bind_addr
+            || curr_config.bind_port != new_config.bind_port
+        {
+            log::warn!("Changes to bind or bind_port require a restart of neolink to take effect");
+        }
+        if curr_config.health != new_config.health {
+            log::warn!("Changes to health require a restart of neolink to take effect");
+        }
+
+        reactor.update_config(new_config).await?;
+        log::info!("Reloaded config from {:?}", path);
+    }
+}
@@ -0,0 +1,589 @@
+//! Runs the `[[rules]]` of the config
+//!
+//! Each rule waits for its trigger, such as motion on one camera, and then
+//! runs its actions which can be on any of the cameras. This works without
+//! a home automation server so simple reflexes, like a floodlight when a
+//! person is seen, keep working when it is down.
+//!
+//! The actions are run through [`NeoInstance::run_task`] so they still
+//! happen if the camera is reconnecting.
+use anyhow::{anyhow, Context};
+use std::{collections::HashMap, sync::Arc};
+use time::OffsetDateTime;
+use tokio::{
+    sync::mpsc::{channel as mpsc, Sender as MpscSender},
+    task::JoinSet,
+    time::{interval, sleep, Duration, Instant, MissedTickBehavior},
+};
+
+use super::{next_time, schedule_timezone, MdState, NeoCamThreadState, NeoInstance, NeoReactor};
+use crate::{
+    config::{RuleAction, RuleConfig, RuleTrigger, ScheduleTime, TimeZone},
+    mqtt::MqttInstance,
+    AnyResult,
+};
+
+/// How often the battery is checked for the `battery_below` trigger
+const BATTERY_CHECK: Duration = Duration::from_secs(300);
+
+/// The longest that we sleep for a schedule, so that changes to the host clock are noticed
+const MAX_SLEEP: i64 = 3600;
+
+/// Run the rules in the config
+///
+/// MQTT actions are published on `mqtt`, without it they are skipped.
+/// This only returns if the reactor is dropped
+pub(crate) async fn run_rules(reactor: NeoReactor, mqtt: Option<MqttInstance>) -> AnyResult<()> {
+    let mqtt = mqtt.map(Arc::new);
+    let mut config = reactor.config().await?;
+    loop {
+        let rules = config.borrow_and_update().rules.clone();
+        if rules.iter().any(|rule| rule.enabled) {
+            log::info!(
+                "Running {} rules",
+                rules.iter().filter(|rule| rule.enabled).count()
+            );
+        }
+
+        let (fire_tx, mut fire_rx) = mpsc(100);
+        let mut triggers = JoinSet::new();
+        for (index, rule) in rules.iter().enumerate().filter(|(_, rule)| rule.enabled) {
+            let reactor = reactor.clone();
+            let rule = rule.clone();
+            let fire_tx = fire_tx.clone();
+            triggers.spawn(async move {
+                if let Err(e) = watch_trigger(&reactor, &rule.when, index, &fire_tx).await {
+                    log::warn!(
+                        "Rule {:?}: Stopped watching for its trigger: {e:?}",
+                        rule.name
+                    );
+                }
+            });
+        }
+        drop(fire_tx);
+
+        let mut actions = JoinSet::new();
+        let mut cooldowns = Cooldowns::default();
+        let r = tokio::select! {
+            v = config.wait_for(|new| new.rules != rules) => v.map(|_| ()),
+            _ = async {
+                while let Some((index, event)) = fire_rx.recv().await {
+                    let rule: &RuleConfig = &rules[index];
+                    let cooldown = Duration::from_secs(rule.cooldown);
+                    if !cooldowns.start(index, cooldown, Instant::now()) {
+                        log::debug!("Rule {:?}: Skipped {event} during the cooldown", rule.name);
+                        continue;
+                    }
+
+                    log::info!("Rule {:?}: Triggered by {event}", rule.name);
+                    for action in rule.then.iter() {
+                        let action = action.clone();
+                        let reactor = reactor.clone();
+                        let mqtt = mqtt.clone();
+                        let name = rule.name.clone();
+                        actions.spawn(async move {
+                            if let Err(e) = run_action(&reactor, mqtt.as_deref(), &action).await {
+                                log::warn!("Rule {name:?}: Could not run {action:?}: {e:?}");
+                            }
+                        });
+                    }
+                    // Tidy up the finished actions
+                    while actions.try_join_next().is_some() {}
+                }
+                // Every trigger stopped so wait for the rules to change
+                futures::future::pending::<()>().await;
+            } => Ok(()),
+        };
+        triggers.abort_all();
+        r.with_context(|| "Rules Config Watcher")?;
+    }
+}
+
+/// When each rule last ran, so that it does not run again during its cooldown
+#[derive(Default)]
+struct Cooldowns(HashMap<usize, Instant>);
+
+impl Cooldowns {
+    /// Start the rule at `index` at `now`, false if it is still cooling down
+    fn start(&mut self, index: usize, cooldown: Duration, now: Instant) -> bool {
+        let cooling = self
+            .0
+            .get(&index)
+            .is_some_and(|last| now.saturating_duration_since(*last) < cooldown);
+        if !cooling {
+            self.0.insert(index, now);
+        }
+        !cooling
+    }
+}
+
+/// Follows the motion of a camera for the `motion_start`, `motion_stop` and `ai` triggers
+struct MotionTrigger {
+    moving: bool,
+    /// The AI classes seen since the motion started, each only counts once
+    seen: Vec<String>,
+}
+
+impl MotionTrigger {
+    /// The `state` when we start is not an event
+    fn new(state: &MdState) -> Self {
+        let mut motion = Self {
+            moving: false,
+            seen: vec![],
+        };
+        motion.follow(state);
+        motion
+    }
+
+    /// The event of the `trigger` on camera `name` if the motion changed to `state`
+    fn update(&mut self, trigger: &RuleTrigger, name: &str, state: &MdState) -> Option<String> {
+        let event = match (trigger, state) {
+            (RuleTrigger::MotionStart { .. }, MdState::Start(..)) if !self.moving => {
+                Some(format!("motion on {name}"))
+            }
+            (RuleTrigger::MotionStop { .. }, MdState::Stop(..)) if self.moving => {
+                Some(format!("motion stopping on {name}"))
+            }
+            (RuleTrigger::Ai { class, .. }, MdState::Start(_, ai_types))
+                if ai_types.contains(class) && !self.seen.contains(class) =>
+            {
+                Some(format!("{class} on {name}"))
+            }
+            _ => None,
+        };
+        self.follow(state);
+        event
+    }
+
+    fn follow(&mut self, state: &MdState) {
+        match state {
+            MdState::Start(_, ai_types) => {
+                self.moving = true;
+                self.seen.extend(ai_types.iter().cloned());
+            }
+            _ => {
+                self.moving = false;
+                self.seen.clear();
+            }
+        }
+    }
+}
+
+/// Follows the battery level for the `battery_below` trigger
+#[derive(Default)]
+struct BatteryTrigger {
+    low: bool,
+}
+
+impl BatteryTrigger {
+    /// The event if the battery of camera `name` fell below `percent`
+    ///
+    /// Only when it falls below, or if it is already low when we start
+    fn update(&mut self, name: &str, level: u32, percent: u32) -> Option<String> {
+        let was_low = std::mem::replace(&mut self.low, level < percent);
+        (self.low && !was_low).then(|| format!("the battery of {name} at {level}%"))
+    }
+}
+
+/// Follows the connection to a camera for the `disconnected` trigger
+struct DisconnectTrigger {
+    online: bool,
+}
+
+impl DisconnectTrigger {
+    /// True if the camera just went offline, on purpose or not
+    fn went_offline(&mut self, now_online: bool) -> bool {
+        let was_online = std::mem::replace(&mut self.online, now_online);
+        was_online && !now_online
+    }
+
+    /// The event of camera `name` going offline while its thread wants the `state`
+    ///
+    /// Disconnecting on purpose, such as when idle, is not an event
+    fn event(name: &str, state: NeoCamThreadState) -> Option<String> {
+        matches!(state, NeoCamThreadState::Connected).then(|| format!("{name} disconnecting"))
+    }
+}
+
+/// How long to sleep for the `schedule` trigger at `now`, and if it happens after that
+///
+/// Long sleeps are cut short so that changes to the host clock are noticed
+fn schedule_wait(
+    at: &ScheduleTime,
+    location: (f64, f64),
+    timezone: Option<&TimeZone>,
+    now: i64,
+) -> (Duration, bool) {
+    let wait = next_time(at, location, timezone, now) - now;
+    if wait > MAX_SLEEP {
+        (Duration::from_secs(MAX_SLEEP as u64), false)
+    } else {
+        (Duration::from_secs(wait.max(0) as u64), true)
+    }
+}
+
+/// Send `(index, description)` on `fire` every time that the `trigger` happens
+async fn watch_trigger(
+    reactor: &NeoReactor,
+    trigger: &RuleTrigger,
+    index: usize,
+    fire: &MpscSender<(usize, String)>,
+) -> AnyResult<()> {
+    let send = |event: String| async move {
+        fire.send((index, event))
+            .await
+            .map_err(|_| anyhow!("The rules have stopped"))
+    };
+    let camera = match trigger.camera() {
+        Some(name) => Some(reactor.get(name).await?),
+        None => None,
+    };
+
+    match (trigger, camera) {
+        (RuleTrigger::MotionStart { camera: name }, Some(camera))
+        | (RuleTrigger::MotionStop { camera: name }, Some(camera))
+        | (RuleTrigger::Ai { camera: name, .. }, Some(camera)) => {
+            let mut md = camera.motion().await?;
+            let mut motion = MotionTrigger::new(&md.borrow_and_update());
+            loop {
+                md.changed().await?;
+                let state = md.borrow_and_update().clone();
+                if let Some(event) = motion.update(trigger, name, &state) {
+                    send(event).await?;
+                }
+            }
+        }
+        (RuleTrigger::PushNotification { camera: name }, Some(camera)) => {
+            watch_push(name, &camera, send).await
+        }
+        (
+            RuleTrigger::BatteryBelow {
+                camera: name,
+                percent,
+            },
+            Some(camera),
+        ) => {
+            let mut ticker = interval(BATTERY_CHECK);
+            ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            let mut battery = BatteryTrigger::default();
+            loop {
+                ticker.tick().await;
+                let level = camera
+                    .run_passive_task(|cam| {
+                        Box::pin(async move { Ok(cam.battery_info().await?.battery_percent) })
+                    })
+                    .await;
+                match level {
+                    Ok(level) => {
+                        if let Some(event) = battery.update(name, level, *percent) {
+                            send(event).await?;
+                        }
+                    }
+                    Err(e) => match e.downcast_ref::<neolink_core::Error>() {
+                        Some(neolink_core::Error::MissingAbility { .. })
+                        | Some(neolink_core::Error::CameraServiceUnavailable { .. }) => {
+                            return Err(e.context(format!("{name} has no battery")));
+                        }
+                        _ => log::debug!("{name}: Could not check the battery: {e:?}"),
+                    },
+                }
+            }
+        }
+        (RuleTrigger::Disconnected { camera: name }, Some(camera)) => {
+            let mut camera_watch = camera.camera();
+            let mut connection = DisconnectTrigger {
+                online: camera_watch.borrow_and_update().upgrade().is_some(),
+            };
+            loop {
+                camera_watch.changed().await?;
+                let now_online = camera_watch.borrow_and_update().upgrade().is_some();
+                if connection.went_offline(now_online) {
+                    let state = camera.get_state().await?;
+                    if let Some(event) = DisconnectTrigger::event(name, state) {
+                        send(event).await?;
+                    }
+                }
+            }
+        }
+        (RuleTrigger::Schedule { at, .. }, camera) => {
+            let mut config = match camera.as_ref() {
+                Some(camera) => Some(camera.config().await?),
+                None => None,
+            };
+            loop {
+                let (location, timezone) = match config.as_mut() {
+                    Some(config) => {
+                        let config = config.borrow_and_update();
+                        let location = config
+                            .schedule
+                            .as_ref()
+                            .map(|schedule| (schedule.latitude, schedule.longitude))
+                            .unwrap_or((0.0, 0.0));
+                        (location, schedule_timezone(&config))
+                    }
+                    None => ((0.0, 0.0), None),
+                };
+                let now = OffsetDateTime::now_utc().unix_timestamp();
+                let (wait, happens) = schedule_wait(at, location, timezone.as_ref(), now);
+                sleep(wait).await;
+                if !happens {
+                    continue;
+                }
+                send(format!("the time being {at}")).await?;
+                // Move past the time so that it runs once
+                sleep(Duration::from_secs(1)).await;
+            }
+        }
+        (trigger, None) => Err(anyhow!("No camera to watch for {trigger:?}")),
+    }
+}
+
+#[cfg(feature = "pushnoti")]
+async fn watch_push<F, Fut>(name: &str, camera: &NeoInstance, send: F) -> AnyResult<()>
+where
+    F: Fn(String) -> Fut,
+    Fut: std::future::Future<Output = AnyResult<()>>,
+{
+    let mut pn = camera.push_notifications().await?;
+    pn.borrow_and_update();
+    loop {
+        pn.changed().await?;
+        let message = pn
+            .borrow_and_update()
+            .as_ref()
+            .map(|noti| noti.message.clone());
+        if let Some(message) = message {
+            log::debug!("{name}: Push notification for a rule: {message}");
+            send(format!("a push notification from {name}")).await?;
+        }
+    }
+}
+
+#[cfg(not(feature = "pushnoti"))]
+async fn watch_push<F, Fut>(name: &str, _camera: &NeoInstance, _send: F) -> AnyResult<()>
+where
+    F: Fn(String) -> Fut,
+    Fut: std::future::Future<Output = AnyResult<()>>,
+{
+    Err(anyhow!(
+        "Push notifications from {name} need neolink to be built with the pushnoti feature"
+    ))
+}
+
+/// Run a single action of a rule
+async fn run_action(
+    reactor: &NeoReactor,
+    mqtt: Option<&MqttInstance>,
+    action: &RuleAction,
+) -> AnyResult<()> {
+    let camera = match action.camera() {
+        Some(name) => Some(reactor.get(name).await?),
+        None => None,
+    };
+    match (action, camera) {
+        (
+            RuleAction::Mqtt {
+                topic,
+                message,
+                retain,
+            },
+            _,
+        ) => {
+            let mqtt = mqtt.ok_or_else(|| anyhow!("MQTT actions only run with `neolink mqtt`"))?;
+            mqtt.send_message_with_root_topic("", topic, message, *retain)
+                .await
+        }
+        (RuleAction::Talk { file, volume, .. }, Some(camera)) => {
+            crate::talk::talk_from_file(&camera, file, *volume).await
+        }
+        (action, Some(camera)) => {
+            let action = action.clone();
+            camera
+                .run_task(|cam| {
+                    let action = action.clone();
+                    Box::pin(async move {
+                        match action {
+                            RuleAction::Floodlight { seconds, .. } => {
+                                cam.set_floodlight_manual(true, seconds).await?
+                            }
+                            RuleAction::Siren { .. } => cam.siren().await?,
+                            RuleAction::PtzPreset { preset, .. } => {
+                                cam.moveto_ptz_preset(preset).await?
+                            }
+                            RuleAction::Led { on, .. } => cam.led_light_set(on).await?,
+                            RuleAction::Reboot { .. } => cam.reboot().await?,
+                            RuleAction::Talk { .. } | RuleAction::Mqtt { .. } => {
+                                unreachable!("Handled above")
+                            }
+                        }
+                        AnyResult::Ok(())
+                    })
+                })
+                .await
+        }
+        (action, None) => Err(anyhow!("No camera to run {action:?}")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn trigger(toml: &str) -> RuleTrigger {
+        toml::from_str(toml).unwrap()
+    }
+
+    fn start(ai_types: &[&str]) -> MdState {
+        MdState::Start(
+            Instant::now(),
+            ai_types.iter().map(|ai| ai.to_string()).collect(),
+        )
+    }
+
+    fn stop() -> MdState {
+        MdState::Stop(Instant::now())
+    }
+
+    /// The events of the `trigger` as the motion goes through the `states`
+    fn motion_events(trigger: &RuleTrigger, initial: MdState, states: &[MdState]) -> Vec<String> {
+        let mut motion = MotionTrigger::new(&initial);
+        states
+            .iter()
+            .filter_map(|state| motion.update(trigger, "Garage", state))
+            .collect()
+    }
+
+    #[test]
+    fn test_motion_triggers() {
+        let states = [
+            start(&[]),
+            start(&["people"]),
+            stop(),
+            MdState::Unknown,
+            start(&["vehicle"]),
+        ];
+        let motion_start = trigger("event = \"motion_start\"\ncamera = \"Garage\"");
+        assert_eq!(
+            motion_events(&motion_start, MdState::Unknown, &states),
+            vec!["motion on Garage", "motion on Garage"]
+        );
+        let motion_stop = trigger("event = \"motion_stop\"\ncamera = \"Garage\"");
+        assert_eq!(
+            motion_events(&motion_stop, MdState::Unknown, &states),
+            vec!["motion stopping on Garage"]
+        );
+
+        // Motion that is already going on when we start is not an event
+        assert_eq!(
+            motion_events(&motion_start, start(&[]), &[start(&[]), stop(), start(&[])]),
+            vec!["motion on Garage"]
+        );
+        assert_eq!(
+            motion_events(&motion_stop, start(&[]), &[stop(), stop()]),
+            vec!["motion stopping on Garage"]
+        );
+    }
+
+    #[test]
+    fn test_ai_trigger() {
+        let people = trigger("event = \"ai\"\ncamera = \"Garage\"\nclass = \"people\"");
+        // Each class counts once until the motion stops
+        let states = [
+            start(&[]),
+            start(&["vehicle"]),
+            start(&["people"]),
+            start(&["people", "vehicle"]),
+            stop(),
+            start(&["people"]),
+        ];
+        assert_eq!(
+            motion_events(&people, MdState::Unknown, &states),
+            vec!["people on Garage", "people on Garage"]
+        );
+        // People that are already seen when we start are not an event
+        assert_eq!(
+            motion_events(&people, start(&["people"]), &[start(&["people"])]),
+            Vec::<String>::new()
+        );
+    }
+
+    #[test]
+    fn test_battery_trigger() {
+        let mut battery = BatteryTrigger::default();
+        let events = [50, 21, 20, 15, 12, 30, 10]
+            .iter()
+            .filter_map(|&level| battery.update("Garage", level, 20))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            events,
+            vec![
+                "the battery of Garage at 15%",
+                "the battery of Garage at 10%"
+            ]
+        );
+
+        // Already low when we start
+        let mut battery = BatteryTrigger::default();
+        assert!(battery.update("Garage", 5, 20).is_some());
+        assert!(battery.update("Garage", 4, 20).is_none());
+    }
+
+    #[test]
+    fn test_disconnect_trigger() {
+        let mut connection = DisconnectTrigger { online: false };
+        let offline = [true, true, false, false, true, false]
+            .iter()
+            .map(|&online| connection.went_offline(online))
+            .collect::<Vec<_>>();
+        assert_eq!(offline, vec![false, false, true, false, false, true]);
+
+        assert_eq!(
+            DisconnectTrigger::event("Garage", NeoCamThreadState::Connected).as_deref(),
+            Some("Garage disconnecting")
+        );
+        // Such as an idle disconnect
+        assert_eq!(
+            DisconnectTrigger::event("Garage", NeoCamThreadState::Disconnected),
+            None
+        );
+    }
+
+    #[test]
+    fn test_schedule_wait() {
+        let at: ScheduleTime = "12:00".parse().unwrap();
+        // 2024-06-01 11:00 UTC
+        let now = 1717239600;
+        assert_eq!(
+            schedule_wait(&at, (0.0, 0.0), None, now),
+            (Duration::from_secs(3600), true)
+        );
+        // Just after it happened it is tomorrow, which is too long to sleep
+        assert_eq!(
+            schedule_wait(&at, (0.0, 0.0), None, now + 3601),
+            (Duration::from_secs(MAX_SLEEP as u64), false)
+        );
+        // 10:30 UTC is 11:30 in CET, so it is half an hour to 12:00 rather than an hour and a half
+        let tz: TimeZone = "CET-1".parse().unwrap();
+        assert_eq!(
+            schedule_wait(&at, (0.0, 0.0), Some(&tz), now - 1800),
+            (Duration::from_secs(1800), true)
+        );
+    }
+
+    #[test]
+    fn test_cooldowns() {
+        let mut cooldowns = Cooldowns::default();
+        let t0 = Instant::now();
+        let cooldown = Duration::from_secs(10);
+        let runs = [(0, 0), (0, 5), (1, 5), (0, 9), (0, 10), (1, 12), (0, 15)]
+            .iter()
+            .map(|&(index, secs)| cooldowns.start(index, cooldown, t0 + Duration::from_secs(secs)))
+            .collect::<Vec<_>>();
+        // A skipped trigger does not restart the cooldown
+        assert_eq!(runs, vec![true, false, true, false, true, false, false]);
+
+        let mut cooldowns = Cooldowns::default();
+        assert!(cooldowns.start(0, Duration::ZERO, t0));
+        assert!(cooldowns.start(0, Duration::ZERO, t0));
+    }
+}
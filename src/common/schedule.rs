@@ -0,0 +1,237 @@
+//! Switches the lights on the schedules in the config
+//!
+//! Each `[[cameras.schedule.lights]]` puts a light in its `state` from
+//! `from` until `to` and in its `otherwise` state for the rest of the
+//! day. The times can be relative to the sunrise and sunset, which are
+//! worked out from the `latitude` and `longitude`.
+//!
+//! The lights are set through [`NeoInstance::run_task`] so a change is
+//! still made if the camera is reconnecting when it is due.
+use std::collections::HashSet;
+use time::{Date, Duration as TimeDuration, OffsetDateTime};
+use tokio::time::{sleep, Duration};
+
+use super::{sun::sun_times, NeoInstance};
+use crate::{
+    config::{
+        CameraConfig, IrMode, LightScheduleConfig, ScheduleConfig, ScheduleTime, ScheduleTimeKind,
+        ScheduledLight, TimeZone,
+    },
+    AnyResult,
+};
+use neolink_core::bc_protocol::LightState;
+
+/// The longest that we sleep, so that changes to the host clock are noticed
+const MAX_SLEEP: Duration = Duration::from_secs(3600);
+
+/// Run the light schedules of the camera
+///
+/// This only returns if the camera is dropped
+pub(crate) async fn run_schedule(instance: NeoInstance) -> AnyResult<()> {
+    let mut config = instance.config().await?;
+    loop {
+        let (name, schedule, timezone) = {
+            let config = config.borrow_and_update();
+            (
+                config.name.clone(),
+                config.schedule.clone(),
+                schedule_timezone(&config),
+            )
+        };
+        let Some(schedule) = schedule.filter(|schedule| !schedule.lights.is_empty()) else {
+            config.changed().await?;
+            continue;
+        };
+
+        // The state that each light was last set to and until when, for the floodlight
+        let mut applied: Vec<Option<(IrMode, i64)>> = vec![None; schedule.lights.len()];
+        let mut unsupported = HashSet::new();
+        loop {
+            let now = OffsetDateTime::now_utc().unix_timestamp();
+            let mut wake = now + MAX_SLEEP.as_secs() as i64;
+            for (light, applied) in schedule.lights.iter().zip(applied.iter_mut()) {
+                let (active, next_change) = window(&schedule, light, timezone.as_ref(), now);
+                wake = wake.min(next_change);
+                let want = if active { light.state } else { light.otherwise };
+                let expired = applied.is_some_and(|(_, until)| until <= now);
+                if unsupported.contains(&light.light)
+                    || (applied.map(|(state, _)| state) == Some(want) && !expired)
+                {
+                    continue;
+                }
+
+                // The floodlight only stays on for up to u16::MAX seconds so it is renewed
+                let duration = (next_change - now).clamp(1, u16::MAX as i64);
+                let until = match (light.light, want) {
+                    (ScheduledLight::Floodlight, IrMode::On) => now + duration,
+                    _ => i64::MAX,
+                };
+                wake = wake.min(until);
+
+                log::info!("{name}: Schedule sets the {:?} to {want:?}", light.light);
+                match set_light(&instance, light.light, want, duration as u16).await {
+                    Ok(()) => *applied = Some((want, until)),
+                    Err(e) => match e.downcast_ref::<neolink_core::Error>() {
+                        Some(neolink_core::Error::MissingAbility { .. })
+                        | Some(neolink_core::Error::CameraServiceUnavailable { .. }) => {
+                            log::warn!(
+                                "{name}: The camera does not support the scheduled {:?}: {e}",
+                                light.light
+                            );
+                            unsupported.insert(light.light);
+                        }
+                        _ => {
+                            log::warn!("{name}: Could not set the {:?}: {e:?}", light.light);
+                            // Try again soon
+                            wake = wake.min(now + 60);
+                        }
+                    },
+                }
+            }
+
+            // Wake just after the change so that it is in effect
+            let delay = Duration::from_secs((wake - now).max(0) as u64 + 1).min(MAX_SLEEP);
+            log::debug!("{name}: Next schedule check in {delay:?}");
+            tokio::select! {
+                _ = sleep(delay) => {}
+                v = config.changed() => {
+                    v?;
+                    break;
+                }
+            }
+        }
+    }
+}
+
+async fn set_light(
+    instance: &NeoInstance,
+    light: ScheduledLight,
+    state: IrMode,
+    duration: u16,
+) -> AnyResult<()> {
+    instance
+        .run_task(|cam| {
+            Box::pin(async move {
+                let on = state == IrMode::On;
+                match light {
+                    ScheduledLight::Irled => {
+                        cam.irled_light_set(match state {
+                            IrMode::On => LightState::On,
+                            IrMode::Off => LightState::Off,
+                            IrMode::Auto => LightState::Auto,
+                        })
+                        .await?
+                    }
+                    ScheduledLight::Floodlight => cam.set_floodlight_manual(on, duration).await?,
+                    ScheduledLight::Led => cam.led_light_set(on).await?,
+                    ScheduledLight::FloodlightTasks => cam.flightlight_tasks_enable(on).await?,
+                }
+                AnyResult::Ok(())
+            })
+        })
+        .await
+}
+
+/// If the `light` should be in its `state` at `now`, and when that next changes
+fn window(
+    schedule: &ScheduleConfig,
+    light: &LightScheduleConfig,
+    timezone: Option<&TimeZone>,
+    now: i64,
+) -> (bool, i64) {
+    let location = (schedule.latitude, schedule.longitude);
+    let today = local_date(timezone, now);
+
+    let mut active = false;
+    let mut next_change = i64::MAX;
+    // A window can start on the day before and end today, such as sunset to sunrise
+    for days in -1..=2 {
+        let Some(date) = today.checked_add(TimeDuration::days(days)) else {
+            continue;
+        };
+        let start = resolve(&light.from, location, timezone, date);
+        let mut end = resolve(&light.to, location, timezone, date);
+        if end <= start {
+            end = date
+                .next_day()
+                .map(|next| resolve(&light.to, location, timezone, next))
+                .unwrap_or(end);
+        }
+        if start <= now && now < end {
+            active = true;
+        }
+        for change in [start, end] {
+            if change > now {
+                next_change = next_change.min(change);
+            }
+        }
+    }
+    (active, next_change)
+}
+
+/// The timezone of the clock times in the camera's schedules
+///
+/// This is the one of `[cameras.schedule]` or else of `[cameras.time_sync]`
+pub(crate) fn schedule_timezone(config: &CameraConfig) -> Option<TimeZone> {
+    config
+        .schedule
+        .as_ref()
+        .and_then(|schedule| schedule.timezone.clone())
+        .or_else(|| {
+            config
+                .time_sync
+                .as_ref()
+                .and_then(|sync| sync.timezone.clone())
+        })
+}
+
+/// The next unix time after `now` that it is `time`
+///
+/// The `location` is the latitude and longitude for the sun
+pub(crate) fn next_time(
+    time: &ScheduleTime,
+    location: (f64, f64),
+    timezone: Option<&TimeZone>,
+    now: i64,
+) -> i64 {
+    let today = local_date(timezone, now);
+    (-1..=2)
+        .filter_map(|days| today.checked_add(TimeDuration::days(days)))
+        .map(|date| resolve(time, location, timezone, date))
+        .filter(|at| *at > now)
+        .min()
+        .unwrap_or(now + 86400)
+}
+
+fn local_date(timezone: Option<&TimeZone>, now: i64) -> Date {
+    let now = OffsetDateTime::from_unix_timestamp(now).unwrap_or(OffsetDateTime::UNIX_EPOCH);
+    let offset = timezone
+        .map(|timezone| timezone.offset_at(now))
+        .unwrap_or(time::UtcOffset::UTC);
+    now.to_offset(offset).date()
+}
+
+/// The unix time of `time` on the `date`
+fn resolve(
+    time: &ScheduleTime,
+    (latitude, longitude): (f64, f64),
+    timezone: Option<&TimeZone>,
+    date: Date,
+) -> i64 {
+    match time.kind {
+        ScheduleTimeKind::Sunrise(offset) => sun_times(date, latitude, longitude).sunrise + offset,
+        ScheduleTimeKind::Sunset(offset) => sun_times(date, latitude, longitude).sunset + offset,
+        ScheduleTimeKind::Clock(seconds) => {
+            let local = date.midnight().assume_utc().unix_timestamp() + seconds;
+            let offset = timezone
+                .and_then(|timezone| {
+                    OffsetDateTime::from_unix_timestamp(local)
+                        .ok()
+                        .map(|at| timezone.offset_at(at))
+                })
+                .map(|offset| offset.whole_seconds() as i64)
+                .unwrap_or(0);
+            local - offset
+        }
+    }
+}
@@ -0,0 +1,72 @@
+//! Tracks the state of the servers that neolink runs
+//!
+//! Such as the RTSP server and the MQTT client. This is
+//! reported by the health endpoints
+use std::{collections::BTreeMap, sync::Arc};
+use tokio::sync::watch::{channel as watch, Sender as WatchSender};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum ServiceState {
+    Starting,
+    Running,
+    Failed(String),
+}
+
+/// A shared handle to the states of the services
+#[derive(Clone)]
+pub(crate) struct Services {
+    states: Arc<WatchSender<BTreeMap<&'static str, ServiceState>>>,
+}
+
+impl Services {
+    pub(crate) fn new() -> Self {
+        let (states, _) = watch(Default::default());
+        Self {
+            states: Arc::new(states),
+        }
+    }
+
+    /// Set the state of the service called `name`
+    pub(crate) fn set(&self, name: &'static str, state: ServiceState) {
+        self.states.send_if_modified(|states| {
+            if states.get(name) == Some(&state) {
+                false
+            } else {
+                log::debug!("{name}: Service is now {state:?}");
+                states.insert(name, state);
+                true
+            }
+        });
+    }
+
+    pub(crate) fn get(&self) -> BTreeMap<&'static str, ServiceState> {
+        self.states.borrow().clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_services() {
+        let services = Services::new();
+        let mut changes = services.states.subscribe();
+        services.set("rtsp", ServiceState::Starting);
+        services.set("mqtt", ServiceState::Failed("refused".to_string()));
+        services.set("rtsp", ServiceState::Running);
+        assert!(changes.has_changed().unwrap());
+        changes.mark_unchanged();
+
+        // Setting the same state again is not a change
+        services.set("rtsp", ServiceState::Running);
+        assert!(!changes.has_changed().unwrap());
+        assert_eq!(
+            services.get(),
+            BTreeMap::from([
+                ("mqtt", ServiceState::Failed("refused".to_string())),
+                ("rtsp", ServiceState::Running),
+            ])
+        );
+    }
+}
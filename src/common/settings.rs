@@ -0,0 +1,438 @@
+//! Keeps the camera's settings at those in the config
+//!
+//! The `[cameras.settings]` are applied whenever we connect and whenever
+//! they change in the config. They are then checked every `check_interval`
+//! so that anything that changed them, such as a factory reset or a firmware
+//! update, is undone.
+use futures::Future;
+use std::collections::HashSet;
+use tokio::{
+    sync::watch::Receiver as WatchReceiver,
+    time::{interval, Duration, MissedTickBehavior},
+};
+
+use crate::{
+    config::{CameraConfig, CameraSettingsConfig, IrMode, ServicePortConfig},
+    AnyResult,
+};
+use neolink_core::{bc::xml::LedState, bc_protocol::BcCamera};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Setting {
+    LedState,
+    Pir,
+    FloodlightTasks,
+    Email,
+    Port(Service),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Service {
+    Server,
+    Http,
+    Https,
+    Rtsp,
+    Rtmp,
+    Onvif,
+}
+
+impl Setting {
+    /// The settings that the config sets
+    fn configured(settings: &CameraSettingsConfig) -> Vec<Setting> {
+        let mut configured = vec![];
+        if settings.led.is_some() || settings.ir.is_some() {
+            configured.push(Setting::LedState);
+        }
+        if settings.pir.is_some() {
+            configured.push(Setting::Pir);
+        }
+        if settings.floodlight_tasks.is_some() {
+            configured.push(Setting::FloodlightTasks);
+        }
+        if settings.email.is_some() {
+            configured.push(Setting::Email);
+        }
+        for (service, port) in [
+            (Service::Server, &settings.server_port),
+            (Service::Http, &settings.http_port),
+            (Service::Https, &settings.https_port),
+            (Service::Rtsp, &settings.rtsp_port),
+            (Service::Rtmp, &settings.rtmp_port),
+            (Service::Onvif, &settings.onvif_port),
+        ] {
+            if port.is_some() {
+                configured.push(Setting::Port(service));
+            }
+        }
+        configured
+    }
+}
+
+/// Apply the camera settings from the config and keep them applied
+///
+/// This only returns if the config is dropped
+pub(crate) async fn maintain_settings(
+    camera: &BcCamera,
+    mut config: WatchReceiver<CameraConfig>,
+) -> AnyResult<()> {
+    // Settings the camera does not support, so that we only warn once per connection
+    let mut unsupported = HashSet::new();
+    loop {
+        let (name, settings) = {
+            let config = config.borrow_and_update();
+            (config.name.clone(), config.settings.clone())
+        };
+        let Some(settings) = settings else {
+            config.changed().await?;
+            continue;
+        };
+
+        apply_settings(camera, &name, &settings, &mut unsupported).await;
+
+        if settings.check_interval == 0 {
+            config.changed().await?;
+            continue;
+        }
+        let period = Duration::from_secs(settings.check_interval);
+        let mut ticker = interval(period);
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        ticker.tick().await; // The first tick is immediate
+        loop {
+            tokio::select! {
+                _ = ticker.tick() => {
+                    log::debug!("{name}: Checking the camera settings");
+                    apply_settings(camera, &name, &settings, &mut unsupported).await;
+                }
+                v = config.changed() => {
+                    v?;
+                    break;
+                }
+            }
+        }
+    }
+}
+
+async fn apply_settings(
+    camera: &BcCamera,
+    name: &str,
+    settings: &CameraSettingsConfig,
+    unsupported: &mut HashSet<Setting>,
+) {
+    apply_each(
+        name,
+        Setting::configured(settings),
+        unsupported,
+        |setting| apply_setting(camera, name, settings, setting),
+    )
+    .await
+}
+
+/// Apply each of the `settings`, remembering the ones the camera does not support
+///
+/// Those are not tried again
+async fn apply_each<F, Fut>(
+    name: &str,
+    settings: Vec<Setting>,
+    unsupported: &mut HashSet<Setting>,
+    mut apply: F,
+) where
+    F: FnMut(Setting) -> Fut,
+    Fut: Future<Output = AnyResult<()>>,
+{
+    for setting in settings {
+        if unsupported.contains(&setting) {
+            continue;
+        }
+        if let Err(e) = apply(setting).await {
+            match e.downcast_ref::<neolink_core::Error>() {
+                Some(neolink_core::Error::MissingAbility { .. })
+                | Some(neolink_core::Error::CameraServiceUnavailable { .. }) => {
+                    log::warn!("{name}: The camera does not support the {setting:?} setting: {e}");
+                    unsupported.insert(setting);
+                }
+                _ => log::warn!("{name}: Could not apply the {setting:?} setting: {e:?}"),
+            }
+        }
+    }
+}
+
+async fn apply_setting(
+    camera: &BcCamera,
+    name: &str,
+    settings: &CameraSettingsConfig,
+    setting: Setting,
+) -> AnyResult<()> {
+    match setting {
+        Setting::LedState => {
+            let mut led_state = camera.get_ledstate().await?;
+            if reconcile_led_state(name, settings, &mut led_state) {
+                camera.set_ledstate(led_state).await?;
+            }
+        }
+        Setting::Pir => {
+            let mut pir = camera.get_pirstate().await?;
+            if let Some(want) = reconcile_enable(settings.pir, pir.enable) {
+                log::info!("{name}: PIR is {}, setting it to {want}", pir.enable);
+                pir.enable = want;
+                camera.set_pirstate(pir).await?;
+            }
+        }
+        Setting::FloodlightTasks => {
+            let want = settings.floodlight_tasks.unwrap_or_default();
+            let current = camera.is_flightlight_tasks_enabled().await?;
+            if current != want {
+                log::info!("{name}: Floodlight tasks are {current}, setting them to {want}");
+                camera.flightlight_tasks_enable(want).await?;
+            }
+        }
+        Setting::Email => {
+            let mut task = camera.get_email_task().await?;
+            if let Some(want) = reconcile_enable(settings.email, task.enable) {
+                log::info!(
+                    "{name}: Email notifications are {}, setting them to {want}",
+                    task.enable
+                );
+                task.enable = want;
+                camera.set_email_task(task).await?;
+            }
+        }
+        Setting::Port(service) => {
+            let ServicePortConfig { enable, port } = match service {
+                Service::Server => settings.server_port,
+                Service::Http => settings.http_port,
+                Service::Https => settings.https_port,
+                Service::Rtsp => settings.rtsp_port,
+                Service::Rtmp => settings.rtmp_port,
+                Service::Onvif => settings.onvif_port,
+            }
+            .expect("Only ports that are set are applied");
+            let (current_port, current_enable) = get_port(camera, service).await?;
+            if port_drifted(enable, port, current_port, current_enable) {
+                let current_enable = current_enable.map(|enable| enable == 1);
+                log::info!(
+                    "{name}: {service:?} port is {current_port} (enabled: {current_enable:?}), setting it to {port:?} (enabled: {enable:?})"
+                );
+                set_port(camera, service, enable, port).await?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Set the status LED and IR of the `led_state` to those of the config
+///
+/// True if they were different
+fn reconcile_led_state(
+    name: &str,
+    settings: &CameraSettingsConfig,
+    led_state: &mut LedState,
+) -> bool {
+    let mut changed = false;
+    if let Some(led) = settings.led {
+        let want = if led { "open" } else { "close" };
+        if led_state.light_state != want {
+            log::info!(
+                "{name}: Status LED is {}, setting it to {want}",
+                led_state.light_state
+            );
+            led_state.light_state = want.to_string();
+            changed = true;
+        }
+    }
+    if let Some(ir) = settings.ir {
+        let want = match ir {
+            IrMode::On => "open",
+            IrMode::Off => "close",
+            IrMode::Auto => "auto",
+        };
+        if led_state.state != want {
+            log::info!("{name}: IR is {}, setting it to {want}", led_state.state);
+            led_state.state = want.to_string();
+            changed = true;
+        }
+    }
+    changed
+}
+
+/// The enable to write if the camera's `current` one is not what the config wants
+fn reconcile_enable(want: Option<bool>, current: u8) -> Option<u8> {
+    let want = want.unwrap_or_default() as u8;
+    (current != want).then_some(want)
+}
+
+/// True if the camera's port or its enable are not what the config wants
+///
+/// An enable that the camera does not report is left alone
+fn port_drifted(
+    enable: Option<bool>,
+    port: Option<u32>,
+    current_port: u32,
+    current_enable: Option<u32>,
+) -> bool {
+    let current_enable = current_enable.map(|enable| enable == 1);
+    let enable_drifted = enable.is_some() && current_enable.is_some() && enable != current_enable;
+    let port_drifted = port.is_some_and(|port| port != current_port);
+    enable_drifted || port_drifted
+}
+
+/// The port and enabled state of a service
+async fn get_port(camera: &BcCamera, service: Service) -> AnyResult<(u32, Option<u32>)> {
+    Ok(match service {
+        Service::Server => {
+            let xml = camera.get_serverport().await?;
+            (xml.port, xml.enable)
+        }
+        Service::Http => {
+            let xml = camera.get_http().await?;
+            (xml.port, xml.enable)
+        }
+        Service::Https => {
+            let xml = camera.get_https().await?;
+            (xml.port, xml.enable)
+        }
+        Service::Rtsp => {
+            let xml = camera.get_rtsp().await?;
+            (xml.port, xml.enable)
+        }
+        Service::Rtmp => {
+            let xml = camera.get_rtmp().await?;
+            (xml.port, xml.enable)
+        }
+        Service::Onvif => {
+            let xml = camera.get_onvif().await?;
+            (xml.port, xml.enable)
+        }
+    })
+}
+
+async fn set_port(
+    camera: &BcCamera,
+    service: Service,
+    enable: Option<bool>,
+    port: Option<u32>,
+) -> AnyResult<()> {
+    match service {
+        Service::Server => camera.set_serverport(enable, port).await?,
+        Service::Http => camera.set_http(enable, port).await?,
+        Service::Https => camera.set_https(enable, port).await?,
+        Service::Rtsp => camera.set_rtsp(enable, port).await?,
+        Service::Rtmp => camera.set_rtmp(enable, port).await?,
+        Service::Onvif => camera.set_onvif(enable, port).await?,
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings(toml: &str) -> CameraSettingsConfig {
+        toml::from_str(toml).unwrap()
+    }
+
+    #[test]
+    fn test_configured() {
+        assert_eq!(Setting::configured(&settings("")), vec![]);
+        assert_eq!(
+            Setting::configured(&settings(
+                "ir = \"auto\"\nemail = false\nrtsp_port = { port = 554 }\n"
+            )),
+            vec![
+                Setting::LedState,
+                Setting::Email,
+                Setting::Port(Service::Rtsp)
+            ]
+        );
+    }
+
+    /// The camera's LED state, with the IR on auto and the status LED on
+    fn camera_led_state() -> LedState {
+        LedState {
+            version: "1.1".to_string(),
+            channel_id: 0,
+            led_version: Some(2),
+            state: "auto".to_string(),
+            light_state: "open".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_reconcile_led_state() {
+        // Already as configured
+        let mut led_state = camera_led_state();
+        let config = settings("led = true\nir = \"auto\"\n");
+        assert!(!reconcile_led_state("Garage", &config, &mut led_state));
+        assert_eq!(led_state, camera_led_state());
+
+        // Only what is configured changes
+        let mut led_state = camera_led_state();
+        let config = settings("led = false\n");
+        assert!(reconcile_led_state("Garage", &config, &mut led_state));
+        assert_eq!(led_state.light_state, "close");
+        assert_eq!(led_state.state, "auto");
+
+        let mut led_state = camera_led_state();
+        let config = settings("ir = \"off\"\n");
+        assert!(reconcile_led_state("Garage", &config, &mut led_state));
+        assert_eq!(led_state.light_state, "open");
+        assert_eq!(led_state.state, "close");
+    }
+
+    #[test]
+    fn test_reconcile_enable() {
+        assert_eq!(reconcile_enable(Some(true), 1), None);
+        assert_eq!(reconcile_enable(Some(false), 0), None);
+        assert_eq!(reconcile_enable(Some(true), 0), Some(1));
+        assert_eq!(reconcile_enable(Some(false), 1), Some(0));
+    }
+
+    #[test]
+    fn test_port_drifted() {
+        assert!(!port_drifted(Some(true), Some(554), 554, Some(1)));
+        assert!(port_drifted(None, Some(554), 8554, Some(1)));
+        assert!(port_drifted(Some(false), None, 554, Some(1)));
+        assert!(!port_drifted(Some(true), None, 554, Some(1)));
+        // The camera does not report if it is enabled
+        assert!(!port_drifted(Some(false), None, 554, None));
+        assert!(!port_drifted(None, None, 554, Some(0)));
+    }
+
+    #[tokio::test]
+    async fn test_unsupported_not_retried() {
+        let all = vec![Setting::LedState, Setting::Pir, Setting::Email];
+        let mut unsupported = HashSet::new();
+        let mut tried = vec![];
+        for _ in 0..3 {
+            apply_each("Garage", all.clone(), &mut unsupported, |setting| {
+                tried.push(setting);
+                async move {
+                    match setting {
+                        Setting::Pir => Err(neolink_core::Error::MissingAbility {
+                            name: "pir".to_string(),
+                            requested: "write".to_string(),
+                            actual: "none".to_string(),
+                        }
+                        .into()),
+                        // Other errors may pass, such as a timeout
+                        Setting::Email => Err(anyhow::anyhow!("Timed out")),
+                        _ => Ok(()),
+                    }
+                }
+            })
+            .await;
+        }
+        assert_eq!(unsupported, HashSet::from([Setting::Pir]));
+        assert_eq!(
+            tried,
+            vec![
+                Setting::LedState,
+                Setting::Pir,
+                Setting::Email,
+                Setting::LedState,
+                Setting::Email,
+                Setting::LedState,
+                Setting::Email,
+            ]
+        );
+    }
+}
@@ -0,0 +1,110 @@
+//! Works out sunrise and sunset from the latitude and longitude
+//!
+//! This uses the sunrise equation with the usual allowance for refraction
+//! and the size of the sun. It is within a minute or two away from the
+//! poles and needs no network
+use time::Date;
+
+/// The Julian day of the unix epoch
+const UNIX_EPOCH_JD: f64 = 2440587.5;
+/// The Julian day of J2000.0
+const J2000: f64 = 2451545.0;
+
+/// Sunrise and sunset in unix seconds
+///
+/// When the sun does not set they are half a day either side of noon and
+/// when it does not rise they are both at noon
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct SunTimes {
+    pub(crate) sunrise: i64,
+    pub(crate) sunset: i64,
+}
+
+/// The sunrise and sunset on `date` at the local solar day
+pub(crate) fn sun_times(date: Date, latitude: f64, longitude: f64) -> SunTimes {
+    // Days since J2000.0 to the local solar noon
+    let days = (date.to_julian_day() as f64 - J2000) - longitude / 360.0;
+    let anomaly = (357.5291 + 0.98560028 * days)
+        .rem_euclid(360.0)
+        .to_radians();
+    let center =
+        1.9148 * anomaly.sin() + 0.0200 * (2.0 * anomaly).sin() + 0.0003 * (3.0 * anomaly).sin();
+    let ecliptic_longitude = (anomaly.to_degrees() + center + 180.0 + 102.9372)
+        .rem_euclid(360.0)
+        .to_radians();
+    let transit = J2000 + days + 0.0053 * anomaly.sin() - 0.0069 * (2.0 * ecliptic_longitude).sin();
+
+    let declination = (ecliptic_longitude.sin() * 23.4397f64.to_radians().sin()).asin();
+    let latitude = latitude.to_radians();
+    let cos_hour_angle = ((-0.833f64).to_radians().sin() - latitude.sin() * declination.sin())
+        / (latitude.cos() * declination.cos());
+    // Clamped for the midnight sun and the polar night
+    let hour_angle = cos_hour_angle.clamp(-1.0, 1.0).acos().to_degrees();
+
+    let to_unix = |julian: f64| ((julian - UNIX_EPOCH_JD) * 86400.0).round() as i64;
+    SunTimes {
+        sunrise: to_unix(transit - hour_angle / 360.0),
+        sunset: to_unix(transit + hour_angle / 360.0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use time::{Month, OffsetDateTime, Time};
+
+    /// The unix time of `hour:minute` UTC on the `date`
+    fn utc(date: Date, hour: u8, minute: u8) -> i64 {
+        date.with_time(Time::from_hms(hour, minute, 0).unwrap())
+            .assume_utc()
+            .unix_timestamp()
+    }
+
+    fn date(year: i32, month: Month, day: u8) -> Date {
+        Date::from_calendar_date(year, month, day).unwrap()
+    }
+
+    /// Within two minutes of the published times
+    fn assert_near(actual: i64, expected: i64) {
+        assert!(
+            (actual - expected).abs() <= 120,
+            "{} is not near {}",
+            OffsetDateTime::from_unix_timestamp(actual).unwrap(),
+            OffsetDateTime::from_unix_timestamp(expected).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_london_summer() {
+        let day = date(2024, Month::June, 21);
+        let times = sun_times(day, 51.5074, -0.1278);
+        assert_near(times.sunrise, utc(day, 3, 43));
+        assert_near(times.sunset, utc(day, 20, 21));
+    }
+
+    #[test]
+    fn test_new_york_winter() {
+        let day = date(2024, Month::December, 21);
+        let times = sun_times(day, 40.7128, -74.0060);
+        assert_near(times.sunrise, utc(day, 12, 16));
+        assert_near(times.sunset, utc(day, 21, 32));
+    }
+
+    #[test]
+    fn test_sydney() {
+        // The sun rises on the day before in UTC this far east
+        let day = date(2024, Month::December, 21);
+        let times = sun_times(day, -33.8688, 151.2093);
+        assert_near(times.sunrise, utc(date(2024, Month::December, 20), 18, 41));
+        assert_near(times.sunset, utc(day, 9, 5));
+    }
+
+    #[test]
+    fn test_polar() {
+        // Tromsø has the midnight sun in June and the polar night in December
+        let times = sun_times(date(2024, Month::June, 21), 69.6492, 18.9553);
+        assert_near(times.sunset - times.sunrise, 86400);
+        let times = sun_times(date(2024, Month::December, 21), 69.6492, 18.9553);
+        assert_eq!(times.sunrise, times.sunset);
+    }
+}
@@ -0,0 +1,167 @@
+//! Keeps the camera's clock in sync with this host
+//!
+//! With `[cameras.time_sync]` the camera's clock is compared to ours every
+//! `check_interval` and set again if it drifted by more than `max_drift`
+//! seconds or if its offset from UTC is not the one of the `timezone`,
+//! such as after a change to or from DST. The measured drift is shared
+//! so that it can be reported over MQTT
+use time::{OffsetDateTime, UtcOffset};
+use tokio::{
+    sync::watch::{Receiver as WatchReceiver, Sender as WatchSender},
+    time::{interval, Duration, MissedTickBehavior},
+};
+
+use crate::{
+    config::{CameraConfig, TimeSyncConfig},
+    AnyResult,
+};
+use neolink_core::bc_protocol::BcCamera;
+
+/// Set the camera time when we connect if it is unset or `update_time` is on
+///
+/// With `[cameras.time_sync]` this is left to [`maintain_time`], which
+/// checks the time as soon as we connect
+pub(crate) async fn update_camera_time(
+    camera: &BcCamera,
+    name: &str,
+    config: &CameraConfig,
+) -> AnyResult<()> {
+    if config.time_sync.is_some() {
+        log::debug!("{name}: The time sync will check the camera time");
+        return Ok(());
+    }
+    let cam_time = camera.get_time().await?;
+    let mut update = false;
+    if let Some(time) = cam_time {
+        log::info!("{}: Camera time is already set: {}", name, time);
+        if config.update_time {
+            update = true;
+        }
+    } else {
+        update = true;
+        log::warn!("{}: Camera has no time set, Updating", name);
+    }
+    if update {
+        let offset = cam_time.map(|time| time.offset()).unwrap_or(UtcOffset::UTC);
+        let new_time = OffsetDateTime::now_utc().to_offset(offset);
+
+        log::info!("{}: Setting time to {}", name, new_time);
+        match camera.set_time(new_time).await {
+            Ok(_) => {
+                let cam_time = camera.get_time().await?;
+                if let Some(time) = cam_time {
+                    log::info!("{}: Camera time is now set: {}", name, time);
+                }
+            }
+            Err(e) => {
+                log::error!(
+                    "{}: Camera did not accept new time (is user an admin?): Error: {:?}",
+                    name,
+                    e
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Check the camera's clock as set in the config and correct it
+///
+/// The drift in seconds is sent to `drift`, it is positive if the camera
+/// is ahead of us. This only returns if the config is dropped
+pub(crate) async fn maintain_time(
+    camera: &BcCamera,
+    mut config: WatchReceiver<CameraConfig>,
+    drift: &WatchSender<Option<i64>>,
+) -> AnyResult<()> {
+    loop {
+        let (name, sync) = {
+            let config = config.borrow_and_update();
+            (config.name.clone(), config.time_sync.clone())
+        };
+        let Some(sync) = sync else {
+            config.changed().await?;
+            continue;
+        };
+
+        let period = Duration::from_secs(sync.check_interval.max(1));
+        let mut ticker = interval(period);
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        loop {
+            tokio::select! {
+                _ = ticker.tick() => {
+                    log::debug!("{name}: Checking the camera time");
+                    match check_time(camera, &name, &sync).await {
+                        Ok(measured) => {
+                            drift.send_replace(measured);
+                        }
+                        Err(e) => {
+                            match e.downcast_ref::<neolink_core::Error>() {
+                                Some(neolink_core::Error::MissingAbility { .. })
+                                | Some(neolink_core::Error::CameraServiceUnavailable { .. }) => {
+                                    log::warn!("{name}: The camera does not support time sync: {e}");
+                                    // Wait for the config to change rather than asking again
+                                    config.changed().await?;
+                                    break;
+                                }
+                                _ => log::warn!("{name}: Could not check the camera time: {e:?}"),
+                            }
+                        }
+                    }
+                    if sync.check_interval == 0 {
+                        config.changed().await?;
+                        break;
+                    }
+                }
+                v = config.changed() => {
+                    v?;
+                    break;
+                }
+            }
+        }
+    }
+}
+
+/// Measure the drift of the camera's clock and set it if needed
+///
+/// Returns the drift before it was corrected, which is unset if the camera had no time
+async fn check_time(
+    camera: &BcCamera,
+    name: &str,
+    sync: &TimeSyncConfig,
+) -> AnyResult<Option<i64>> {
+    let before = OffsetDateTime::now_utc();
+    let cam_time = camera.get_time().await?;
+    // Take our time as halfway through the request
+    let now = before + (OffsetDateTime::now_utc() - before) / 2;
+
+    let want_offset = match (&sync.timezone, cam_time) {
+        (Some(timezone), _) => timezone.offset_at(now),
+        (None, Some(cam_time)) => cam_time.offset(),
+        (None, None) => UtcOffset::UTC,
+    };
+    let drift = cam_time.map(|cam_time| (cam_time - now).whole_seconds());
+    match (cam_time, drift) {
+        (Some(cam_time), Some(drift)) if drift.unsigned_abs() > sync.max_drift => {
+            log::info!("{name}: Camera time {cam_time} has drifted by {drift}s, setting it again");
+        }
+        (Some(cam_time), _) if cam_time.offset() != want_offset => {
+            log::info!(
+                "{name}: Camera is at UTC{}, setting it to UTC{want_offset}",
+                cam_time.offset()
+            );
+        }
+        (Some(_), Some(drift)) => {
+            log::debug!("{name}: Camera time has drifted by {drift}s");
+            return Ok(drift.into());
+        }
+        _ => {
+            log::warn!("{name}: Camera has no time set, setting it");
+        }
+    }
+
+    camera
+        .set_time(OffsetDateTime::now_utc().to_offset(want_offset))
+        .await?;
+    Ok(drift)
+}
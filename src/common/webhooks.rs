@@ -0,0 +1,398 @@
+//! Posts the camera events to the `[[webhooks]]` of the config
+//!
+//! Each [`CameraEvent`] is posted as JSON to the webhooks that want it,
+//! with a snapshot from the camera if asked for. A failed post is tried
+//! again with an exponential backoff.
+use anyhow::{anyhow, Context};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use serde::Serialize;
+use std::{collections::HashSet, sync::Arc};
+use tokio::{
+    sync::broadcast::{error::RecvError, Sender as BroadcastSender},
+    task::JoinSet,
+    time::{sleep, timeout, Duration},
+};
+
+use super::{CameraEvent, NeoInstance, NeoReactor};
+use crate::{
+    config::{EventKind, WebhookConfig},
+    AnyResult,
+};
+
+/// The longest wait between the tries of a post
+const MAX_BACKOFF: Duration = Duration::from_secs(300);
+
+/// How long to wait for a snapshot before sending without it
+const SNAPSHOT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The JSON that is posted to the webhooks
+#[derive(Serialize, Debug, Clone)]
+struct WebhookPayload {
+    #[serde(flatten)]
+    event: CameraEvent,
+    /// A base64 JPEG, if the webhook asked for it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    snapshot: Option<String>,
+}
+
+/// Send the camera `events` to the webhooks in the config
+///
+/// This only returns if the reactor is dropped
+pub(crate) async fn run_webhooks(
+    reactor: NeoReactor,
+    events: BroadcastSender<CameraEvent>,
+) -> AnyResult<()> {
+    let client = reqwest::Client::builder()
+        .user_agent(concat!("neolink/", env!("NEOLINK_VERSION")))
+        .build()
+        .context("Could not create the HTTP client")?;
+    let mut config = reactor.config().await?;
+    loop {
+        let webhooks = Arc::new(config.borrow_and_update().webhooks.clone());
+        if webhooks.is_empty() {
+            config.changed().await?;
+            continue;
+        }
+
+        let mut events = events.subscribe();
+        let mut posts = JoinSet::new();
+        // The cameras that each webhook has already sent `battery_low` for
+        let mut battery_low: HashSet<(usize, String)> = Default::default();
+        let r = tokio::select! {
+            v = config.wait_for(|new| &new.webhooks != webhooks.as_ref()) => v.map(|_| ()).context("Webhook Config Watcher"),
+            v = async {
+                loop {
+                    let event = match events.recv().await {
+                        Ok(event) => event,
+                        Err(RecvError::Lagged(missed)) => {
+                            log::warn!("The webhooks missed {missed} events");
+                            continue;
+                        }
+                        Err(RecvError::Closed) => return Err(anyhow!("The events have stopped")),
+                    };
+
+                    let (hooks, event) = select(&webhooks, &mut battery_low, event);
+                    send(&reactor, &client, &webhooks, hooks, event, &mut posts).await;
+                    // Tidy up the finished posts
+                    while posts.try_join_next().is_some() {}
+                }
+            } => v,
+        };
+        r?;
+    }
+}
+
+/// The indexes of the `webhooks` that want the `event`, and the event that they get
+///
+/// The battery level is only sent as `battery_low` when it falls below the
+/// `battery_below` of each webhook, or if it is already low when we start.
+/// `battery_low` keeps the cameras that each webhook has sent it for
+fn select(
+    webhooks: &[WebhookConfig],
+    battery_low: &mut HashSet<(usize, String)>,
+    mut event: CameraEvent,
+) -> (Vec<usize>, CameraEvent) {
+    if let Some(level) = event
+        .battery_percent
+        .filter(|_| event.event == EventKind::Battery)
+    {
+        let mut hooks = vec![];
+        for (index, hook) in webhooks.iter().enumerate() {
+            let key = (index, event.camera.clone());
+            if level >= hook.battery_below {
+                battery_low.remove(&key);
+            } else if battery_low.insert(key) && hook.wants(&event.camera, EventKind::BatteryLow) {
+                hooks.push(index);
+            }
+        }
+        event.event = EventKind::BatteryLow;
+        return (hooks, event);
+    }
+    if !event.event.is_webhook() {
+        return (vec![], event);
+    }
+
+    let hooks = webhooks
+        .iter()
+        .enumerate()
+        .filter(|(_, hook)| hook.wants(&event.camera, event.event))
+        .map(|(index, _)| index)
+        .collect();
+    (hooks, event)
+}
+
+/// Post the `event` to the `hooks` in the background
+async fn send(
+    reactor: &NeoReactor,
+    client: &reqwest::Client,
+    webhooks: &Arc<Vec<WebhookConfig>>,
+    hooks: Vec<usize>,
+    event: CameraEvent,
+    posts: &mut JoinSet<()>,
+) {
+    if hooks.is_empty() {
+        return;
+    }
+    let Ok(instance) = reactor.get(&event.camera).await else {
+        return;
+    };
+    let webhooks = webhooks.clone();
+    let client = client.clone();
+    posts.spawn(async move {
+        let snapshot = if hooks.iter().any(|index| webhooks[*index].snapshot)
+            && event.event != EventKind::Disconnected
+        {
+            match snapshot(&instance).await {
+                Ok(jpeg) => Some(BASE64.encode(jpeg)),
+                Err(e) => {
+                    log::debug!("{}: No snapshot for the webhooks: {e:?}", event.camera);
+                    None
+                }
+            }
+        } else {
+            None
+        };
+
+        let mut sends = JoinSet::new();
+        for index in hooks {
+            let hook = webhooks[index].clone();
+            let payload = WebhookPayload {
+                event: event.clone(),
+                snapshot: snapshot.clone().filter(|_| hook.snapshot),
+            };
+            let client = client.clone();
+            sends.spawn(async move {
+                if let Err(e) = post(&client, &hook, &payload).await {
+                    log::warn!("{}: {e:#}", payload.event.camera);
+                }
+            });
+        }
+        while sends.join_next().await.is_some() {}
+    });
+}
+
+async fn snapshot(instance: &NeoInstance) -> AnyResult<Vec<u8>> {
+    timeout(
+        SNAPSHOT_TIMEOUT,
+        instance.run_passive_task(|cam| Box::pin(async move { Ok(cam.get_snapshot().await?) })),
+    )
+    .await
+    .map_err(|_| anyhow!("Timed out"))?
+}
+
+/// Post the `payload` to the webhook, trying again with a backoff if it fails
+async fn post(
+    client: &reqwest::Client,
+    hook: &WebhookConfig,
+    payload: &WebhookPayload,
+) -> AnyResult<()> {
+    let event = payload.event.event;
+    let camera = &payload.event.camera;
+    let mut backoff = Duration::from_secs(1);
+    for attempt in 0..=hook.retries {
+        if attempt > 0 {
+            sleep(backoff).await;
+            backoff = (backoff * 2).min(MAX_BACKOFF);
+        }
+        let mut request = client
+            .post(&hook.resolved_url)
+            .timeout(Duration::from_secs(hook.timeout))
+            .json(payload);
+        for (name, value) in hook.resolved_headers.iter() {
+            request = request.header(name, value);
+        }
+        match request.send().await {
+            Ok(reply) if reply.status().is_success() => {
+                log::debug!("{camera}: Sent {event} to the webhook {}", hook.url);
+                return Ok(());
+            }
+            // These will not work if we try again, except for too many requests
+            Ok(reply)
+                if reply.status().is_client_error()
+                    && reply.status() != reqwest::StatusCode::TOO_MANY_REQUESTS =>
+            {
+                return Err(anyhow!(
+                    "The webhook {} refused {event} with {}",
+                    hook.url,
+                    reply.status()
+                ));
+            }
+            Ok(reply) => log::debug!(
+                "{camera}: The webhook {} replied {} on try {}",
+                hook.url,
+                reply.status(),
+                attempt + 1
+            ),
+            Err(e) => log::debug!(
+                "{camera}: Could not reach the webhook {} on try {}: {e}",
+                hook.url,
+                attempt + 1
+            ),
+        }
+    }
+    Err(anyhow!(
+        "Gave up sending {event} to the webhook {} after {} tries",
+        hook.url,
+        hook.retries + 1
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+        task::JoinHandle,
+    };
+
+    /// A stand-in for the endpoint that replies with each of the `statuses` in turn
+    ///
+    /// Returns its url and the requests it got
+    async fn stand_in(statuses: Vec<u16>) -> (String, JoinHandle<Vec<String>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        let requests = tokio::spawn(async move {
+            let mut requests = vec![];
+            for status in statuses {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let mut request = vec![];
+                let mut buf = [0; 4096];
+                // Read the head and then as much of the body as it says
+                loop {
+                    let read = stream.read(&mut buf).await.unwrap();
+                    request.extend_from_slice(&buf[..read]);
+                    let text = String::from_utf8_lossy(&request).to_lowercase();
+                    if let Some(end) = text.find("\r\n\r\n") {
+                        let length = text
+                            .lines()
+                            .find_map(|line| line.strip_prefix("content-length:"))
+                            .and_then(|length| length.trim().parse::<usize>().ok())
+                            .unwrap_or(0);
+                        if request.len() >= end + 4 + length || read == 0 {
+                            break;
+                        }
+                    }
+                }
+                requests.push(String::from_utf8(request).unwrap());
+                let reply = format!(
+                    "HTTP/1.1 {status} Stand-in\r\ncontent-length: 0\r\nconnection: close\r\n\r\n"
+                );
+                stream.write_all(reply.as_bytes()).await.unwrap();
+            }
+            requests
+        });
+        (url, requests)
+    }
+
+    fn hook(url: &str, retries: u32) -> WebhookConfig {
+        let mut hook: WebhookConfig = toml::from_str(&format!(
+            r#"
+url = "{url}"
+retries = {retries}
+timeout = 5
+headers = {{ Authorization = "Bearer ${{NEOLINK_TEST_WEBHOOK_TOKEN}}" }}
+"#
+        ))
+        .unwrap();
+        std::env::set_var("NEOLINK_TEST_WEBHOOK_TOKEN", "abc123");
+        hook.resolve_secrets().unwrap();
+        hook
+    }
+
+    fn payload() -> WebhookPayload {
+        let mut event = CameraEvent::new("Garage", EventKind::Ai);
+        event.ai_types = vec!["people".to_string()];
+        WebhookPayload {
+            event,
+            snapshot: None,
+        }
+    }
+
+    fn battery(level: u32) -> CameraEvent {
+        let mut event = CameraEvent::new("Garage", EventKind::Battery);
+        event.battery_percent = Some(level);
+        event
+    }
+
+    #[test]
+    fn test_select() {
+        let mut all = hook("http://localhost/all", 0);
+        all.battery_below = 20;
+        let mut motion = hook("http://localhost/motion", 0);
+        motion.events = vec![EventKind::MotionStart, EventKind::BatteryLow];
+        motion.battery_below = 50;
+        let webhooks = [all, motion];
+        let mut battery_low = HashSet::new();
+
+        let mut selected = |event| {
+            let (hooks, event) = select(&webhooks, &mut battery_low, event);
+            (hooks, event.event)
+        };
+        assert_eq!(
+            selected(CameraEvent::new("Garage", EventKind::MotionStart)),
+            (vec![0, 1], EventKind::MotionStart)
+        );
+        assert_eq!(
+            selected(CameraEvent::new("Garage", EventKind::Sound)),
+            (vec![0], EventKind::Sound)
+        );
+        // Only for the event log, even for the webhooks without an events list
+        assert!(selected(CameraEvent::new("Garage", EventKind::Floodlight))
+            .0
+            .is_empty());
+        assert_eq!(selected(battery(80)), (vec![], EventKind::BatteryLow));
+
+        // Each webhook sends battery_low once when it falls below its own threshold
+        assert_eq!(selected(battery(40)), (vec![1], EventKind::BatteryLow));
+        assert!(selected(battery(30)).0.is_empty());
+        assert_eq!(selected(battery(10)).0, vec![0]);
+        assert!(selected(battery(60)).0.is_empty());
+        assert_eq!(selected(battery(10)).0, vec![0, 1]);
+    }
+
+    #[tokio::test]
+    async fn test_post() {
+        let (url, requests) = stand_in(vec![200]).await;
+        post(&reqwest::Client::new(), &hook(&url, 0), &payload())
+            .await
+            .unwrap();
+
+        let requests = requests.await.unwrap();
+        assert_eq!(requests.len(), 1);
+        let (head, body) = requests[0].split_once("\r\n\r\n").unwrap();
+        let head = head.to_lowercase();
+        assert!(head.starts_with("post /hook http/1.1\r\n"));
+        assert!(head.contains("\r\nauthorization: bearer abc123\r\n"));
+        assert!(head.contains("\r\ncontent-type: application/json\r\n"));
+        let body: serde_json::Value = serde_json::from_str(body).unwrap();
+        assert_eq!(body["camera"], "Garage");
+        assert_eq!(body["event"], "ai");
+        assert_eq!(body["ai_types"], serde_json::json!(["people"]));
+        assert!(body.get("snapshot").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_retries() {
+        let (url, requests) = stand_in(vec![503, 200]).await;
+        post(&reqwest::Client::new(), &hook(&url, 1), &payload())
+            .await
+            .unwrap();
+        assert_eq!(requests.await.unwrap().len(), 2);
+
+        // Too few retries
+        let (url, requests) = stand_in(vec![500, 500]).await;
+        let result = post(&reqwest::Client::new(), &hook(&url, 1), &payload()).await;
+        assert!(result.is_err());
+        assert_eq!(requests.await.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_refused() {
+        // A client error is not tried again
+        let (url, requests) = stand_in(vec![403]).await;
+        let result = post(&reqwest::Client::new(), &hook(&url, 3), &payload()).await;
+        assert!(result.is_err());
+        assert_eq!(requests.await.unwrap().len(), 1);
+    }
+}
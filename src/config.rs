@@ -1,23 +1,73 @@
 use crate::mqtt::Discoveries;
+use anyhow::{anyhow, Context, Result};
 #[cfg(feature = "gstreamer")]
 use neolink_core::bc_protocol::StreamKind;
 use neolink_core::bc_protocol::{DiscoveryMethods, PrintFormat};
 use once_cell::sync::Lazy;
 use regex::Regex;
+use schemars::{json_schema, JsonSchema, Schema, SchemaGenerator};
 use serde::{Deserialize, Serialize};
 use std::clone::Clone;
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashSet};
+use std::{fs, path::Path};
 use validator::Validate;
 use validator::ValidationError;
 
+mod persist;
+mod schedule;
+mod secrets;
+mod strict;
+mod timezone;
+
+pub(crate) use persist::write_config;
+pub(crate) use schedule::{ScheduleTime, ScheduleTimeKind};
+pub(crate) use strict::UnknownKey;
+pub(crate) use timezone::TimeZone;
+
+/// The `(field, aliases)` of the `#[serde(alias)]`s of a config table
+///
+/// These are next to each struct, a test checks them against serde
+type Aliases = &'static [(&'static str, &'static [&'static str])];
+
+/// The aliases of the config tables as `(table, aliases)`
+///
+/// These are used to keep the keys of the file when writing it and
+/// to suggest the right key for unknown ones
+const ALIASES: &[(&str, Aliases)] = &[
+    ("cameras", CameraConfig::ALIASES),
+    ("cameras.audio_level", AudioLevelConfig::ALIASES),
+    ("cameras.pause", PauseConfig::ALIASES),
+    ("cameras.schedule", ScheduleConfig::ALIASES),
+    ("cameras.settings", CameraSettingsConfig::ALIASES),
+    ("cameras.time_sync", TimeSyncConfig::ALIASES),
+    ("health", HealthConfig::ALIASES),
+    ("mqtt", MqttServerConfig::ALIASES),
+    ("rules", RuleConfig::ALIASES),
+    ("rules.when", RuleTrigger::ALIASES),
+    ("users", UserConfig::ALIASES),
+];
+
+/// The aliases of the `field` of the config `table`
+fn field_aliases<'a>(table: &'a str, field: &'a str) -> impl Iterator<Item = &'static str> + 'a {
+    ALIASES
+        .iter()
+        .filter(move |(name, _)| *name == table)
+        .flat_map(|(_, aliases)| aliases.iter())
+        .filter(move |(name, _)| *name == field)
+        .flat_map(|(_, aliases)| aliases.iter().copied())
+}
+
 static RE_TLS_CLIENT_AUTH: Lazy<Regex> =
     Lazy::new(|| Regex::new(r"^(none|request|require)$").unwrap());
+static RE_WEBHOOK_URL: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^[hH][tT][tT][pP][sS]?://").unwrap());
 static RE_PAUSE_MODE: Lazy<Regex> = Lazy::new(|| Regex::new(r"^(black|still|test|none)$").unwrap());
 static RE_MAXENC_SRC: Lazy<Regex> = Lazy::new(|| {
     Regex::new(r"^([nN]one|[Aa][Ee][Ss]|[Bb][Cc][Ee][Nn][Cc][Rr][Yy][Pp][Tt])$").unwrap()
 });
 
-#[derive(Debug, Deserialize, Serialize, Validate, Clone, PartialEq)]
+#[derive(Debug, Deserialize, Serialize, JsonSchema, Validate, Clone, PartialEq)]
+#[validate(schema(function = "validate_config", skip_on_field_errors = true))]
 pub(crate) struct Config {
     #[validate(nested)]
     pub(crate) cameras: Vec<CameraConfig>,
@@ -35,9 +85,13 @@ pub(crate) struct Config {
     #[serde(default = "default_certificate")]
     pub(crate) certificate: Option<String>,
 
-    #[serde(default = "Default::default")]
+    #[serde(default)]
     pub(crate) mqtt: Option<MqttServerConfig>,
 
+    #[validate(nested)]
+    #[serde(default)]
+    pub(crate) health: Option<HealthConfig>,
+
     #[validate(regex(
         path = *RE_TLS_CLIENT_AUTH,
         message = "Incorrect tls auth",
@@ -49,9 +103,78 @@ pub(crate) struct Config {
     #[validate(nested)]
     #[serde(default)]
     pub(crate) users: Vec<UserConfig>,
+
+    /// Refuse to load a config file with unknown keys
+    #[serde(default = "default_false")]
+    pub(crate) strict_config: bool,
+
+    /// Automations that run actions on the cameras when something happens
+    #[validate(nested)]
+    #[serde(default)]
+    pub(crate) rules: Vec<RuleConfig>,
+
+    /// HTTP endpoints that are sent the camera events
+    #[validate(nested)]
+    #[serde(default)]
+    pub(crate) webhooks: Vec<WebhookConfig>,
+
+    /// Keep the camera events on disk
+    #[validate(nested)]
+    #[serde(default)]
+    pub(crate) event_log: Option<EventLogConfig>,
 }
 
-#[derive(Debug, Deserialize, Serialize, Clone, Validate, PartialEq, Eq)]
+impl Config {
+    /// Read, parse and validate the config file at `path`
+    ///
+    /// Unknown keys are an error if `strict` or the config's `strict_config`
+    /// is set, otherwise they are only logged
+    pub(crate) fn load(path: &Path, strict: bool) -> Result<Config> {
+        let contents =
+            fs::read_to_string(path).with_context(|| format!("Failed to read {:?}", path))?;
+        Self::parse(&contents, path, strict)
+    }
+
+    /// Parse and validate the `contents` of the config file at `path`
+    pub(crate) fn parse(contents: &str, path: &Path, strict: bool) -> Result<Config> {
+        let (mut config, unknown) = Config::from_toml(contents)
+            .with_context(|| format!("Failed to parse the {:?} config file", path))?;
+
+        if strict || config.strict_config {
+            if !unknown.is_empty() {
+                let unknown = unknown
+                    .iter()
+                    .map(UnknownKey::to_string)
+                    .collect::<Vec<_>>();
+                return Err(anyhow!(
+                    "Strict mode: the {:?} config file has {} unknown key(s):\n  {}",
+                    path,
+                    unknown.len(),
+                    unknown.join("\n  ")
+                ));
+            }
+        } else {
+            for key in unknown.iter() {
+                log::warn!("{:?}: Ignoring {key}", path);
+            }
+        }
+
+        config.resolve_secrets().with_context(|| {
+            format!(
+                "Failed to resolve the secrets in the {:?} config file",
+                path
+            )
+        })?;
+
+        config
+            .validate()
+            .with_context(|| format!("Failed to validate the {:?} config file", path))?;
+
+        Ok(config)
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, JsonSchema, Clone, Validate, PartialEq, Eq)]
 #[validate(schema(function = "validate_mqtt_server", skip_on_field_errors = true))]
 pub(crate) struct MqttServerConfig {
     #[serde(alias = "server")]
@@ -62,14 +185,61 @@ pub(crate) struct MqttServerConfig {
     #[serde(default, skip_serializing)]
     pub(crate) credentials: Option<(String, String)>,
 
+    /// Read the password of the credentials from this file
+    pub(crate) password_file: Option<std::path::PathBuf>,
+
+    /// Read the password of the credentials from this environment variable
+    pub(crate) password_env: Option<String>,
+
     #[serde(default, skip_serializing)]
     pub(crate) ca: Option<std::path::PathBuf>,
 
     #[serde(default, skip_serializing)]
     pub(crate) client_auth: Option<(std::path::PathBuf, std::path::PathBuf)>,
+
+    /// Write config changes made over MQTT back into the config file
+    #[serde(default = "default_false", alias = "persist")]
+    pub(crate) persist_config: bool,
+
+    /// The directory of the files that `control/talk` may play
+    #[serde(default)]
+    pub(crate) talk_dir: Option<std::path::PathBuf>,
 }
 
-#[derive(Debug, Deserialize, Serialize, Clone, Copy, Eq, PartialEq)]
+impl MqttServerConfig {
+    const ALIASES: Aliases = &[
+        ("broker_addr", &["server"]),
+        ("persist_config", &["persist"]),
+    ];
+}
+
+#[derive(Debug, Deserialize, Serialize, JsonSchema, Clone, Validate, PartialEq, Eq)]
+pub(crate) struct HealthConfig {
+    /// Serve `/healthz` and `/readyz` over http
+    #[serde(default = "default_true", alias = "http")]
+    pub(crate) enable_http: bool,
+
+    #[serde(rename = "bind", default = "default_bind_addr")]
+    pub(crate) bind_addr: String,
+
+    #[serde(rename = "port", default = "default_health_port")]
+    pub(crate) bind_port: u16,
+
+    /// Send READY and WATCHDOG notifications to systemd
+    #[serde(default = "default_false", alias = "systemd")]
+    pub(crate) sd_notify: bool,
+
+    /// How long in ms the reactor and cameras may take to respond before they are unhealthy
+    #[validate(range(min = 100, message = "Timeout should be > 100ms", code = "timeout"))]
+    #[serde(default = "default_health_timeout")]
+    pub(crate) timeout: u64,
+}
+
+impl HealthConfig {
+    const ALIASES: Aliases = &[("enable_http", &["http"]), ("sd_notify", &["systemd"])];
+}
+
+#[derive(Debug, Deserialize, Serialize, JsonSchema, Clone, Copy, Eq, PartialEq)]
 pub(crate) enum StreamConfig {
     #[serde(alias = "none")]
     None,
@@ -126,7 +296,7 @@ impl StreamConfig {
     }
 }
 
-#[derive(Debug, Deserialize, Serialize, Validate, Clone, PartialEq)]
+#[derive(Debug, Deserialize, Serialize, JsonSchema, Validate, Clone, PartialEq)]
 #[validate(schema(function = "validate_camera_config"))]
 pub(crate) struct CameraConfig {
     pub(crate) name: String,
@@ -142,11 +312,22 @@ pub(crate) struct CameraConfig {
     #[serde(alias = "pass", skip_serializing, default)]
     pub(crate) password: Option<String>,
 
+    /// Read the password from this file
+    pub(crate) password_file: Option<std::path::PathBuf>,
+
+    /// Read the password from this environment variable
+    pub(crate) password_env: Option<String>,
+
     #[serde(default = "default_stream")]
     pub(crate) stream: StreamConfig,
 
     pub(crate) permitted_users: Option<Vec<String>>,
 
+    /// Users that may talk through the camera from the RTSP backchannel
+    ///
+    /// The backchannel is off when this is not set
+    pub(crate) talk_users: Option<Vec<String>>,
+
     #[validate(range(min = 0, max = 31, message = "Invalid channel", code = "channel_id"))]
     #[serde(default = "default_channel_id", alias = "channel")]
     pub(crate) channel_id: u8,
@@ -160,6 +341,7 @@ pub(crate) struct CameraConfig {
     pub(crate) pause: PauseConfig,
 
     #[serde(default = "default_discovery")]
+    #[schemars(schema_with = "discovery_methods_schema")]
     pub(crate) discovery: DiscoveryMethods,
 
     #[serde(default = "default_maxenc")]
@@ -175,6 +357,7 @@ pub(crate) struct CameraConfig {
     pub(crate) strict: bool,
 
     #[serde(default = "default_print", alias = "print")]
+    #[schemars(schema_with = "print_format_schema")]
     pub(crate) print_format: PrintFormat,
 
     #[serde(default = "default_update_time", alias = "time")]
@@ -218,9 +401,539 @@ pub(crate) struct CameraConfig {
 
     #[serde(default = "default_false", alias = "idle", alias = "idle_disc")]
     pub(crate) idle_disconnect: bool,
+
+    /// The settings that neolink keeps the camera at
+    #[validate(nested)]
+    #[serde(default)]
+    pub(crate) settings: Option<CameraSettingsConfig>,
+
+    /// Keep the camera's clock in sync with this host
+    #[validate(nested)]
+    #[serde(default)]
+    pub(crate) time_sync: Option<TimeSyncConfig>,
+
+    /// Switch the lights at set times, such as from sunset to sunrise
+    #[validate(nested)]
+    #[serde(default)]
+    pub(crate) schedule: Option<ScheduleConfig>,
+
+    /// Measure the sound that the camera hears and report loud noises
+    #[validate(nested)]
+    #[serde(default)]
+    pub(crate) audio_level: Option<AudioLevelConfig>,
+}
+
+impl CameraConfig {
+    const ALIASES: Aliases = &[
+        ("buffer_duration", &["duration", "buffer"]),
+        ("channel_id", &["channel"]),
+        ("debug", &["verbose"]),
+        ("enabled", &["enable"]),
+        ("idle_disconnect", &["idle", "idle_disc"]),
+        ("max_discovery_retries", &["retries", "max_retries"]),
+        ("password", &["pass"]),
+        ("print_format", &["print"]),
+        ("push_notifications", &["push", "push_noti"]),
+        ("splash_pattern", &["pattern"]),
+        ("update_time", &["time"]),
+        ("use_splash", &["splash"]),
+    ];
+
+    /// True if the two configs would connect and login to the camera in the same way
+    ///
+    /// Changes to any other field can be applied without a reconnect
+    pub(crate) fn same_connection(&self, other: &CameraConfig) -> bool {
+        self.camera_addr == other.camera_addr
+            && self.camera_uid == other.camera_uid
+            && self.username == other.username
+            && self.password == other.password
+            && self.channel_id == other.channel_id
+            && self.discovery == other.discovery
+            && self.max_encryption == other.max_encryption
+            && self.debug == other.debug
+            && self.max_discovery_retries == other.max_discovery_retries
+    }
+}
+
+/// The desired state of the camera's settings
+///
+/// Unset settings are left as they are on the camera
+#[derive(Debug, Deserialize, Serialize, JsonSchema, Clone, Validate, PartialEq, Eq)]
+pub(crate) struct CameraSettingsConfig {
+    /// Seconds between checks that the camera still has these settings, 0 to only apply on connect
+    #[serde(default = "default_settings_interval", alias = "interval")]
+    pub(crate) check_interval: u64,
+
+    /// The status LED
+    #[serde(alias = "status_led")]
+    pub(crate) led: Option<bool>,
+
+    /// The IR lights
+    #[serde(alias = "irled")]
+    pub(crate) ir: Option<IrMode>,
+
+    pub(crate) pir: Option<bool>,
+
+    /// Turn the floodlight on with motion at night
+    pub(crate) floodlight_tasks: Option<bool>,
+
+    /// Email notifications
+    pub(crate) email: Option<bool>,
+
+    pub(crate) server_port: Option<ServicePortConfig>,
+    pub(crate) http_port: Option<ServicePortConfig>,
+    pub(crate) https_port: Option<ServicePortConfig>,
+    pub(crate) rtsp_port: Option<ServicePortConfig>,
+    pub(crate) rtmp_port: Option<ServicePortConfig>,
+    pub(crate) onvif_port: Option<ServicePortConfig>,
+}
+
+impl CameraSettingsConfig {
+    const ALIASES: Aliases = &[
+        ("check_interval", &["interval"]),
+        ("ir", &["irled"]),
+        ("led", &["status_led"]),
+    ];
+}
+
+/// How the camera's clock is kept in sync
+#[derive(Debug, Deserialize, Serialize, JsonSchema, Clone, Validate, PartialEq, Eq)]
+pub(crate) struct TimeSyncConfig {
+    /// Seconds between checks of the camera's clock, 0 to only check on connect
+    #[serde(default = "default_time_sync_interval", alias = "interval")]
+    pub(crate) check_interval: u64,
+
+    /// Seconds that the camera's clock may drift before it is set again
+    #[serde(default = "default_max_drift", alias = "threshold")]
+    pub(crate) max_drift: u64,
+
+    /// A POSIX TZ rule such as `CET-1CEST,M3.5.0,M10.5.0/3`
+    ///
+    /// If unset the camera keeps the offset from UTC that it already has
+    #[serde(default, alias = "tz")]
+    #[schemars(with = "Option<String>")]
+    pub(crate) timezone: Option<TimeZone>,
+}
+
+impl TimeSyncConfig {
+    const ALIASES: Aliases = &[
+        ("check_interval", &["interval"]),
+        ("max_drift", &["threshold"]),
+        ("timezone", &["tz"]),
+    ];
+}
+
+/// How the sound that the camera hears is measured
+#[derive(Debug, Deserialize, Serialize, JsonSchema, Clone, Validate, PartialEq)]
+pub(crate) struct AudioLevelConfig {
+    /// The dB below full scale that the sound must reach to be a loud noise
+    #[validate(range(min = -96.0, max = 0.0, message = "Invalid threshold", code = "threshold"))]
+    #[serde(default = "default_loud_threshold")]
+    pub(crate) threshold: f32,
+
+    /// Seconds that the sound must stay under the threshold before the loud noise ends
+    #[validate(range(max = 86400, message = "Hold should be at most a day", code = "hold"))]
+    #[serde(default = "default_loud_hold", alias = "hold_time")]
+    pub(crate) hold: u64,
+
+    /// Seconds between the reports of the level
+    #[validate(range(
+        min = 1,
+        max = 86400,
+        message = "Interval should be between 1s and a day",
+        code = "report_interval"
+    ))]
+    #[serde(default = "default_audio_level_interval", alias = "interval")]
+    pub(crate) report_interval: u64,
+}
+
+impl AudioLevelConfig {
+    const ALIASES: Aliases = &[("hold", &["hold_time"]), ("report_interval", &["interval"])];
+}
+
+/// Where the camera is and when to switch its lights
+#[derive(Debug, Deserialize, Serialize, JsonSchema, Clone, Validate, PartialEq)]
+pub(crate) struct ScheduleConfig {
+    /// Degrees north of the equator, negative for south
+    #[validate(range(min = -90.0, max = 90.0, message = "Invalid latitude"))]
+    #[serde(alias = "lat")]
+    pub(crate) latitude: f64,
+
+    /// Degrees east of Greenwich, negative for west
+    #[validate(range(min = -180.0, max = 180.0, message = "Invalid longitude"))]
+    #[serde(alias = "lon", alias = "long")]
+    pub(crate) longitude: f64,
+
+    /// A POSIX TZ rule for the clock times such as `22:30`
+    ///
+    /// If unset the `time_sync` timezone is used or else UTC
+    #[serde(default, alias = "tz")]
+    #[schemars(with = "Option<String>")]
+    pub(crate) timezone: Option<TimeZone>,
+
+    #[validate(nested)]
+    #[serde(default)]
+    pub(crate) lights: Vec<LightScheduleConfig>,
+}
+
+impl ScheduleConfig {
+    const ALIASES: Aliases = &[
+        ("latitude", &["lat"]),
+        ("longitude", &["lon", "long"]),
+        ("timezone", &["tz"]),
+    ];
+}
+
+/// A light that is in one state from `from` until `to` and in another for the rest of the day
+#[derive(Debug, Deserialize, Serialize, JsonSchema, Clone, Validate, PartialEq, Eq)]
+#[validate(schema(function = "validate_light_schedule"))]
+pub(crate) struct LightScheduleConfig {
+    pub(crate) light: ScheduledLight,
+
+    /// Such as `sunset+15m`, `sunrise-1h` or `22:30`
+    #[schemars(with = "String")]
+    pub(crate) from: ScheduleTime,
+
+    #[schemars(with = "String")]
+    pub(crate) to: ScheduleTime,
+
+    /// The state from `from` until `to`
+    #[serde(default = "default_light_state")]
+    pub(crate) state: IrMode,
+
+    /// The state for the rest of the day
+    #[serde(default = "default_light_otherwise")]
+    pub(crate) otherwise: IrMode,
+}
+
+#[derive(Debug, Deserialize, Serialize, JsonSchema, Clone, Copy, Eq, PartialEq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum ScheduledLight {
+    /// The IR lights, which can also be set to `auto`
+    #[serde(alias = "ir")]
+    Irled,
+    /// The floodlight itself
+    Floodlight,
+    /// The status LED
+    #[serde(alias = "status_led")]
+    Led,
+    /// The automatic floodlight on motion at night
+    FloodlightTasks,
+}
+
+/// Posts the camera events as JSON to `url`
+#[derive(Debug, Deserialize, Serialize, JsonSchema, Clone, Validate, PartialEq, Eq)]
+pub(crate) struct WebhookConfig {
+    /// This can use `${VAR}`, it is kept as written so that the secret is not shared
+    pub(crate) url: String,
+
+    /// The `url` with its `${VAR}`s filled in
+    #[validate(regex(
+        path = *RE_WEBHOOK_URL,
+        message = "The url must start with http:// or https://",
+        code = "url"
+    ))]
+    #[serde(skip)]
+    pub(crate) resolved_url: String,
+
+    /// The events to send, all of them if empty
+    #[serde(default)]
+    pub(crate) events: Vec<EventKind>,
+
+    /// The cameras to send the events of, all of them if empty
+    #[serde(default)]
+    pub(crate) cameras: Vec<String>,
+
+    /// Add a JPEG snapshot from the camera to the event
+    #[serde(default = "default_false")]
+    pub(crate) snapshot: bool,
+
+    /// Send `battery_low` when the battery falls below this percent
+    #[validate(range(
+        min = 1,
+        max = 100,
+        message = "Invalid percent",
+        code = "battery_below"
+    ))]
+    #[serde(default = "default_webhook_battery")]
+    pub(crate) battery_below: u32,
+
+    /// Extra HTTP headers such as `Authorization`, these can use `${VAR}`
+    #[serde(default)]
+    pub(crate) headers: BTreeMap<String, String>,
+
+    /// The `headers` with their `${VAR}`s filled in
+    #[serde(skip)]
+    pub(crate) resolved_headers: BTreeMap<String, String>,
+
+    /// How many times a failed post is tried again
+    #[serde(default = "default_webhook_retries")]
+    pub(crate) retries: u32,
+
+    /// Seconds to wait for the endpoint to reply
+    #[validate(range(min = 1, message = "Timeout should be at least 1s", code = "timeout"))]
+    #[serde(default = "default_webhook_timeout")]
+    pub(crate) timeout: u64,
+}
+
+impl WebhookConfig {
+    /// If this webhook wants the `event` of the `camera`
+    pub(crate) fn wants(&self, camera: &str, event: EventKind) -> bool {
+        (self.cameras.is_empty() || self.cameras.iter().any(|name| name == camera))
+            && (self.events.is_empty() || self.events.contains(&event))
+    }
+}
+
+/// The kinds of camera events
+#[derive(Debug, Deserialize, Serialize, JsonSchema, Clone, Copy, Eq, PartialEq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum EventKind {
+    MotionStart,
+    MotionStop,
+    /// The camera's AI detected a new class such as `people` during the motion
+    Ai,
+    PushNotification,
+    /// The battery level changed
+    Battery,
+    /// The battery fell below the webhook's `battery_below`
+    BatteryLow,
+    Connected,
+    Disconnected,
+    /// The floodlight turned on or off, only for `neolink events`
+    Floodlight,
+    /// Someone pressed the button of a doorbell
+    Visitor,
+    /// The sound detection of the camera heard something
+    Sound,
+    /// The sound that the camera hears went over the `audio_level` threshold
+    LoudNoise,
+}
+
+impl EventKind {
+    /// If the webhooks send this kind, the others are only for the event log
+    pub(crate) fn is_webhook(&self) -> bool {
+        !matches!(self, EventKind::Battery | EventKind::Floodlight)
+    }
+
+    /// The name as used in the config
+    pub(crate) fn as_str(&self) -> &'static str {
+        match self {
+            EventKind::MotionStart => "motion_start",
+            EventKind::MotionStop => "motion_stop",
+            EventKind::Ai => "ai",
+            EventKind::PushNotification => "push_notification",
+            EventKind::Battery => "battery",
+            EventKind::BatteryLow => "battery_low",
+            EventKind::Connected => "connected",
+            EventKind::Disconnected => "disconnected",
+            EventKind::Floodlight => "floodlight",
+            EventKind::Visitor => "visitor",
+            EventKind::Sound => "sound",
+            EventKind::LoudNoise => "loud_noise",
+        }
+    }
+}
+
+impl std::fmt::Display for EventKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// Appends the camera events to a JSON lines file
+#[derive(Debug, Deserialize, Serialize, JsonSchema, Clone, Validate, PartialEq, Eq)]
+pub(crate) struct EventLogConfig {
+    pub(crate) path: std::path::PathBuf,
+
+    /// The events to log, all of them if empty
+    #[serde(default)]
+    pub(crate) events: Vec<EventKind>,
+
+    /// Rotate the file when it is larger than this many MiB
+    #[validate(range(
+        min = 1,
+        message = "The size should be at least 1 MiB",
+        code = "max_size"
+    ))]
+    #[serde(default = "default_event_log_size")]
+    pub(crate) max_size: u64,
+
+    /// How many of the rotated files to keep
+    #[serde(default = "default_event_log_keep")]
+    pub(crate) keep: usize,
+}
+
+impl EventLogConfig {
+    /// The log files from the oldest to the current one
+    pub(crate) fn files(&self) -> Vec<std::path::PathBuf> {
+        (1..=self.keep)
+            .rev()
+            .map(|index| self.rotated(index))
+            .chain(std::iter::once(self.path.clone()))
+            .collect()
+    }
+
+    /// The path of the `index`th most recent rotated file
+    pub(crate) fn rotated(&self, index: usize) -> std::path::PathBuf {
+        let mut path = self.path.clone().into_os_string();
+        path.push(format!(".{index}"));
+        path.into()
+    }
+}
+
+/// Runs the actions in `then` whenever `when` happens
+#[derive(Debug, Deserialize, Serialize, JsonSchema, Clone, Validate, PartialEq)]
+pub(crate) struct RuleConfig {
+    pub(crate) name: String,
+
+    #[serde(default = "default_true", alias = "enable")]
+    pub(crate) enabled: bool,
+
+    pub(crate) when: RuleTrigger,
+
+    #[validate(length(min = 1, message = "A rule needs at least one action"))]
+    pub(crate) then: Vec<RuleAction>,
+
+    /// Seconds after the rule runs during which it will not run again
+    #[serde(default)]
+    pub(crate) cooldown: u64,
+}
+
+impl RuleConfig {
+    const ALIASES: Aliases = &[("enabled", &["enable"])];
+}
+
+/// What makes a rule run
+#[derive(Debug, Deserialize, Serialize, JsonSchema, Clone, PartialEq)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub(crate) enum RuleTrigger {
+    MotionStart {
+        camera: String,
+    },
+    MotionStop {
+        camera: String,
+    },
+    /// The camera's AI detected a class such as `people`, `vehicle` or `dog_cat`
+    Ai {
+        camera: String,
+        #[serde(alias = "type")]
+        class: String,
+    },
+    PushNotification {
+        camera: String,
+    },
+    /// The battery fell below `percent`
+    BatteryBelow {
+        camera: String,
+        percent: u32,
+    },
+    /// The connection to the camera was lost, disconnecting on purpose
+    /// such as with `idle_disconnect` does not count
+    Disconnected {
+        camera: String,
+    },
+    /// Every day at a time such as `sunset` or `22:30`
+    ///
+    /// The location and timezone are those of the camera's
+    /// `[cameras.schedule]`, without a camera clock times are in UTC
+    Schedule {
+        #[schemars(with = "String")]
+        at: ScheduleTime,
+        camera: Option<String>,
+    },
+}
+
+/// What a rule does
+#[derive(Debug, Deserialize, Serialize, JsonSchema, Clone, PartialEq)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub(crate) enum RuleAction {
+    /// Turn the floodlight on for some seconds
+    Floodlight {
+        camera: String,
+        #[serde(default = "default_rule_floodlight")]
+        seconds: u16,
+    },
+    Siren {
+        camera: String,
+    },
+    PtzPreset {
+        camera: String,
+        preset: u8,
+    },
+    Led {
+        camera: String,
+        on: bool,
+    },
+    /// Play an audio file through the camera's speaker
+    Talk {
+        camera: String,
+        file: std::path::PathBuf,
+        #[serde(default = "default_rule_volume")]
+        volume: f32,
+    },
+    /// Publish to the MQTT broker, this only works with `neolink mqtt`
+    Mqtt {
+        topic: String,
+        message: String,
+        #[serde(default = "default_false")]
+        retain: bool,
+    },
+    Reboot {
+        camera: String,
+    },
 }
 
-#[derive(Debug, Deserialize, Serialize, Validate, Clone, PartialEq, Eq, Hash)]
+impl RuleTrigger {
+    /// The aliases of the fields of the variants
+    const ALIASES: Aliases = &[("class", &["type"])];
+
+    /// The camera that the trigger watches
+    pub(crate) fn camera(&self) -> Option<&str> {
+        match self {
+            RuleTrigger::MotionStart { camera }
+            | RuleTrigger::MotionStop { camera }
+            | RuleTrigger::Ai { camera, .. }
+            | RuleTrigger::PushNotification { camera }
+            | RuleTrigger::BatteryBelow { camera, .. }
+            | RuleTrigger::Disconnected { camera } => Some(camera),
+            RuleTrigger::Schedule { camera, .. } => camera.as_deref(),
+        }
+    }
+}
+
+impl RuleAction {
+    /// The camera that the action is run on
+    pub(crate) fn camera(&self) -> Option<&str> {
+        match self {
+            RuleAction::Floodlight { camera, .. }
+            | RuleAction::Siren { camera }
+            | RuleAction::PtzPreset { camera, .. }
+            | RuleAction::Led { camera, .. }
+            | RuleAction::Talk { camera, .. }
+            | RuleAction::Reboot { camera } => Some(camera),
+            RuleAction::Mqtt { .. } => None,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, JsonSchema, Clone, Copy, Eq, PartialEq)]
+pub(crate) enum IrMode {
+    #[serde(alias = "on", alias = "open")]
+    On,
+    #[serde(alias = "off", alias = "close")]
+    Off,
+    #[serde(alias = "auto")]
+    Auto,
+}
+
+/// A service port on the camera, unset values are left as they are
+#[derive(Debug, Deserialize, Serialize, JsonSchema, Clone, Copy, Eq, PartialEq)]
+pub(crate) struct ServicePortConfig {
+    pub(crate) enable: Option<bool>,
+    pub(crate) port: Option<u32>,
+}
+
+#[derive(Debug, Deserialize, Serialize, JsonSchema, Validate, Clone, PartialEq, Eq, Hash)]
 pub(crate) struct UserConfig {
     #[validate(custom(function = "validate_username"))]
     #[serde(alias = "username")]
@@ -228,12 +941,35 @@ pub(crate) struct UserConfig {
 
     #[serde(alias = "password", skip_serializing, default)]
     pub(crate) pass: Option<String>,
+
+    /// Read the password from this file
+    #[serde(alias = "password_file")]
+    pub(crate) pass_file: Option<std::path::PathBuf>,
+
+    /// Read the password from this environment variable
+    #[serde(alias = "password_env")]
+    pub(crate) pass_env: Option<String>,
+}
+
+impl UserConfig {
+    const ALIASES: Aliases = &[
+        ("name", &["username"]),
+        ("pass", &["password"]),
+        ("pass_env", &["password_env"]),
+        ("pass_file", &["password_file"]),
+    ];
 }
 
-#[derive(Debug, Deserialize, Serialize, Clone, Validate, PartialEq, Eq)]
+#[derive(Debug, Deserialize, Serialize, JsonSchema, Clone, Validate, PartialEq, Eq)]
 pub(crate) struct MqttConfig {
     #[serde(default = "default_true")]
     pub(crate) enable_motion: bool,
+    /// Publish the rings of a doorbell on `status/visitor`
+    #[serde(default = "default_true")]
+    pub(crate) enable_visitor: bool,
+    /// Publish the alarms of the sound detection of the camera on `status/sound`
+    #[serde(default = "default_true")]
+    pub(crate) enable_sound: bool,
     #[serde(default = "default_true")]
     pub(crate) enable_light: bool,
     #[serde(default = "default_true")]
@@ -246,6 +982,13 @@ pub(crate) struct MqttConfig {
         code = "battery_update"
     ))]
     pub(crate) battery_update: u64,
+    /// Publish the free space and state of the SD card on `status/disk`
+    #[serde(default = "default_true")]
+    pub(crate) enable_disk: bool,
+    /// Update time in ms
+    #[serde(default = "default_60000")]
+    #[validate(range(min = 500, message = "Update ms should be > 500", code = "disk_update"))]
+    pub(crate) disk_update: u64,
     #[serde(default = "default_true")]
     pub(crate) enable_preview: bool,
     /// Update time in ms
@@ -275,7 +1018,7 @@ pub(crate) struct MqttConfig {
     pub(crate) discovery: Option<MqttDiscoveryConfig>,
 }
 
-#[derive(Debug, Deserialize, Serialize, Clone, Validate, PartialEq, Eq)]
+#[derive(Debug, Deserialize, Serialize, JsonSchema, Clone, Validate, PartialEq, Eq)]
 pub(crate) struct MqttDiscoveryConfig {
     pub(crate) topic: String,
 
@@ -287,6 +1030,12 @@ fn validate_mqtt_server(config: &MqttServerConfig) -> Result<(), ValidationError
         Err(ValidationError::new(
             "Cannot have both ca and client_auth set",
         ))
+    } else if config.credentials.is_none()
+        && (config.password_file.is_some() || config.password_env.is_some())
+    {
+        Err(ValidationError::new(
+            "password_file and password_env need the username from credentials",
+        ))
     } else {
         Ok(())
     }
@@ -303,9 +1052,13 @@ const fn default_false() -> bool {
 fn default_mqtt() -> MqttConfig {
     MqttConfig {
         enable_motion: true,
+        enable_visitor: true,
+        enable_sound: true,
         enable_light: true,
         enable_battery: true,
         battery_update: 2000,
+        enable_disk: true,
+        disk_update: 60000,
         enable_preview: true,
         preview_update: 2000,
         enable_floodlight: true,
@@ -322,11 +1075,25 @@ fn default_discovery() -> DiscoveryMethods {
     DiscoveryMethods::Relay
 }
 
+fn discovery_methods_schema(_: &mut SchemaGenerator) -> Schema {
+    json_schema!({
+        "type": "string",
+        "enum": ["None", "Local", "Remote", "Map", "Relay", "none", "local", "remote", "map", "relay"],
+    })
+}
+
+fn print_format_schema(_: &mut SchemaGenerator) -> Schema {
+    json_schema!({
+        "type": "string",
+        "enum": ["None", "Human", "Xml"],
+    })
+}
+
 fn default_maxenc() -> String {
     "Aes".to_string()
 }
 
-#[derive(Debug, Deserialize, Serialize, Validate, Clone, PartialEq)]
+#[derive(Debug, Deserialize, Serialize, JsonSchema, Validate, Clone, PartialEq)]
 pub(crate) struct PauseConfig {
     #[serde(default = "default_on_motion")]
     pub(crate) on_motion: bool,
@@ -346,7 +1113,14 @@ pub(crate) struct PauseConfig {
     pub(crate) mode: String,
 }
 
-#[derive(Debug, Deserialize, Serialize, Clone, Copy, Eq, PartialEq)]
+impl PauseConfig {
+    const ALIASES: Aliases = &[
+        ("motion_timeout", &["timeout"]),
+        ("on_disconnect", &["on_client"]),
+    ];
+}
+
+#[derive(Debug, Deserialize, Serialize, JsonSchema, Clone, Copy, Eq, PartialEq)]
 pub(crate) enum SplashPattern {
     #[serde(alias = "smpte")]
     Smpte,
@@ -445,6 +1219,74 @@ fn default_bind_port() -> u16 {
     8554
 }
 
+fn default_settings_interval() -> u64 {
+    600
+}
+
+fn default_time_sync_interval() -> u64 {
+    3600
+}
+
+fn default_max_drift() -> u64 {
+    5
+}
+
+fn default_loud_threshold() -> f32 {
+    -25.0
+}
+
+fn default_loud_hold() -> u64 {
+    5
+}
+
+fn default_audio_level_interval() -> u64 {
+    5
+}
+
+fn default_rule_floodlight() -> u16 {
+    60
+}
+
+fn default_rule_volume() -> f32 {
+    1.0
+}
+
+fn default_webhook_battery() -> u32 {
+    20
+}
+
+fn default_webhook_retries() -> u32 {
+    5
+}
+
+fn default_webhook_timeout() -> u64 {
+    10
+}
+
+fn default_event_log_size() -> u64 {
+    10
+}
+
+fn default_event_log_keep() -> usize {
+    5
+}
+
+fn default_light_state() -> IrMode {
+    IrMode::On
+}
+
+fn default_light_otherwise() -> IrMode {
+    IrMode::Off
+}
+
+fn default_health_port() -> u16 {
+    8555
+}
+
+fn default_health_timeout() -> u64 {
+    5000
+}
+
 fn default_stream() -> StreamConfig {
     StreamConfig::All
 }
@@ -510,6 +1352,10 @@ fn default_2000() -> u64 {
     2000
 }
 
+fn default_60000() -> u64 {
+    60000
+}
+
 fn default_splash() -> SplashPattern {
     SplashPattern::Snow
 }
@@ -531,5 +1377,127 @@ fn validate_camera_config(camera_config: &CameraConfig) -> Result<(), Validation
             "Either camera address or uid must be given",
         )),
         _ => Ok(()),
+    }?;
+
+    // A light that is kept at one setting cannot also be switched on a schedule
+    if let (Some(settings), Some(schedule)) = (&camera_config.settings, &camera_config.schedule) {
+        for light in schedule.lights.iter().map(|l| l.light) {
+            let kept = match light {
+                ScheduledLight::Irled => settings.ir.is_some(),
+                ScheduledLight::Led => settings.led.is_some(),
+                ScheduledLight::FloodlightTasks => settings.floodlight_tasks.is_some(),
+                ScheduledLight::Floodlight => false,
+            };
+            if kept {
+                return Err(ValidationError::new("schedule").with_message(
+                    format!("{light:?} is in both the settings and the schedule").into(),
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+fn validate_config(config: &Config) -> Result<(), ValidationError> {
+    validate_rules(config)?;
+    validate_webhooks(config)
+}
+
+fn validate_webhooks(config: &Config) -> Result<(), ValidationError> {
+    let cameras = config
+        .cameras
+        .iter()
+        .map(|camera| camera.name.as_str())
+        .collect::<HashSet<_>>();
+    for webhook in config.webhooks.iter() {
+        if let Some(event) = webhook.events.iter().find(|event| !event.is_webhook()) {
+            return Err(ValidationError::new("webhooks").with_message(
+                format!(
+                    "The webhook to {} cannot send {}, it is only for the event log",
+                    webhook.url,
+                    event.as_str()
+                )
+                .into(),
+            ));
+        }
+        for name in webhook.cameras.iter() {
+            if !cameras.contains(name.as_str()) {
+                return Err(ValidationError::new("webhooks").with_message(
+                    format!(
+                        "The webhook to {} uses the unknown camera {name:?}",
+                        webhook.url
+                    )
+                    .into(),
+                ));
+            }
+        }
     }
+    Ok(())
+}
+
+fn validate_rules(config: &Config) -> Result<(), ValidationError> {
+    let cameras = config
+        .cameras
+        .iter()
+        .map(|camera| camera.name.as_str())
+        .collect::<HashSet<_>>();
+    for rule in config.rules.iter() {
+        let names = rule
+            .when
+            .camera()
+            .into_iter()
+            .chain(rule.then.iter().filter_map(RuleAction::camera));
+        for name in names {
+            if !cameras.contains(name) {
+                return Err(ValidationError::new("rules").with_message(
+                    format!("Rule {:?} uses the unknown camera {name:?}", rule.name).into(),
+                ));
+            }
+        }
+
+        if let RuleTrigger::Schedule { at, camera } = &rule.when {
+            let sun = !matches!(at.kind, ScheduleTimeKind::Clock(_));
+            let located = camera.as_ref().is_some_and(|name| {
+                config
+                    .cameras
+                    .iter()
+                    .any(|camera| &camera.name == name && camera.schedule.is_some())
+            });
+            if sun && !located {
+                return Err(ValidationError::new("rules").with_message(
+                    format!(
+                        "Rule {:?} runs at {at} which needs a camera with a [cameras.schedule] location",
+                        rule.name
+                    )
+                    .into(),
+                ));
+            }
+        }
+
+        if cfg!(not(feature = "gstreamer"))
+            && rule
+                .then
+                .iter()
+                .any(|action| matches!(action, RuleAction::Talk { .. }))
+        {
+            return Err(ValidationError::new("rules").with_message(
+                format!(
+                    "Rule {:?} uses talk which needs neolink to be built with gstreamer",
+                    rule.name
+                )
+                .into(),
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn validate_light_schedule(light: &LightScheduleConfig) -> Result<(), ValidationError> {
+    if light.light != ScheduledLight::Irled
+        && (light.state == IrMode::Auto || light.otherwise == IrMode::Auto)
+    {
+        return Err(ValidationError::new("light_state")
+            .with_message("Only the irled can be set to Auto".into()));
+    }
+    Ok(())
 }
@@ -0,0 +1,417 @@
+//! Writes config changes back to the config file
+//!
+//! Only the values that differ between the config on disk and the new
+//! config are written. Everything else in the file, including comments
+//! and formatting, is kept as it is.
+//!
+//! Fields that are never serialised, such as passwords, are left untouched.
+//! This means that a secret can only be in the file if it was already there.
+use anyhow::{anyhow, Context, Result};
+use serde::Serialize;
+use std::{
+    fs::{self, OpenOptions},
+    io::Write,
+    path::Path,
+};
+use toml_edit::{ArrayOfTables, DocumentMut, Item, TableLike};
+
+use super::{field_aliases, CameraConfig, Config};
+
+/// The fields that identify a camera, these are written first for new cameras
+const CAMERA_REQUIRED: &[&str] = &["name", "username", "address", "uid"];
+
+/// Write the `new_config` into the config file at `path`
+///
+/// The file is replaced atomically so that a crash never leaves
+/// a half written config behind
+pub(crate) fn write_config(path: &Path, new_config: &Config) -> Result<()> {
+    // Write through symlinks rather than replacing them
+    let path = fs::canonicalize(path).with_context(|| format!("Failed to find {:?}", path))?;
+    let contents =
+        fs::read_to_string(&path).with_context(|| format!("Failed to read {:?}", path))?;
+    let mut doc = contents
+        .parse::<DocumentMut>()
+        .with_context(|| format!("Failed to parse the {:?} config file", path))?;
+    let old_config = Config::parse(&contents, &path, false)?;
+
+    let old = toml::Table::try_from(&old_config)?;
+    let new = toml::Table::try_from(new_config)?;
+    merge_table(doc.as_table_mut(), &old, &new, "")?;
+
+    let updated = doc.to_string();
+    if updated == contents {
+        return Ok(());
+    }
+
+    // Check that the file now says what we intended
+    let written = Config::parse(&updated, &path, false)?;
+    if without_secrets(&written) != without_secrets(new_config) {
+        return Err(anyhow!(
+            "Refusing to write {:?}, the updated file would not match the new config",
+            path
+        ));
+    }
+
+    write_atomic(&path, &updated)
+}
+
+fn merge_table(
+    file: &mut dyn TableLike,
+    old: &toml::Table,
+    new: &toml::Table,
+    ctx: &str,
+) -> Result<()> {
+    for (key, new_value) in new.iter() {
+        let old_value = old.get(key);
+        if old_value == Some(new_value) {
+            continue;
+        }
+        let file_key = file_key(file, ctx, key);
+        let child_ctx = if ctx.is_empty() {
+            key.clone()
+        } else {
+            format!("{ctx}.{key}")
+        };
+        match new_value {
+            toml::Value::Table(new_table) => {
+                let empty = toml::Table::new();
+                let old_table = old_value.and_then(toml::Value::as_table).unwrap_or(&empty);
+                if !file.get(&file_key).is_some_and(Item::is_table_like) {
+                    file.insert(&file_key, Item::Table(Default::default()));
+                }
+                let file_table = file
+                    .get_mut(&file_key)
+                    .and_then(Item::as_table_like_mut)
+                    .ok_or_else(|| anyhow!("Could not create the {child_ctx} table"))?;
+                merge_table(file_table, old_table, new_table, &child_ctx)?;
+            }
+            toml::Value::Array(new_array)
+                if file.get(&file_key).is_some_and(Item::is_array_of_tables) =>
+            {
+                let empty = vec![];
+                let old_array = old_value.and_then(toml::Value::as_array).unwrap_or(&empty);
+                let file_array = file
+                    .get_mut(&file_key)
+                    .and_then(Item::as_array_of_tables_mut)
+                    .expect("Checked in match guard");
+                merge_named_tables(file_array, old_array, new_array, &child_ctx)?;
+            }
+            new_value => {
+                let mut value = new_value.serialize(toml_edit::ser::ValueSerializer::new())?;
+                match file.get_mut(&file_key) {
+                    Some(Item::Value(existing)) => {
+                        *value.decor_mut() = existing.decor().clone();
+                        *existing = value;
+                    }
+                    _ => {
+                        file.insert(&file_key, Item::Value(value));
+                    }
+                }
+            }
+        }
+    }
+
+    // Fields that were set but are now unset
+    for key in old.keys() {
+        if !new.contains_key(key) {
+            let file_key = file_key(file, ctx, key);
+            file.remove(&file_key);
+        }
+    }
+    Ok(())
+}
+
+/// Merges `[[tables]]` such as the cameras and users
+///
+/// Entries are matched up by their name, or by their position for tables
+/// such as the webhooks that do not have names
+fn merge_named_tables(
+    file: &mut ArrayOfTables,
+    old: &[toml::Value],
+    new: &[toml::Value],
+    ctx: &str,
+) -> Result<()> {
+    fn name_of(table: &toml::Table) -> Option<&str> {
+        table.get("name").and_then(toml::Value::as_str)
+    }
+    if new.is_empty()
+        || !new
+            .iter()
+            .filter_map(toml::Value::as_table)
+            .all(|table| name_of(table).is_some())
+    {
+        return merge_unnamed_tables(file, old, new, ctx);
+    }
+    let file_name_of = |table: &toml_edit::Table| {
+        table
+            .get(&file_key(table, ctx, "name"))
+            .and_then(Item::as_str)
+            .map(str::to_string)
+    };
+
+    let mut names = vec![];
+    for new_table in new.iter().filter_map(toml::Value::as_table) {
+        let name = name_of(new_table).expect("Checked above");
+        names.push(name.to_string());
+
+        let file_index = file
+            .iter()
+            .position(|table| file_name_of(table).as_deref() == Some(name));
+        if let Some(file_table) = file_index.and_then(|index| file.get_mut(index)) {
+            let empty = toml::Table::new();
+            let old_table = old
+                .iter()
+                .filter_map(toml::Value::as_table)
+                .find(|table| name_of(table) == Some(name))
+                .unwrap_or(&empty);
+            merge_table(file_table, old_table, new_table, ctx)?;
+        } else {
+            // A new entry, only write what is not already a default
+            let defaults = defaults_for(ctx, new_table);
+            let mut file_table = toml_edit::Table::new();
+            for key in CAMERA_REQUIRED.iter().filter(|_| ctx == "cameras") {
+                if let Some(value) = new_table.get(*key) {
+                    let value = value.serialize(toml_edit::ser::ValueSerializer::new())?;
+                    file_table.insert(key, Item::Value(value));
+                }
+            }
+            merge_table(&mut file_table, &defaults, new_table, ctx)?;
+            file.push(file_table);
+            if ctx == "cameras" {
+                log::warn!(
+                    "{name}: Camera was added to the config file without a password, please add it manually"
+                );
+            }
+        }
+    }
+
+    file.retain(|table| {
+        file_name_of(table)
+            .map(|name| names.contains(&name))
+            .unwrap_or(true)
+    });
+    Ok(())
+}
+
+/// Merges `[[tables]]` without names entry by entry
+fn merge_unnamed_tables(
+    file: &mut ArrayOfTables,
+    old: &[toml::Value],
+    new: &[toml::Value],
+    ctx: &str,
+) -> Result<()> {
+    let empty = toml::Table::new();
+    let new = new
+        .iter()
+        .filter_map(toml::Value::as_table)
+        .collect::<Vec<_>>();
+    for (index, new_table) in new.iter().enumerate() {
+        let old_table = old
+            .get(index)
+            .and_then(toml::Value::as_table)
+            .unwrap_or(&empty);
+        if let Some(file_table) = file.get_mut(index) {
+            merge_table(file_table, old_table, new_table, ctx)?;
+        } else {
+            let mut file_table = toml_edit::Table::new();
+            merge_table(&mut file_table, &empty, new_table, ctx)?;
+            file.push(file_table);
+        }
+    }
+    while file.len() > new.len() {
+        file.remove(file.len() - 1);
+    }
+    Ok(())
+}
+
+/// The values that a new entry would get if it was not in the file
+fn defaults_for(ctx: &str, new_table: &toml::Table) -> toml::Table {
+    if ctx != "cameras" {
+        return Default::default();
+    }
+    let required = new_table
+        .iter()
+        .filter(|(key, _)| CAMERA_REQUIRED.contains(&key.as_str()))
+        .map(|(key, value)| (key.clone(), value.clone()))
+        .collect::<toml::Table>();
+    let mut defaults = required
+        .try_into::<CameraConfig>()
+        .ok()
+        .and_then(|camera| toml::Table::try_from(camera).ok())
+        .unwrap_or_default();
+    // The required fields must always be written
+    defaults.retain(|key, _| !CAMERA_REQUIRED.contains(&key));
+    defaults
+}
+
+/// Get the key used for `key` in the file, taking aliases into account
+fn file_key(file: &dyn TableLike, ctx: &str, key: &str) -> String {
+    field_aliases(ctx, key)
+        .find(|alias| !file.contains_key(key) && file.contains_key(alias))
+        .map(|alias| alias.to_string())
+        .unwrap_or_else(|| key.to_string())
+}
+
+fn without_secrets(config: &Config) -> Config {
+    let mut config = config.clone();
+    for camera in config.cameras.iter_mut() {
+        camera.password = None;
+    }
+    for user in config.users.iter_mut() {
+        user.pass = None;
+    }
+    if let Some(mqtt) = config.mqtt.as_mut() {
+        mqtt.credentials = None;
+        mqtt.ca = None;
+        mqtt.client_auth = None;
+    }
+    config
+}
+
+fn write_atomic(path: &Path, contents: &str) -> Result<()> {
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| anyhow!("{:?} is not a file", path))?;
+    let tmp_path = path.with_file_name(format!(".{}.tmp", file_name.to_string_lossy()));
+    let permissions = fs::metadata(path)?.permissions();
+
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        // Only we can read it until the permissions are copied over
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let result = (|| {
+        let mut tmp = options.open(&tmp_path)?;
+        tmp.write_all(contents.as_bytes())?;
+        tmp.sync_all()?;
+        fs::set_permissions(&tmp_path, permissions)?;
+        fs::rename(&tmp_path, path)
+    })();
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp_path);
+    }
+    result.with_context(|| format!("Failed to write {:?}", path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+    use tempfile::{tempdir, TempDir};
+
+    const CONFIG: &str = r#"# Written by hand
+bind = "0.0.0.0"
+
+[mqtt]
+# The local broker
+server = "127.0.0.1" # Uses the alias
+port = 1883
+
+[[cameras]]
+name = "Garage"
+username = "admin"
+password = "secret" # Never written
+address = "192.168.1.10:9000"
+
+[[webhooks]]
+url = "http://127.0.0.1:8080/first"
+
+[[webhooks]]
+# The second hook
+url = "http://127.0.0.1:8080/second" # Changed below
+"#;
+
+    /// Write the file `name` into the `dir`
+    fn write(dir: &TempDir, name: &str, contents: &str) -> PathBuf {
+        let path = dir.path().join(name);
+        fs::write(&path, contents).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_keeps_comments() {
+        let dir = tempdir().unwrap();
+        let path = write(&dir, "neolink.toml", CONFIG);
+        let mut config = Config::load(&path, false).unwrap();
+        config.mqtt.as_mut().unwrap().port = 1884;
+        config.cameras[0].camera_addr = Some("192.168.1.11:9000".to_string());
+
+        write_config(&path, &config).unwrap();
+        let written = fs::read_to_string(&path).unwrap();
+        assert!(written.starts_with("# Written by hand\n"));
+        assert!(written.contains("# The local broker\n"));
+        assert!(written.contains("server = \"127.0.0.1\" # Uses the alias\n"));
+        assert!(written.contains("port = 1884\n"));
+        assert!(written.contains("password = \"secret\" # Never written\n"));
+        assert!(written.contains("address = \"192.168.1.11:9000\"\n"));
+        assert_eq!(Config::load(&path, false).unwrap(), config);
+    }
+
+    #[test]
+    fn test_unnamed_tables() {
+        let dir = tempdir().unwrap();
+        let path = write(&dir, "neolink.toml", CONFIG);
+        let mut config = Config::load(&path, false).unwrap();
+        config.webhooks[1].url = "http://127.0.0.1:8080/third".to_string();
+        config.webhooks[1].resolve_secrets().unwrap();
+
+        write_config(&path, &config).unwrap();
+        let written = fs::read_to_string(&path).unwrap();
+        assert!(written.contains("url = \"http://127.0.0.1:8080/first\"\n"));
+        assert!(written.contains(
+            "# The second hook\nurl = \"http://127.0.0.1:8080/third\" # Changed below\n"
+        ));
+
+        config.webhooks.remove(0);
+        write_config(&path, &config).unwrap();
+        let written = fs::read_to_string(&path).unwrap();
+        assert_eq!(written.matches("[[webhooks]]").count(), 1);
+        assert!(!written.contains("/first"));
+        assert_eq!(Config::load(&path, false).unwrap(), config);
+    }
+
+    #[test]
+    fn test_write_atomic() {
+        let dir = tempdir().unwrap();
+        let path = write(&dir, "neolink.toml", CONFIG);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&path, fs::Permissions::from_mode(0o640)).unwrap();
+        }
+
+        write_atomic(&path, "bind = \"127.0.0.1\"\n").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "bind = \"127.0.0.1\"\n");
+        // Only the config is left, not the temporary file
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o640);
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_write_through_symlink() {
+        let dir = tempdir().unwrap();
+        let target = write(&dir, "target.toml", CONFIG);
+        let link = dir.path().join("neolink.toml");
+        std::os::unix::fs::symlink(&target, &link).unwrap();
+        let mut config = Config::load(&link, false).unwrap();
+        config.mqtt.as_mut().unwrap().port = 1884;
+
+        write_config(&link, &config).unwrap();
+        assert!(fs::symlink_metadata(&link)
+            .unwrap()
+            .file_type()
+            .is_symlink());
+        assert!(fs::read_to_string(&target)
+            .unwrap()
+            .contains("port = 1884\n"));
+    }
+}
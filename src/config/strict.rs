@@ -0,0 +1,471 @@
+//! Finds the keys in the config file that neolink does not use
+//!
+//! Serde silently ignores unknown keys, so a typo such as `idel_disconnect`
+//! would fall back to the default. These keys are reported with their
+//! position in the file and the closest known key
+use std::{fmt, ops::Range};
+use toml_edit::{ImDocument, Item, TableLike};
+
+use super::{Config, ALIASES};
+
+/// How similar a known key must be to be suggested, from 0 to 1
+const SUGGESTION_THRESHOLD: f64 = 0.8;
+
+/// A key in the config file that neolink does not use
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct UnknownKey {
+    /// Where the key is, such as `cameras[0].pause.timout`
+    pub(crate) path: String,
+    /// The line and column of the key, both start at 1
+    pub(crate) position: Option<(usize, usize)>,
+    /// The closest known key
+    pub(crate) suggestion: Option<String>,
+}
+
+impl fmt::Display for UnknownKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "unknown key `{}`", self.path)?;
+        if let Some((line, column)) = self.position {
+            write!(f, " at line {line} column {column}")?;
+        }
+        if let Some(suggestion) = &self.suggestion {
+            write!(f, ", did you mean `{suggestion}`?")?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Segment {
+    Key(String),
+    Index(usize),
+}
+
+impl Config {
+    /// Deserialise the config from toml, also returning any keys that were not used
+    ///
+    /// The config is not validated and its secrets are not resolved
+    pub(crate) fn from_toml(contents: &str) -> Result<(Config, Vec<UnknownKey>), toml::de::Error> {
+        let mut ignored = vec![];
+        let config: Config =
+            serde_ignored::deserialize(toml::Deserializer::new(contents), |path| {
+                ignored.push(segments(&path))
+            })?;
+        if ignored.is_empty() {
+            return Ok((config, vec![]));
+        }
+
+        let doc = ImDocument::parse(contents).ok();
+        let schema = serde_json::to_value(schemars::schema_for!(Config)).unwrap_or_default();
+        let unknown = ignored
+            .iter()
+            .map(|path| UnknownKey {
+                path: display_path(path),
+                position: doc
+                    .as_ref()
+                    .and_then(|doc| key_span(doc.as_table(), path))
+                    .map(|span| line_column(contents, span.start)),
+                suggestion: suggest(&schema, path),
+            })
+            .collect();
+        Ok((config, unknown))
+    }
+}
+
+fn segments(path: &serde_ignored::Path) -> Vec<Segment> {
+    use serde_ignored::Path;
+    let mut segments = match path {
+        Path::Root => return vec![],
+        Path::Seq { parent, .. }
+        | Path::Map { parent, .. }
+        | Path::Some { parent }
+        | Path::NewtypeStruct { parent }
+        | Path::NewtypeVariant { parent } => segments(parent),
+    };
+    match path {
+        Path::Seq { index, .. } => segments.push(Segment::Index(*index)),
+        Path::Map { key, .. } => segments.push(Segment::Key(key.clone())),
+        _ => {}
+    }
+    segments
+}
+
+fn display_path(path: &[Segment]) -> String {
+    let mut result = String::new();
+    for segment in path {
+        match segment {
+            Segment::Key(key) if result.is_empty() => result.push_str(key),
+            Segment::Key(key) => {
+                result.push('.');
+                result.push_str(key);
+            }
+            Segment::Index(index) => result.push_str(&format!("[{index}]")),
+        }
+    }
+    result
+}
+
+/// Find where the key at `path` is in the file
+fn key_span(table: &dyn TableLike, path: &[Segment]) -> Option<Range<usize>> {
+    match path {
+        [Segment::Key(key)] => table.key(key)?.span(),
+        [Segment::Key(key), rest @ ..] => item_key_span(table.get(key)?, rest),
+        _ => None,
+    }
+}
+
+fn item_key_span(item: &Item, path: &[Segment]) -> Option<Range<usize>> {
+    match path.first()? {
+        Segment::Index(index) => {
+            if let Some(tables) = item.as_array_of_tables() {
+                key_span(tables.get(*index)?, &path[1..])
+            } else {
+                let table = item.as_array()?.get(*index)?.as_inline_table()?;
+                key_span(table, &path[1..])
+            }
+        }
+        Segment::Key(_) => key_span(item.as_table_like()?, path),
+    }
+}
+
+fn line_column(contents: &str, offset: usize) -> (usize, usize) {
+    let before = &contents[..offset.min(contents.len())];
+    let line = before.matches('\n').count() + 1;
+    let column = before
+        .rsplit('\n')
+        .next()
+        .map(|line| line.chars().count())
+        .unwrap_or_default()
+        + 1;
+    (line, column)
+}
+
+/// Find the known key that is closest to the unknown one
+///
+/// The known keys are taken from the config's schema and the aliases
+fn suggest(schema: &serde_json::Value, path: &[Segment]) -> Option<String> {
+    let (Segment::Key(unknown), parents) = path.split_last()? else {
+        return None;
+    };
+    let parents = parents
+        .iter()
+        .filter_map(|segment| match segment {
+            Segment::Key(key) => Some(key.as_str()),
+            Segment::Index(_) => None,
+        })
+        .collect::<Vec<_>>();
+    let ctx = parents.join(".");
+
+    let known = known_keys(schema, &parents);
+    let aliases = ALIASES
+        .iter()
+        .filter(|(table, _)| *table == ctx)
+        .flat_map(|(_, aliases)| aliases.iter())
+        .flat_map(|(_, aliases)| aliases.iter().map(|alias| alias.to_string()));
+
+    known
+        .into_iter()
+        .chain(aliases)
+        .map(|known| (strsim::jaro_winkler(unknown, &known), known))
+        .filter(|(score, _)| *score >= SUGGESTION_THRESHOLD)
+        .max_by(|(a, _), (b, _)| a.total_cmp(b))
+        .map(|(_, known)| known)
+}
+
+/// The keys allowed in the table at `parents`
+///
+/// For enums such as the rule triggers these are the keys of every variant
+fn known_keys(schema: &serde_json::Value, parents: &[&str]) -> Vec<String> {
+    // Follow `$ref`s, optional values, enum variants and arrays to the table's schemas
+    fn resolve<'a>(
+        root: &'a serde_json::Value,
+        node: &'a serde_json::Value,
+    ) -> Vec<&'a serde_json::Value> {
+        if let Some(name) = node
+            .get("$ref")
+            .and_then(serde_json::Value::as_str)
+            .and_then(|r| r.strip_prefix("#/$defs/"))
+        {
+            resolve(root, &root["$defs"][name])
+        } else if let Some(options) = node
+            .get("anyOf")
+            .or_else(|| node.get("oneOf"))
+            .and_then(serde_json::Value::as_array)
+        {
+            options
+                .iter()
+                .flat_map(|option| resolve(root, option))
+                .collect()
+        } else if let Some(items) = node.get("items") {
+            resolve(root, items)
+        } else {
+            vec![node]
+        }
+    }
+
+    let mut nodes = resolve(schema, schema);
+    for key in parents {
+        nodes = nodes
+            .into_iter()
+            .flat_map(|node| resolve(schema, &node["properties"][key]))
+            .collect();
+    }
+    let mut keys = nodes
+        .into_iter()
+        .filter_map(|node| {
+            node.get("properties")
+                .and_then(serde_json::Value::as_object)
+        })
+        .flat_map(|properties| properties.keys().cloned())
+        .collect::<Vec<_>>();
+    keys.sort();
+    keys.dedup();
+    keys
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeSet;
+
+    const CAMERA: &str = r#"
+[[cameras]]
+name = "Garage"
+username = "admin"
+address = "192.168.1.10:9000"
+"#;
+
+    fn unknown(extra: &str) -> Vec<UnknownKey> {
+        Config::from_toml(&format!("{CAMERA}{extra}")).unwrap().1
+    }
+
+    #[test]
+    fn test_unknown_keys() {
+        let keys = unknown("idel_disconnect = true\n  bufer = 50\n");
+        assert_eq!(
+            keys,
+            vec![
+                UnknownKey {
+                    path: "cameras[0].idel_disconnect".to_string(),
+                    position: Some((6, 1)),
+                    suggestion: Some("idle_disconnect".to_string()),
+                },
+                UnknownKey {
+                    path: "cameras[0].bufer".to_string(),
+                    position: Some((7, 3)),
+                    // An alias can be the closest key
+                    suggestion: Some("buffer".to_string()),
+                },
+            ]
+        );
+        assert_eq!(
+            keys[0].to_string(),
+            "unknown key `cameras[0].idel_disconnect` at line 6 column 1, did you mean `idle_disconnect`?"
+        );
+    }
+
+    #[test]
+    fn test_nested_unknown_keys() {
+        let keys = unknown("pause = { on_motion = true, timout = 2.0 }\n");
+        assert_eq!(keys.len(), 1);
+        assert_eq!(keys[0].path, "cameras[0].pause.timout");
+        assert_eq!(keys[0].position, Some((6, 29)));
+        assert_eq!(keys[0].suggestion.as_deref(), Some("timeout"));
+
+        let keys = unknown("\n[cameras.time_sync]\nthresold = 5\n");
+        assert_eq!(keys.len(), 1);
+        assert_eq!(keys[0].path, "cameras[0].time_sync.thresold");
+        assert_eq!(keys[0].position, Some((8, 1)));
+        assert_eq!(keys[0].suggestion.as_deref(), Some("threshold"));
+    }
+
+    #[test]
+    fn test_no_suggestion() {
+        let keys = unknown("\n[zzzz]\nvalue = 1\n");
+        assert_eq!(
+            keys,
+            vec![UnknownKey {
+                path: "zzzz".to_string(),
+                position: Some((7, 2)),
+                suggestion: None,
+            }]
+        );
+        assert_eq!(keys[0].to_string(), "unknown key `zzzz` at line 7 column 2");
+    }
+
+    /// The keys that serde accepts for the struct `T`, with its aliases
+    ///
+    /// serde lists each field's name and aliases sorted, one field after the
+    /// other. It hands these to the deserializer, which records them and stops
+    fn serde_fields<T: serde::de::DeserializeOwned>() -> Vec<String> {
+        use serde::de::{value::Error, Error as _, Visitor};
+
+        struct Fields(&'static [&'static str]);
+
+        impl<'de> serde::Deserializer<'de> for &mut Fields {
+            type Error = Error;
+
+            fn deserialize_any<V: Visitor<'de>>(self, _: V) -> Result<V::Value, Error> {
+                Err(Error::custom("not a struct"))
+            }
+
+            fn deserialize_struct<V: Visitor<'de>>(
+                self,
+                _: &'static str,
+                fields: &'static [&'static str],
+                _: V,
+            ) -> Result<V::Value, Error> {
+                self.0 = fields;
+                Err(Error::custom("recorded"))
+            }
+
+            serde::forward_to_deserialize_any! {
+                bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+                bytes byte_buf option unit unit_struct newtype_struct seq tuple
+                tuple_struct map enum identifier ignored_any
+            }
+        }
+
+        let mut fields = Fields(&[]);
+        let _ = T::deserialize(&mut fields);
+        assert!(
+            !fields.0.is_empty(),
+            "{} is not a struct",
+            std::any::type_name::<T>()
+        );
+        fields.0.iter().map(|field| field.to_string()).collect()
+    }
+
+    /// Every table in the config schema, such as `cameras.settings`
+    fn schema_tables(schema: &serde_json::Value) -> Vec<String> {
+        fn walk(schema: &serde_json::Value, parents: &mut Vec<String>, tables: &mut Vec<String>) {
+            let path = parents.iter().map(String::as_str).collect::<Vec<_>>();
+            for key in known_keys(schema, &path) {
+                parents.push(key);
+                let table = parents.join(".");
+                let path = parents.iter().map(String::as_str).collect::<Vec<_>>();
+                if !known_keys(schema, &path).is_empty() && !tables.contains(&table) {
+                    tables.push(table);
+                    walk(schema, parents, tables);
+                }
+                parents.pop();
+            }
+        }
+        let mut tables = vec![];
+        walk(schema, &mut vec![], &mut tables);
+        tables
+    }
+
+    #[test]
+    fn test_aliases_match_serde() {
+        use crate::config::*;
+
+        let schema = serde_json::to_value(schemars::schema_for!(Config)).unwrap();
+        let structs = [
+            ("", serde_fields::<Config>()),
+            ("cameras", serde_fields::<CameraConfig>()),
+            ("cameras.audio_level", serde_fields::<AudioLevelConfig>()),
+            ("cameras.mqtt", serde_fields::<MqttConfig>()),
+            (
+                "cameras.mqtt.discovery",
+                serde_fields::<MqttDiscoveryConfig>(),
+            ),
+            ("cameras.pause", serde_fields::<PauseConfig>()),
+            ("cameras.schedule", serde_fields::<ScheduleConfig>()),
+            (
+                "cameras.schedule.lights",
+                serde_fields::<LightScheduleConfig>(),
+            ),
+            ("cameras.settings", serde_fields::<CameraSettingsConfig>()),
+            ("cameras.time_sync", serde_fields::<TimeSyncConfig>()),
+            ("event_log", serde_fields::<EventLogConfig>()),
+            ("health", serde_fields::<HealthConfig>()),
+            ("mqtt", serde_fields::<MqttServerConfig>()),
+            ("rules", serde_fields::<RuleConfig>()),
+            ("users", serde_fields::<UserConfig>()),
+            ("webhooks", serde_fields::<WebhookConfig>()),
+        ];
+        for table in schema_tables(&schema) {
+            // The ports are all ServicePortConfig, without aliases, and the
+            // rule triggers and actions are enums, see test_enum_aliases
+            let port = table.starts_with("cameras.settings.") && table.ends_with("_port");
+            assert!(
+                port || table.starts_with("rules.")
+                    || structs.iter().any(|(name, _)| *name == table),
+                "The struct of the `{}` table should be in this test",
+                table
+            );
+        }
+        for (table, _) in ALIASES {
+            assert!(
+                *table == "rules.when" || structs.iter().any(|(name, _)| name == table),
+                "`{}` is not a table",
+                table
+            );
+        }
+
+        for (table, fields) in structs.iter() {
+            let parents = match *table {
+                "" => vec![],
+                table => table.split('.').collect::<Vec<_>>(),
+            };
+            let known = known_keys(&schema, &parents);
+            let aliases = ALIASES
+                .iter()
+                .filter(|(name, _)| name == table)
+                .flat_map(|(_, aliases)| aliases.iter())
+                .collect::<Vec<_>>();
+
+            let listed = aliases
+                .iter()
+                .flat_map(|(_, aliases)| aliases.iter().map(|alias| alias.to_string()))
+                .collect::<BTreeSet<_>>();
+            let unlisted = fields
+                .iter()
+                .filter(|field| !known.contains(field))
+                .cloned()
+                .collect::<BTreeSet<_>>();
+            assert_eq!(listed, unlisted, "The aliases of `{}`", table);
+
+            for (field, aliases) in aliases {
+                assert!(
+                    known.iter().any(|key| key == field),
+                    "`{}.{}` is not in the config",
+                    table,
+                    field
+                );
+                let mut names = aliases.to_vec();
+                names.push(field);
+                names.sort();
+                assert!(
+                    fields.windows(names.len()).any(|run| run == names),
+                    "{:?} are not the aliases of `{}.{}`",
+                    aliases,
+                    table,
+                    field
+                );
+            }
+        }
+    }
+
+    /// serde does not list the fields of enum variants, so parse them instead
+    #[test]
+    fn test_enum_aliases() {
+        use crate::config::RuleTrigger;
+
+        for (field, aliases) in RuleTrigger::ALIASES {
+            for alias in aliases.iter() {
+                let trigger = format!("event = \"ai\"\ncamera = \"Garage\"\n{alias} = \"people\"");
+                let trigger: RuleTrigger = toml::from_str(&trigger).unwrap();
+                let table = toml::Table::try_from(&trigger).unwrap();
+                assert_eq!(
+                    table.get(*field).and_then(toml::Value::as_str),
+                    Some("people"),
+                    "`{}` is not an alias of `rules.when.{}`",
+                    alias,
+                    field
+                );
+            }
+        }
+    }
+}
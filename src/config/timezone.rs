@@ -0,0 +1,366 @@
+//! Timezones given as POSIX TZ rules
+//!
+//! Such as `CET-1CEST,M3.5.0,M10.5.0/3` for central Europe or
+//! `EST5EDT,M3.2.0,M11.1.0` for the US east coast. As in POSIX the offsets
+//! are hours *west* of UTC, so `EST5` is UTC-5.
+//!
+//! The cameras only understand a fixed offset, so neolink works out the
+//! offset that applies at the time and sets the camera to it
+use anyhow::{anyhow, Context, Error, Result};
+use serde::{Deserialize, Serialize};
+use std::{convert::TryFrom, fmt};
+use time::{util, Date, Month, OffsetDateTime, UtcOffset};
+
+/// The rules of a timezone
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub(crate) struct TimeZone {
+    rule: String,
+    /// Seconds east of UTC outside of DST
+    std_offset: i32,
+    dst: Option<Dst>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Dst {
+    /// Seconds east of UTC during DST
+    offset: i32,
+    start: Transition,
+    end: Transition,
+}
+
+/// When DST starts or ends in a year
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Transition {
+    date: TransitionDate,
+    /// Seconds after local midnight, this can be negative or past a day
+    time: i32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TransitionDate {
+    /// `Jn`: day 1 to 365, the 29th of February is never counted
+    Julian(u16),
+    /// `n`: day 0 to 365, the 29th of February is counted
+    Ordinal(u16),
+    /// `Mm.w.d`: day `d` (0 is Sunday) of week `w` (5 is the last) of month `m`
+    Weekday { month: u8, week: u8, weekday: u8 },
+}
+
+impl TimeZone {
+    /// The offset from UTC at the instant `at`
+    pub(crate) fn offset_at(&self, at: OffsetDateTime) -> UtcOffset {
+        let offset = match &self.dst {
+            Some(dst) if dst.is_active(at.unix_timestamp(), self.std_offset) => dst.offset,
+            _ => self.std_offset,
+        };
+        UtcOffset::from_whole_seconds(offset).unwrap_or(UtcOffset::UTC)
+    }
+}
+
+impl Dst {
+    fn is_active(&self, unix: i64, std_offset: i32) -> bool {
+        let year = OffsetDateTime::from_unix_timestamp(unix + std_offset as i64)
+            .map(|local| local.year())
+            .unwrap_or(1970);
+        // The start is in standard time and the end is in DST
+        let start = self.start.unix(year, std_offset);
+        let end = self.end.unix(year, self.offset);
+        if start < end {
+            start <= unix && unix < end
+        } else {
+            // Southern hemisphere, DST spans the new year
+            !(end <= unix && unix < start)
+        }
+    }
+}
+
+impl Transition {
+    /// The instant of the transition in `year` when the local time is at `offset`
+    fn unix(&self, year: i32, offset: i32) -> i64 {
+        let date = self.date.date(year);
+        date.midnight().assume_utc().unix_timestamp() + self.time as i64 - offset as i64
+    }
+}
+
+impl TransitionDate {
+    fn date(&self, year: i32) -> Date {
+        let last = util::days_in_year(year);
+        let ordinal = match *self {
+            TransitionDate::Julian(day) if util::is_leap_year(year) && day >= 60 => day + 1,
+            TransitionDate::Julian(day) => day,
+            TransitionDate::Ordinal(day) => (day + 1).min(last),
+            TransitionDate::Weekday {
+                month,
+                week,
+                weekday,
+            } => {
+                let month = Month::try_from(month).expect("Month is checked when parsed");
+                let first = Date::from_calendar_date(year, month, 1).expect("Always a valid date");
+                let first_weekday = first.weekday().number_days_from_sunday();
+                let mut day = 1 + (weekday + 7 - first_weekday) % 7 + (week - 1) * 7;
+                while day > month.length(year) {
+                    day -= 7;
+                }
+                return first.replace_day(day).expect("Day is within the month");
+            }
+        };
+        Date::from_ordinal_date(year, ordinal).expect("Day is within the year")
+    }
+}
+
+impl std::str::FromStr for TimeZone {
+    type Err = Error;
+
+    fn from_str(rule: &str) -> Result<Self> {
+        parse(rule).with_context(|| format!("Invalid timezone rule {rule:?}"))
+    }
+}
+
+impl TryFrom<String> for TimeZone {
+    type Error = String;
+
+    /// The error includes its cause so that serde shows why the rule is invalid
+    fn try_from(rule: String) -> std::result::Result<Self, String> {
+        rule.parse().map_err(|e: Error| format!("{e:#}"))
+    }
+}
+
+impl From<TimeZone> for String {
+    fn from(tz: TimeZone) -> String {
+        tz.rule
+    }
+}
+
+impl fmt::Display for TimeZone {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.rule)
+    }
+}
+
+/// The US rules, which POSIX uses when a DST name is given without any rules
+const DEFAULT_RULES: &str = "M3.2.0,M11.1.0";
+
+fn parse(rule: &str) -> Result<TimeZone> {
+    let mut parser = Parser { rest: rule };
+    parser.name()?;
+    let std_offset = -parser.offset(24)?;
+    if parser.rest.is_empty() {
+        return Ok(TimeZone {
+            rule: rule.to_string(),
+            std_offset,
+            dst: None,
+        });
+    }
+
+    parser.name()?;
+    let offset = if parser.rest.is_empty() || parser.rest.starts_with(',') {
+        std_offset + 3600
+    } else {
+        -parser.offset(24)?
+    };
+    if parser.rest.is_empty() {
+        parser.rest = DEFAULT_RULES;
+    } else {
+        parser.expect(',')?;
+    }
+    let start = parser.transition()?;
+    parser.expect(',')?;
+    let end = parser.transition()?;
+    if !parser.rest.is_empty() {
+        return Err(anyhow!("Unexpected {:?} at the end", parser.rest));
+    }
+
+    Ok(TimeZone {
+        rule: rule.to_string(),
+        std_offset,
+        dst: Some(Dst { offset, start, end }),
+    })
+}
+
+struct Parser<'a> {
+    rest: &'a str,
+}
+
+impl Parser<'_> {
+    fn expect(&mut self, c: char) -> Result<()> {
+        self.rest = self
+            .rest
+            .strip_prefix(c)
+            .ok_or_else(|| anyhow!("Expected {c:?} at {:?}", self.rest))?;
+        Ok(())
+    }
+
+    /// A name such as `CET` or `<+03>`
+    fn name(&mut self) -> Result<()> {
+        let (name, rest) = if let Some(quoted) = self.rest.strip_prefix('<') {
+            let end = quoted
+                .find('>')
+                .ok_or_else(|| anyhow!("Missing '>' in {:?}", self.rest))?;
+            (&quoted[..end], &quoted[end + 1..])
+        } else {
+            let end = self
+                .rest
+                .find(|c: char| !c.is_ascii_alphabetic())
+                .unwrap_or(self.rest.len());
+            self.rest.split_at(end)
+        };
+        if name.len() < 3 {
+            return Err(anyhow!(
+                "Expected a name of at least 3 letters at {:?}",
+                self.rest
+            ));
+        }
+        self.rest = rest;
+        Ok(())
+    }
+
+    fn number(&mut self, max: u32) -> Result<u32> {
+        let end = self
+            .rest
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(self.rest.len());
+        let (digits, rest) = self.rest.split_at(end);
+        let value = digits
+            .parse::<u32>()
+            .ok()
+            .filter(|value| *value <= max)
+            .ok_or_else(|| anyhow!("Expected a number up to {max} at {:?}", self.rest))?;
+        self.rest = rest;
+        Ok(value)
+    }
+
+    /// A signed `hh[:mm[:ss]]` in seconds
+    fn offset(&mut self, max_hours: u32) -> Result<i32> {
+        let sign = if let Some(rest) = self.rest.strip_prefix('-') {
+            self.rest = rest;
+            -1
+        } else {
+            if let Some(rest) = self.rest.strip_prefix('+') {
+                self.rest = rest;
+            }
+            1
+        };
+        let mut seconds = self.number(max_hours)? * 3600;
+        if self.rest.starts_with(':') {
+            self.expect(':')?;
+            seconds += self.number(59)? * 60;
+            if self.rest.starts_with(':') {
+                self.expect(':')?;
+                seconds += self.number(59)?;
+            }
+        }
+        Ok(sign * seconds as i32)
+    }
+
+    /// A date with an optional `/time`, which is 02:00 if unset
+    fn transition(&mut self) -> Result<Transition> {
+        let date = if let Some(rest) = self.rest.strip_prefix('J') {
+            self.rest = rest;
+            let day = self.number(365)?;
+            if day == 0 {
+                return Err(anyhow!("Julian days start at 1"));
+            }
+            TransitionDate::Julian(day as u16)
+        } else if let Some(rest) = self.rest.strip_prefix('M') {
+            self.rest = rest;
+            let month = self.number(12)?;
+            if month == 0 {
+                return Err(anyhow!("Months start at 1"));
+            }
+            self.expect('.')?;
+            let week = self.number(5)?;
+            if week == 0 {
+                return Err(anyhow!("Weeks start at 1"));
+            }
+            self.expect('.')?;
+            let weekday = self.number(6)?;
+            TransitionDate::Weekday {
+                month: month as u8,
+                week: week as u8,
+                weekday: weekday as u8,
+            }
+        } else {
+            TransitionDate::Ordinal(self.number(365)? as u16)
+        };
+        let time = if self.rest.starts_with('/') {
+            self.expect('/')?;
+            self.offset(167)?
+        } else {
+            2 * 3600
+        };
+        Ok(Transition { date, time })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use time::{Date, Month, Time};
+
+    fn utc(year: i32, month: Month, day: u8, hour: u8, minute: u8) -> OffsetDateTime {
+        Date::from_calendar_date(year, month, day)
+            .unwrap()
+            .with_time(Time::from_hms(hour, minute, 0).unwrap())
+            .assume_utc()
+    }
+
+    fn hours(tz: &str, at: OffsetDateTime) -> f32 {
+        let tz: TimeZone = tz.parse().unwrap();
+        tz.offset_at(at).whole_seconds() as f32 / 3600.0
+    }
+
+    #[test]
+    fn test_offset_sign() {
+        let winter = utc(2024, Month::January, 15, 12, 0);
+        // POSIX offsets are west of UTC so `EST5` is behind and `CET-1` is ahead
+        assert_eq!(hours("EST5", winter), -5.0);
+        assert_eq!(hours("CET-1", winter), 1.0);
+        assert_eq!(hours("<+0545>-5:45", winter), 5.75);
+        assert_eq!(hours("<-03>3", winter), -3.0);
+        assert_eq!(hours("UTC0", winter), 0.0);
+    }
+
+    #[test]
+    fn test_dst() {
+        let winter = utc(2024, Month::January, 15, 12, 0);
+        let summer = utc(2024, Month::July, 1, 12, 0);
+        assert_eq!(hours("EST5EDT,M3.2.0,M11.1.0", winter), -5.0);
+        assert_eq!(hours("EST5EDT,M3.2.0,M11.1.0", summer), -4.0);
+        // Without rules the DST is an hour ahead with the US dates
+        assert_eq!(hours("EST5EDT", summer), -4.0);
+        assert_eq!(hours("CET-1CEST,M3.5.0,M10.5.0/3", winter), 1.0);
+        assert_eq!(hours("CET-1CEST,M3.5.0,M10.5.0/3", summer), 2.0);
+        // DST spans the new year in the south
+        assert_eq!(hours("AEST-10AEDT,M10.1.0,M4.1.0/3", winter), 11.0);
+        assert_eq!(hours("AEST-10AEDT,M10.1.0,M4.1.0/3", summer), 10.0);
+    }
+
+    #[test]
+    fn test_transitions() {
+        // Central Europe changes at 01:00 UTC on the last Sunday of March and October
+        let tz = "CET-1CEST,M3.5.0,M10.5.0/3";
+        assert_eq!(hours(tz, utc(2024, Month::March, 31, 0, 59)), 1.0);
+        assert_eq!(hours(tz, utc(2024, Month::March, 31, 1, 0)), 2.0);
+        assert_eq!(hours(tz, utc(2024, Month::October, 27, 0, 59)), 2.0);
+        assert_eq!(hours(tz, utc(2024, Month::October, 27, 1, 0)), 1.0);
+        // New York changes at 07:00 UTC on the second Sunday of March
+        let tz = "EST5EDT,M3.2.0,M11.1.0";
+        assert_eq!(hours(tz, utc(2024, Month::March, 10, 6, 59)), -5.0);
+        assert_eq!(hours(tz, utc(2024, Month::March, 10, 7, 0)), -4.0);
+    }
+
+    #[test]
+    fn test_invalid() {
+        for rule in [
+            "",
+            "X5",
+            "EST",
+            "EST5EDT,M13.1.0,M11.1.0",
+            "EST5EDT,M3.2.0",
+            "<EST5",
+        ] {
+            assert!(rule.parse::<TimeZone>().is_err(), "{:?} was accepted", rule);
+        }
+    }
+}
@@ -0,0 +1,19 @@
+use clap::Parser;
+
+/// The config command checks and prints the config file
+#[derive(Parser, Debug)]
+pub struct Opt {
+    /// The action to perform
+    #[command(subcommand)]
+    pub cmd: ConfigAction,
+}
+
+#[derive(Parser, Debug)]
+pub enum ConfigAction {
+    /// Parse and validate the config file, listing every problem found
+    Check,
+    /// Print the effective config with all defaults filled in and secrets redacted
+    Show,
+    /// Print the JSON Schema of the config file for use in editors
+    Schema,
+}
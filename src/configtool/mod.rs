@@ -0,0 +1,209 @@
+///
+/// # Neolink Config
+///
+/// This module checks and prints the config file without
+/// connecting to any cameras
+///
+/// # Usage
+///
+/// ```bash
+/// # Check the config file for mistakes
+/// neolink config --config=config.toml check
+/// # Print the config as neolink sees it, with all the defaults
+/// neolink config --config=config.toml show
+/// # Print the JSON Schema of the config file
+/// neolink config schema > neolink.schema.json
+/// ```
+///
+use anyhow::{anyhow, Context, Result};
+use std::{fs, path::Path};
+use validator::{Validate, ValidationErrors, ValidationErrorsKind};
+
+mod cmdline;
+
+use crate::config::Config;
+pub(crate) use cmdline::*;
+
+/// Shown in place of any secret
+const REDACTED: &str = "<redacted>";
+
+/// Entry point for the config subcommand
+///
+/// Opt is the command line options. Unlike the other subcommands
+/// this runs before the config is loaded, so that a broken config
+/// can be checked and the schema can be printed without one
+///
+/// If `strict` then unknown keys are reported as errors
+pub(crate) fn main(opt: Opt, config_path: Option<&Path>, strict: bool) -> Result<()> {
+    match opt.cmd {
+        ConfigAction::Check => {
+            let path = config_path.context("Must supply --config file")?;
+            check(path, strict)
+        }
+        ConfigAction::Show => {
+            let path = config_path.context("Must supply --config file")?;
+            let config = Config::load(path, strict)?;
+            print!("{}", toml::to_string_pretty(&redacted(&config)?)?);
+            Ok(())
+        }
+        ConfigAction::Schema => {
+            let schema = schemars::schema_for!(Config);
+            println!("{}", serde_json::to_string_pretty(&schema)?);
+            Ok(())
+        }
+    }
+}
+
+fn check(path: &Path, strict: bool) -> Result<()> {
+    let contents =
+        fs::read_to_string(path).with_context(|| format!("Failed to read {:?}", path))?;
+
+    let (mut config, unknown) = match Config::from_toml(&contents) {
+        Ok(parsed) => parsed,
+        Err(e) => {
+            eprintln!("{}: {e}", path.display());
+            return Err(anyhow!("The config file could not be parsed"));
+        }
+    };
+    let strict = strict || config.strict_config;
+    for key in unknown.iter() {
+        let level = if strict { "error" } else { "warning" };
+        eprintln!("{}: {level}: {key}", path.display());
+    }
+    if strict && !unknown.is_empty() {
+        return Err(anyhow!(
+            "Found {} unknown key(s) in the config file",
+            unknown.len()
+        ));
+    }
+    if let Err(e) = config.resolve_secrets() {
+        eprintln!("{}: {e:#}", path.display());
+        return Err(anyhow!(
+            "The secrets in the config file could not be resolved"
+        ));
+    }
+    if let Err(errors) = config.validate() {
+        let mut problems = vec![];
+        field_errors(&errors, &config, "", &mut problems);
+        problems.sort();
+        for (field, message) in problems.iter() {
+            eprintln!("{}: {field}: {message}", path.display());
+        }
+        return Err(anyhow!(
+            "Found {} problem(s) in the config file",
+            problems.len()
+        ));
+    }
+
+    println!(
+        "{}: OK ({} camera(s), {} user(s))",
+        path.display(),
+        config.cameras.len(),
+        config.users.len()
+    );
+    Ok(())
+}
+
+/// Flatten the nested validation errors into `(field, message)` pairs
+fn field_errors(
+    errors: &ValidationErrors,
+    config: &Config,
+    prefix: &str,
+    out: &mut Vec<(String, String)>,
+) {
+    for (field, kind) in errors.errors() {
+        // Errors of the whole struct are stored under `__all__`
+        let path = match (prefix, *field) {
+            ("", "__all__") => "(config)".to_string(),
+            (prefix, "__all__") => prefix.to_string(),
+            ("", field) => field.to_string(),
+            (prefix, field) => format!("{prefix}.{field}"),
+        };
+        match kind {
+            ValidationErrorsKind::Field(field_errors) => {
+                for error in field_errors {
+                    let mut message = error.message.as_deref().unwrap_or(&error.code).to_string();
+                    if let Some(value) = error.params.get("value") {
+                        message = format!("{message} (found {value})");
+                    }
+                    out.push((path.clone(), message));
+                }
+            }
+            ValidationErrorsKind::Struct(errors) => {
+                field_errors(errors, config, &path, out);
+            }
+            ValidationErrorsKind::List(list) => {
+                for (index, errors) in list {
+                    // Name the camera or user so that it is easier to find
+                    let name = match (prefix, *field) {
+                        ("", "cameras") => config.cameras.get(*index).map(|c| c.name.as_str()),
+                        ("", "users") => config.users.get(*index).map(|u| u.name.as_str()),
+                        _ => None,
+                    };
+                    let path = match name {
+                        Some(name) => format!("{path}[{index}] ({name:?})"),
+                        None => format!("{path}[{index}]"),
+                    };
+                    field_errors(errors, config, &path, out);
+                }
+            }
+        }
+    }
+}
+
+/// The config as a toml table with every secret replaced by [`REDACTED`]
+///
+/// Fields that are skipped when serialising are added back so that
+/// the output shows everything that was set
+fn redacted(config: &Config) -> Result<toml::Table> {
+    let mut table = toml::Table::try_from(config)?;
+
+    if let Some(cameras) = table.get_mut("cameras").and_then(toml::Value::as_array_mut) {
+        for (camera, entry) in config.cameras.iter().zip(cameras.iter_mut()) {
+            if let (Some(_), Some(entry)) = (&camera.password, entry.as_table_mut()) {
+                entry.insert("password".to_string(), REDACTED.into());
+            }
+        }
+    }
+    if let Some(users) = table.get_mut("users").and_then(toml::Value::as_array_mut) {
+        for (user, entry) in config.users.iter().zip(users.iter_mut()) {
+            if let (Some(_), Some(entry)) = (&user.pass, entry.as_table_mut()) {
+                entry.insert("pass".to_string(), REDACTED.into());
+            }
+        }
+    }
+    if let Some(webhooks) = table
+        .get_mut("webhooks")
+        .and_then(toml::Value::as_array_mut)
+    {
+        // The headers usually hold an access token
+        for entry in webhooks.iter_mut() {
+            if let Some(headers) = entry.get_mut("headers").and_then(toml::Value::as_table_mut) {
+                for (_, value) in headers.iter_mut() {
+                    *value = REDACTED.into();
+                }
+            }
+        }
+    }
+    if let (Some(mqtt), Some(entry)) = (
+        &config.mqtt,
+        table.get_mut("mqtt").and_then(toml::Value::as_table_mut),
+    ) {
+        if let Some((username, _)) = &mqtt.credentials {
+            entry.insert(
+                "credentials".to_string(),
+                toml::Value::try_from([username.as_str(), REDACTED])?,
+            );
+        }
+        if let Some(ca) = &mqtt.ca {
+            entry.insert("ca".to_string(), toml::Value::try_from(ca)?);
+        }
+        if let Some(client_auth) = &mqtt.client_auth {
+            entry.insert(
+                "client_auth".to_string(),
+                toml::Value::try_from(client_auth)?,
+            );
+        }
+    }
+    Ok(table)
+}
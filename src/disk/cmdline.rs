@@ -0,0 +1,25 @@
+use clap::Parser;
+
+/// The disk command reports the SD cards of the camera and can format them
+#[derive(Parser, Debug)]
+pub struct Opt {
+    /// The name of the camera. Must be a name in the config
+    pub camera: String,
+    /// The action to perform
+    #[command(subcommand)]
+    pub cmd: DiskAction,
+}
+
+#[derive(Parser, Debug)]
+pub enum DiskAction {
+    /// Print the size, free space and state of each disk
+    Get,
+    /// Format a disk, erasing all of its recordings
+    Format {
+        /// The number of the disk as shown by `get`
+        number: u8,
+        /// Confirm that the recordings on the disk should be erased
+        #[arg(long)]
+        yes: bool,
+    },
+}
@@ -0,0 +1,78 @@
+///
+/// # Neolink Disk
+///
+/// This module reports the disks of the camera, such as the SD card, and
+/// formats them
+///
+///
+/// # Usage
+///
+/// ```bash
+/// # To print the size, free space and state of the disks
+/// neolink disk --config=config.toml CameraName get
+/// # To format disk 0, erasing all of its recordings
+/// neolink disk --config=config.toml CameraName format 0 --yes
+/// ```
+///
+use anyhow::{anyhow, Context, Result};
+
+mod cmdline;
+
+use crate::common::NeoReactor;
+pub(crate) use cmdline::*;
+
+/// Entry point for the disk subcommand
+///
+/// Opt is the command line options
+pub(crate) async fn main(opt: Opt, reactor: NeoReactor) -> Result<()> {
+    let camera = reactor.get(&opt.camera).await?;
+
+    match opt.cmd {
+        DiskAction::Get => {
+            let hdd_info_list = camera
+                .run_task(|cam| {
+                    Box::pin(async move {
+                        cam.get_hdd_info_list()
+                            .await
+                            .context("Unable to get camera disks")
+                    })
+                })
+                .await?;
+            if hdd_info_list.hdd_info.is_empty() {
+                println!("No disks");
+            }
+            for hdd_info in hdd_info_list.hdd_info.iter() {
+                println!(
+                    "==Disk {}==\n\
+                    Capacity: {}MB,\n\
+                    Free: {}MB ({:.1}%),\n\
+                    State: {},\n\
+                    ",
+                    hdd_info.number,
+                    hdd_info.capacity,
+                    hdd_info.remain_size,
+                    hdd_info.free_percent(),
+                    hdd_info.state().as_str(),
+                );
+            }
+        }
+        DiskAction::Format { number, yes } => {
+            if !yes {
+                return Err(anyhow!(
+                    "Formatting erases all of the recordings on disk {number}, add --yes to format it"
+                ));
+            }
+            camera
+                .run_task(|cam| {
+                    Box::pin(async move {
+                        cam.format_disk(number)
+                            .await
+                            .context("Unable to format the camera disk")
+                    })
+                })
+                .await?;
+        }
+    }
+
+    Ok(())
+}
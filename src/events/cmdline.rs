@@ -0,0 +1,42 @@
+use clap::Parser;
+
+/// The events command prints the camera events as JSON lines until killed,
+/// or searches the event log
+#[derive(Parser, Debug)]
+pub struct Opt {
+    /// Only the events of this camera. Can be given more than once
+    #[arg(long)]
+    pub camera: Vec<String>,
+    /// The action to perform, the default is to print the live events
+    #[command(subcommand)]
+    pub cmd: Option<EventsAction>,
+}
+
+#[derive(Parser, Debug)]
+pub enum EventsAction {
+    /// Print the logged events that match, from the oldest to the newest
+    Query(QueryOpt),
+}
+
+#[derive(Parser, Debug)]
+pub struct QueryOpt {
+    /// Only the events of this camera. Can be given more than once
+    #[arg(long)]
+    pub camera: Vec<String>,
+    /// Only the events from this time, such as `2024-05-01`, `2024-05-01T20:00:00Z` or `2h` ago
+    #[arg(long)]
+    pub since: Option<String>,
+    /// Only the events before this time, in the same forms as `--since`
+    #[arg(long)]
+    pub until: Option<String>,
+    /// Only the events of this type such as `motion_start`, or with an AI class such as `person`.
+    /// Can be given more than once
+    #[arg(long = "type")]
+    pub types: Vec<String>,
+    /// Only the most recent of the events
+    #[arg(long)]
+    pub limit: Option<usize>,
+    /// Print the events as JSON lines
+    #[arg(long)]
+    pub json: bool,
+}
@@ -0,0 +1,271 @@
+///
+/// # Neolink Events
+///
+/// This module prints the live camera events and searches the event log
+/// that is kept with `[event_log]`
+///
+/// # Usage
+///
+/// ```bash
+/// # Print each event of the Garage camera as a line of JSON
+/// neolink --config=config.toml events --camera Garage
+/// # When did the garage camera last see someone
+/// neolink --config=config.toml events query --camera Garage --type person --limit 1
+/// # Everything from the last two hours as JSON lines
+/// neolink --config=config.toml events query --since 2h --json
+/// ```
+///
+use anyhow::{anyhow, Context, Result};
+use std::{
+    fs::File,
+    io::{BufRead, BufReader, ErrorKind},
+};
+use time::{
+    format_description::{self, well_known::Rfc3339},
+    Date, Duration, OffsetDateTime, Time,
+};
+use tokio::sync::broadcast::{channel as broadcast, error::RecvError};
+
+mod cmdline;
+
+use crate::common::{watch_events, CameraEvent, EventSources, NeoReactor};
+pub(crate) use cmdline::*;
+
+/// Entry point for the events subcommand
+///
+/// Opt is the command line options
+pub(crate) async fn main(opt: Opt, reactor: NeoReactor) -> Result<()> {
+    let config = reactor.config().await?.borrow().clone();
+
+    match opt.cmd {
+        None => {
+            for name in opt.camera.iter() {
+                if !config.cameras.iter().any(|camera| &camera.name == name) {
+                    return Err(anyhow!("There is no camera {name:?} in the config"));
+                }
+            }
+            let (events, mut events_rx) = broadcast(100);
+            let sources = EventSources {
+                active: |_| true,
+                cameras: opt.camera,
+                floodlight: true,
+            };
+            tokio::select! {
+                v = watch_events(reactor, events, sources) => v,
+                v = async {
+                    loop {
+                        match events_rx.recv().await {
+                            Ok(event) => println!("{}", serde_json::to_string(&event)?),
+                            Err(RecvError::Lagged(missed)) => log::warn!("Missed {missed} events"),
+                            Err(RecvError::Closed) => return Err(anyhow!("The events have stopped")),
+                        }
+                    }
+                } => v,
+            }?;
+        }
+        Some(EventsAction::Query(query)) => {
+            let log_config = config
+                .event_log
+                .context("There is no [event_log] in the config")?;
+            let cameras = query
+                .camera
+                .iter()
+                .chain(opt.camera.iter())
+                .collect::<Vec<_>>();
+            let now = OffsetDateTime::now_utc();
+            let since = query
+                .since
+                .as_deref()
+                .map(|text| parse_when(text, now))
+                .transpose()
+                .context("Invalid --since")?;
+            let until = query
+                .until
+                .as_deref()
+                .map(|text| parse_when(text, now))
+                .transpose()
+                .context("Invalid --until")?;
+
+            let mut found = vec![];
+            for path in log_config.files() {
+                let file = match File::open(&path) {
+                    Ok(file) => file,
+                    Err(e) if e.kind() == ErrorKind::NotFound => continue,
+                    Err(e) => return Err(e).with_context(|| format!("Could not read {:?}", path)),
+                };
+                for (number, line) in BufReader::new(file).lines().enumerate() {
+                    let line = line.with_context(|| format!("Could not read {:?}", path))?;
+                    let event: CameraEvent = match serde_json::from_str(&line) {
+                        Ok(event) => event,
+                        Err(e) => {
+                            log::debug!("{:?}:{}: Skipping the line: {e}", path, number + 1);
+                            continue;
+                        }
+                    };
+                    let wanted = (cameras.is_empty() || cameras.contains(&&event.camera))
+                        && since.iter().all(|since| event.timestamp >= *since)
+                        && until.iter().all(|until| event.timestamp < *until)
+                        && (query.types.is_empty()
+                            || query.types.iter().any(|kind| is_type(&event, kind)));
+                    if wanted {
+                        found.push(event);
+                    }
+                }
+            }
+            found.sort_by_key(|event| event.timestamp);
+            if let Some(limit) = query.limit {
+                found.drain(..found.len().saturating_sub(limit));
+            }
+
+            let width = found
+                .iter()
+                .map(|event| event.camera.len())
+                .max()
+                .unwrap_or(0);
+            let time_format = format_description::parse_borrowed::<2>(
+                "[year]-[month]-[day] [hour]:[minute]:[second] UTC",
+            )?;
+            for event in found.iter() {
+                if query.json {
+                    println!("{}", serde_json::to_string(event)?);
+                } else {
+                    let line = format!(
+                        "{}  {:width$}  {:17}  {}",
+                        event.timestamp.format(&time_format)?,
+                        event.camera,
+                        event.event.as_str(),
+                        details(event),
+                    );
+                    println!("{}", line.trim_end());
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// If the `event` is of the `kind` such as `motion_start` or has the AI class `kind`
+fn is_type(event: &CameraEvent, kind: &str) -> bool {
+    let kind = kind.to_lowercase();
+    if event.event.as_str() == kind {
+        return true;
+    }
+    // The names that people use for the camera's AI classes
+    let class = match kind.as_str() {
+        "person" | "people" | "human" => "people",
+        "car" | "vehicle" => "vehicle",
+        "animal" | "pet" | "dog" | "cat" | "dog_cat" => "dog_cat",
+        other => other,
+    };
+    event.ai_types.iter().any(|ai_type| ai_type == class)
+}
+
+fn details(event: &CameraEvent) -> String {
+    let mut details = vec![];
+    if !event.ai_types.is_empty() {
+        details.push(event.ai_types.join(","));
+    }
+    if let Some(percent) = event.battery_percent {
+        details.push(format!("{percent}%"));
+    }
+    if let Some(rms) = event.rms_db {
+        details.push(format!("{rms:.1}dB"));
+    }
+    if let Some(message) = event.message.as_ref() {
+        details.push(message.clone());
+    }
+    details.join(" ")
+}
+
+/// A time such as `2024-05-01`, `2024-05-01T20:00:00Z` or `2h` before `now`
+///
+/// Dates are the start of the day in UTC
+fn parse_when(text: &str, now: OffsetDateTime) -> Result<OffsetDateTime> {
+    let text = text.trim();
+    if let Ok(time) = OffsetDateTime::parse(text, &Rfc3339) {
+        return Ok(time);
+    }
+    if let Ok(date) = Date::parse(
+        text,
+        &format_description::parse_borrowed::<2>("[year]-[month]-[day]")?,
+    ) {
+        return Ok(date.with_time(Time::MIDNIGHT).assume_utc());
+    }
+
+    let split = text
+        .find(|c: char| !c.is_ascii_digit())
+        .ok_or_else(|| anyhow!("Expected a unit of s, m, h, d or w after {text:?}"))?;
+    let value = text[..split].parse::<i64>().map_err(|_| {
+        anyhow!("Expected a time such as 2024-05-01, 2024-05-01T20:00:00Z or 2h, not {text:?}")
+    })?;
+    let unit = match &text[split..] {
+        "s" => 1,
+        "m" => 60,
+        "h" => 3600,
+        "d" => 86400,
+        "w" => 7 * 86400,
+        unit => return Err(anyhow!("Expected a unit of s, m, h, d or w, not {unit:?}")),
+    };
+    value
+        .checked_mul(unit)
+        .and_then(|ago| now.checked_sub(Duration::seconds(ago)))
+        .ok_or_else(|| anyhow!("{text:?} is too long ago"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn now() -> OffsetDateTime {
+        OffsetDateTime::parse("2024-05-01T20:00:00Z", &Rfc3339).unwrap()
+    }
+
+    fn when(text: &str) -> String {
+        parse_when(text, now()).unwrap().format(&Rfc3339).unwrap()
+    }
+
+    #[test]
+    fn test_parse_when() {
+        assert_eq!(
+            when("2024-04-30T08:15:00+02:00"),
+            "2024-04-30T08:15:00+02:00"
+        );
+        assert_eq!(when("2024-04-30"), "2024-04-30T00:00:00Z");
+        assert_eq!(when("90s"), "2024-05-01T19:58:30Z");
+        assert_eq!(when("30m"), "2024-05-01T19:30:00Z");
+        assert_eq!(when(" 2h "), "2024-05-01T18:00:00Z");
+        assert_eq!(when("7d"), "2024-04-24T20:00:00Z");
+        assert_eq!(when("1w"), "2024-04-24T20:00:00Z");
+        assert_eq!(when("0s"), "2024-05-01T20:00:00Z");
+    }
+
+    #[test]
+    fn test_parse_when_invalid() {
+        for text in [
+            "",
+            "2h30m",
+            "2",
+            "h",
+            "2y",
+            "-2h",
+            "2024-13-01",
+            "yesterday",
+        ] {
+            assert!(parse_when(text, now()).is_err(), "{:?} was accepted", text);
+        }
+    }
+
+    #[test]
+    fn test_parse_when_too_long_ago() {
+        // These would overflow the duration or the date
+        for text in [
+            "9223372036854775807w",
+            "9223372036854775807s",
+            "15250284452471d",
+            "100000000w",
+            "99999999999999999999h",
+        ] {
+            assert!(parse_when(text, now()).is_err(), "{:?} was accepted", text);
+        }
+    }
+}
@@ -0,0 +1,80 @@
+use anyhow::{anyhow, Result};
+use clap::{Parser, ValueEnum};
+
+fn onoff_parse(src: &str) -> Result<bool> {
+    match src {
+        "true" | "on" | "yes" => Ok(true),
+        "false" | "off" | "no" => Ok(false),
+        _ => Err(anyhow!(
+            "Could not understand {}, check your input, should be true/false, on/off or yes/no",
+            src
+        )),
+    }
+}
+
+/// The ftp command will control the uploads of the camera to an FTP server
+#[derive(Parser, Debug)]
+pub struct Opt {
+    /// The name of the camera. Must be a name in the config
+    pub camera: String,
+    /// The action to perform
+    #[command(subcommand)]
+    pub cmd: FtpAction,
+}
+
+#[derive(Parser, Debug)]
+pub enum FtpAction {
+    /// Get the current FTP settings and task, the password is not shown
+    Get,
+    /// Change the FTP settings, anything not given is kept
+    Set {
+        /// The address of the FTP server
+        #[arg(long)]
+        server: Option<String>,
+        /// The port of the FTP server
+        #[arg(long)]
+        port: Option<u16>,
+        /// The username to log in with
+        #[arg(long)]
+        username: Option<String>,
+        /// Read the password to log in with from this environment variable
+        ///
+        /// The password is not taken on the command line where other users
+        /// could see it
+        #[arg(long, value_name = "VAR", conflicts_with = "password_stdin")]
+        password_env: Option<String>,
+        /// Read the password to log in with from the first line of stdin
+        #[arg(long)]
+        password_stdin: bool,
+        /// Log in without a username and password
+        #[arg(long, value_parser = onoff_parse, action = clap::ArgAction::Set, value_name = "on|off")]
+        anonymous: Option<bool>,
+        /// The directory on the server to upload into
+        #[arg(long)]
+        directory: Option<String>,
+        /// What to upload, only on cameras that report it
+        #[arg(long, value_enum)]
+        upload: Option<UploadType>,
+        /// Use passive mode, only on cameras that report it
+        #[arg(long, value_parser = onoff_parse, action = clap::ArgAction::Set, value_name = "on|off")]
+        passive: Option<bool>,
+        /// Test the new settings before they are saved
+        #[arg(long)]
+        test: bool,
+    },
+    /// Test the current FTP settings by uploading a file to the server
+    Test,
+    /// Turn the FTP uploads ON
+    On,
+    /// Turn the FTP uploads OFF
+    Off,
+    /// Turn the FTP uploads of the motion ON for every hour of the week
+    Always,
+}
+
+#[derive(Parser, Debug, Clone, Copy, ValueEnum)]
+pub enum UploadType {
+    Picture,
+    Video,
+    Both,
+}
@@ -0,0 +1,205 @@
+///
+/// # Neolink FTP
+///
+/// This module handles the uploads of the camera to an FTP server
+///
+///
+/// # Usage
+///
+/// ```bash
+/// # To get the current settings
+/// neolink ftp --config=config.toml CameraName get
+/// # To point the camera at a server, testing it before it is saved
+/// FTP_PASSWORD=pass neolink ftp --config=config.toml CameraName set --server 192.168.1.2 --username cam --password-env FTP_PASSWORD --directory cams --test
+/// # To upload on motion all the time
+/// neolink ftp --config=config.toml CameraName always
+/// # To turn the uploads off
+/// neolink ftp --config=config.toml CameraName off
+/// ```
+///
+use anyhow::{Context, Result};
+
+mod cmdline;
+
+use crate::common::NeoReactor;
+pub(crate) use cmdline::*;
+
+/// Entry point for the ftp subcommand
+///
+/// Opt is the command line options
+pub(crate) async fn main(opt: Opt, reactor: NeoReactor) -> Result<()> {
+    let camera = reactor.get(&opt.camera).await?;
+
+    match opt.cmd {
+        FtpAction::Get => {
+            let (mut ftp, ftp_task) = camera
+                .run_task(|cam| {
+                    Box::pin(async move {
+                        let ftp = cam.get_ftp().await.context("Unable to get camera ftp")?;
+                        let ftp_task = cam
+                            .get_ftp_task()
+                            .await
+                            .context("Unable to get camera ftp task")?;
+                        Ok((ftp, ftp_task))
+                    })
+                })
+                .await?;
+            if !ftp.password.is_empty() {
+                ftp.password = "<redacted>".to_string();
+            }
+            for ser in [
+                {
+                    let mut buf = bytes::BytesMut::new();
+                    quick_xml::se::to_writer(&mut buf, &ftp).map(|_| buf.to_vec())
+                },
+                {
+                    let mut buf = bytes::BytesMut::new();
+                    quick_xml::se::to_writer(&mut buf, &ftp_task).map(|_| buf.to_vec())
+                },
+            ] {
+                let ser =
+                    String::from_utf8(ser.expect("Should Ser the struct")).expect("Should be UTF8");
+                println!("{}", ser);
+            }
+        }
+        FtpAction::Set {
+            server,
+            port,
+            username,
+            password_env,
+            password_stdin,
+            anonymous,
+            directory,
+            upload,
+            passive,
+            test,
+        } => {
+            let password = read_password(password_env.as_deref(), password_stdin)?;
+            let name = opt.camera.clone();
+            camera
+                .run_task(|cam| {
+                    let name = name.clone();
+                    let server = server.clone();
+                    let username = username.clone();
+                    let password = password.clone();
+                    let directory = directory.clone();
+                    Box::pin(async move {
+                        let mut ftp = cam.get_ftp().await.context("Unable to get camera ftp")?;
+                        if let Some(server) = server {
+                            ftp.server = server;
+                        }
+                        if let Some(port) = port {
+                            ftp.port = port;
+                        }
+                        if let Some(username) = username {
+                            ftp.user_name = username;
+                        }
+                        if let Some(password) = password {
+                            ftp.password = password;
+                        }
+                        if let Some(anonymous) = anonymous {
+                            ftp.anonymous = anonymous.into();
+                        }
+                        if let Some(directory) = directory {
+                            ftp.remote_dir = directory;
+                        }
+                        if let Some(upload) = upload {
+                            if ftp.upload_type.is_none() {
+                                log::warn!(
+                                    "{name}: The camera does not report what it uploads, it may ignore it"
+                                );
+                            }
+                            ftp.upload_type = Some(
+                                match upload {
+                                    UploadType::Picture => "picture",
+                                    UploadType::Video => "video",
+                                    UploadType::Both => "pictureAndVideo",
+                                }
+                                .to_string(),
+                            );
+                        }
+                        if let Some(passive) = passive {
+                            if ftp.transport_mode.is_none() {
+                                log::warn!(
+                                    "{name}: The camera does not report its transport mode, it may ignore it"
+                                );
+                            }
+                            ftp.transport_mode = Some(passive.into());
+                        }
+                        if test {
+                            cam.test_ftp(ftp.clone())
+                                .await
+                                .context("The camera could not upload to the FTP server")?;
+                        }
+                        cam.set_ftp(ftp).await.context("Unable to set camera ftp")
+                    })
+                })
+                .await?;
+        }
+        FtpAction::Test => {
+            camera
+                .run_task(|cam| {
+                    Box::pin(async move {
+                        let ftp = cam.get_ftp().await.context("Unable to get camera ftp")?;
+                        cam.test_ftp(ftp)
+                            .await
+                            .context("The camera could not upload to the FTP server")
+                    })
+                })
+                .await?;
+            println!("The camera uploaded to the FTP server");
+        }
+        FtpAction::On => {
+            camera
+                .run_task(|cam| {
+                    Box::pin(async move {
+                        cam.ftp_on()
+                            .await
+                            .context("Unable to turn on the camera ftp")
+                    })
+                })
+                .await?;
+        }
+        FtpAction::Off => {
+            camera
+                .run_task(|cam| {
+                    Box::pin(async move {
+                        cam.ftp_off()
+                            .await
+                            .context("Unable to turn off the camera ftp")
+                    })
+                })
+                .await?;
+        }
+        FtpAction::Always => {
+            camera
+                .run_task(|cam| {
+                    Box::pin(async move {
+                        cam.ftp_on_always()
+                            .await
+                            .context("Unable to set the camera ftp schedule")
+                    })
+                })
+                .await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// The password from the environment variable `env` or from stdin
+fn read_password(env: Option<&str>, stdin: bool) -> Result<Option<String>> {
+    if let Some(env) = env {
+        let password = std::env::var(env)
+            .with_context(|| format!("Could not read the password from ${}", env))?;
+        Ok(Some(password))
+    } else if stdin {
+        let mut password = String::new();
+        std::io::stdin()
+            .read_line(&mut password)
+            .context("Could not read the password from stdin")?;
+        Ok(Some(password.trim_end_matches(['\r', '\n']).to_string()))
+    } else {
+        Ok(None)
+    }
+}
@@ -33,24 +33,29 @@ use anyhow::{Context, Result};
 use clap::Parser;
 use env_logger::Env;
 use log::*;
-use std::fs;
-use validator::Validate;
+use tokio::sync::broadcast::channel as broadcast;
+use tokio_util::sync::CancellationToken;
 
+mod audio;
 mod battery;
 mod cmdline;
 mod common;
 mod config;
+mod configtool;
+mod disk;
+mod events;
+mod ftp;
 #[cfg(feature = "gstreamer")]
 mod image;
 mod mqtt;
 mod pir;
 mod ptz;
 mod reboot;
+mod recordcfg;
 #[cfg(feature = "gstreamer")]
 mod rtsp;
 mod services;
 mod statusled;
-#[cfg(feature = "gstreamer")]
 mod talk;
 mod users;
 mod utils;
@@ -73,19 +78,121 @@ async fn main() -> Result<()> {
 
     let opt = Opt::parse();
 
-    let conf_path = opt.config.context("Must supply --config file")?;
-    let config: Config = toml::from_str(
-        &fs::read_to_string(&conf_path)
-            .with_context(|| format!("Failed to read {:?}", conf_path))?,
-    )
-    .with_context(|| format!("Failed to parse the {:?} config file", conf_path))?;
+    // The config subcommand must work even when the config is broken
+    if let Some(Command::Config(opts)) = opt.cmd {
+        return configtool::main(opts, opt.config.as_deref(), opt.strict);
+    }
 
-    config
-        .validate()
-        .with_context(|| format!("Failed to validate the {:?} config file", conf_path))?;
+    let conf_path = opt.config.context("Must supply --config file")?;
+    let config = Config::load(&conf_path, opt.strict)?;
 
     let neo_reactor = NeoReactor::new(config.clone()).await;
 
+    // Stops the background tasks when we exit
+    let background_cancel = CancellationToken::new();
+    let _background_drop = background_cancel.clone().drop_guard();
+
+    // Reload the config whenever the file changes or on SIGHUP
+    let reload_cancel = background_cancel.clone();
+    let reload_path = conf_path.clone();
+    let reload_reactor = neo_reactor.clone();
+    let reload_strict = opt.strict;
+    tokio::task::spawn(async move {
+        tokio::select! {
+            _ = reload_cancel.cancelled() => {},
+            _ = common::watch_config_file(reload_path, reload_reactor, reload_strict) => {},
+        }
+    });
+
+    // Report our health while running as a service
+    let is_service = match &opt.cmd {
+        None | Some(Command::Mqtt(_)) => true,
+        #[cfg(feature = "gstreamer")]
+        Some(Command::Rtsp(_)) | Some(Command::MqttRtsp(_)) => true,
+        _ => false,
+    };
+    if let (true, Some(health_config)) = (is_service, config.health.clone()) {
+        let health_reactor = neo_reactor.clone();
+        let health_cancel = background_cancel.clone();
+        tokio::task::spawn(async move {
+            tokio::select! {
+                _ = health_cancel.cancelled() => {},
+                v = common::serve_health(health_config, health_reactor) => {
+                    if let Err(e) = v {
+                        error!("Health server stopped: {e:?}");
+                    }
+                },
+            }
+        });
+    }
+
+    // Send the camera events to the webhooks and the event log
+    if is_service {
+        let (events, _) = broadcast(100);
+        let events_reactor = neo_reactor.clone();
+        let events_cancel = background_cancel.clone();
+        let events_tx = events.clone();
+        tokio::task::spawn(async move {
+            tokio::select! {
+                _ = events_cancel.cancelled() => {},
+                v = common::watch_events(events_reactor, events_tx, common::EventSources {
+                    active: |config| !config.webhooks.is_empty() || config.event_log.is_some(),
+                    cameras: vec![],
+                    // MQTT listens to the floodlight
+                    floodlight: false,
+                }) => {
+                    if let Err(e) = v {
+                        error!("Events stopped: {e:?}");
+                    }
+                },
+            }
+        });
+
+        let webhooks_reactor = neo_reactor.clone();
+        let webhooks_cancel = background_cancel.clone();
+        let webhooks_events = events.clone();
+        tokio::task::spawn(async move {
+            tokio::select! {
+                _ = webhooks_cancel.cancelled() => {},
+                v = common::run_webhooks(webhooks_reactor, webhooks_events) => {
+                    if let Err(e) = v {
+                        error!("Webhooks stopped: {e:?}");
+                    }
+                },
+            }
+        });
+
+        let event_log_reactor = neo_reactor.clone();
+        let event_log_cancel = background_cancel.clone();
+        tokio::task::spawn(async move {
+            tokio::select! {
+                _ = event_log_cancel.cancelled() => {},
+                v = common::run_event_log(event_log_reactor, events) => {
+                    if let Err(e) = v {
+                        error!("Event log stopped: {e:?}");
+                    }
+                },
+            }
+        });
+    }
+
+    // Run the rules, `neolink mqtt` runs its own so that they can publish
+    #[cfg(feature = "gstreamer")]
+    if let None | Some(Command::Rtsp(_)) = &opt.cmd {
+        let rules_reactor = neo_reactor.clone();
+        let rules_cancel = background_cancel.clone();
+        tokio::task::spawn(async move {
+            tokio::select! {
+                _ = rules_cancel.cancelled() => {},
+                v = common::run_rules(rules_reactor, None) => {
+                    if let Err(e) = v {
+                        error!("Rules stopped: {e:?}");
+                    }
+                },
+            }
+        });
+    }
+
     match opt.cmd {
         #[cfg(feature = "gstreamer")]
         None => {
@@ -102,7 +209,7 @@ async fn main() -> Result<()> {
                 "Deprecated command line option. Please use: `neolink mqtt --config={:?}`",
                 conf_path
             );
-            mqtt::main(mqtt::Opt {}, neo_reactor.clone()).await?;
+            mqtt::main(mqtt::Opt {}, neo_reactor.clone(), &conf_path).await?;
         }
         #[cfg(feature = "gstreamer")]
         Some(Command::Rtsp(opts)) => {
@@ -114,23 +221,28 @@ async fn main() -> Result<()> {
         Some(Command::Reboot(opts)) => {
             reboot::main(opts, neo_reactor.clone()).await?;
         }
+        Some(Command::RecordConfig(opts)) => {
+            recordcfg::main(opts, neo_reactor.clone()).await?;
+        }
         Some(Command::Pir(opts)) => {
             pir::main(opts, neo_reactor.clone()).await?;
         }
         Some(Command::Ptz(opts)) => {
             ptz::main(opts, neo_reactor.clone()).await?;
         }
-        #[cfg(feature = "gstreamer")]
+        Some(Command::Audio(opts)) => {
+            audio::main(opts, neo_reactor.clone()).await?;
+        }
         Some(Command::Talk(opts)) => {
             talk::main(opts, neo_reactor.clone()).await?;
         }
         Some(Command::Mqtt(opts)) => {
-            mqtt::main(opts, neo_reactor.clone()).await?;
+            mqtt::main(opts, neo_reactor.clone(), &conf_path).await?;
         }
         #[cfg(feature = "gstreamer")]
         Some(Command::MqttRtsp(opts)) => {
             tokio::select! {
-                v = mqtt::main(opts, neo_reactor.clone()) => v,
+                v = mqtt::main(opts, neo_reactor.clone(), &conf_path) => v,
                 v = rtsp::main(rtsp::Opt {}, neo_reactor.clone()) => v,
             }?;
         }
@@ -141,12 +253,22 @@ async fn main() -> Result<()> {
         Some(Command::Battery(opts)) => {
             battery::main(opts, neo_reactor.clone()).await?;
         }
+        Some(Command::Disk(opts)) => {
+            disk::main(opts, neo_reactor.clone()).await?;
+        }
+        Some(Command::Ftp(opts)) => {
+            ftp::main(opts, neo_reactor.clone()).await?;
+        }
         Some(Command::Services(opts)) => {
             services::main(opts, neo_reactor.clone()).await?;
         }
         Some(Command::Users(opts)) => {
             users::main(opts, neo_reactor.clone()).await?;
         }
+        Some(Command::Events(opts)) => {
+            events::main(opts, neo_reactor.clone()).await?;
+        }
+        Some(Command::Config(_)) => unreachable!("Handled before the config is loaded"),
     }
 
     Ok(())
@@ -8,9 +8,10 @@ use log::*;
 
 use super::mqttc::MqttInstance;
 use crate::{common::NeoInstance, config::MqttDiscoveryConfig};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize, Serializer};
 
-#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq, Copy, Hash)]
+#[derive(Debug, Deserialize, Serialize, JsonSchema, Clone, PartialEq, Eq, Copy, Hash)]
 pub(crate) enum Discoveries {
     #[serde(alias = "floodlight", alias = "light")]
     Floodlight,
@@ -30,6 +31,14 @@ pub(crate) enum Discoveries {
     Battery,
     #[serde(alias = "siren", alias = "alarm")]
     Siren,
+    #[serde(alias = "visitor", alias = "doorbell")]
+    Visitor,
+    #[serde(alias = "audio_level", alias = "audio")]
+    AudioLevel,
+    #[serde(alias = "sound_alarm", alias = "sound_detection", alias = "sound")]
+    SoundAlarm,
+    #[serde(alias = "disk", alias = "sd", alias = "sd_card")]
+    Disk,
 }
 
 #[derive(Debug, Clone)]
@@ -198,6 +207,21 @@ struct DiscoverySensor {
     unit_of_measurement: String,
 }
 
+/// A trigger for home assistant's device automations
+///
+/// https://www.home-assistant.io/integrations/device_trigger.mqtt/
+#[derive(Serialize, Debug)]
+struct DiscoveryTrigger {
+    device: DiscoveryDevice,
+    // Trigger specific
+    automation_type: String,
+    topic: String,
+    payload: String,
+    #[serde(rename = "type")]
+    trigger_type: String,
+    subtype: String,
+}
+
 /// Enables MQTT discovery for a camera. See docs at https://www.home-assistant.io/integrations/mqtt/#mqtt-discovery
 pub(crate) async fn enable_discovery(
     discovery_config: &MqttDiscoveryConfig,
@@ -624,6 +648,185 @@ pub(crate) async fn enable_discovery(
                     )
                 })?;
             }
+            Discoveries::Visitor => {
+                let config_data = DiscoveryTrigger {
+                    device: device.clone(),
+
+                    // Trigger specific
+                    automation_type: "trigger".to_string(),
+                    topic: format!("neolink/{}/status/visitor", cam_config.name),
+                    payload: "on".to_string(),
+                    trigger_type: "button_short_press".to_string(),
+                    subtype: "doorbell".to_string(),
+                };
+
+                // Each feature needs to be individually registered
+                mqtt.send_message_with_root_topic(
+                    &format!(
+                        "{}/device_automation/neolink_{}_visitor",
+                        discovery_config.topic, cam_config.name
+                    ),
+                    "config",
+                    &serde_json::to_string(&config_data).with_context(|| {
+                        "Cound not serialise discovery visitor config into json"
+                    })?,
+                    true,
+                )
+                .await
+                .with_context(|| {
+                    format!(
+                        "Failed to publish visitor auto-discover data on over MQTT for {}",
+                        cam_config.name
+                    )
+                })?;
+            }
+            Discoveries::AudioLevel => {
+                let config_data = DiscoverySensor {
+                    // Common across all potential features
+                    device: device.clone(),
+                    availability: availability.clone(),
+
+                    // Identifiers
+                    name: format!("{} Audio Level", friendly_name.as_str()),
+                    unique_id: format!("neolink_{}_audio_level", cam_config.name),
+                    icon: Some("mdi:volume-high".to_string()),
+
+                    // Camera specific
+                    state_topic: format!("neolink/{}/status/audio_level", cam_config.name),
+                    state_class: "measurement".to_string(),
+                    unit_of_measurement: "dB".to_string(),
+                };
+
+                // Each feature needs to be individually registered
+                mqtt.send_message_with_root_topic(
+                    &format!(
+                        "{}/sensor/{}",
+                        discovery_config.topic, &config_data.unique_id
+                    ),
+                    "config",
+                    &serde_json::to_string(&config_data).with_context(|| {
+                        "Cound not serialise discovery audio level config into json"
+                    })?,
+                    true,
+                )
+                .await
+                .with_context(|| {
+                    format!(
+                        "Failed to publish audio level auto-discover data on over MQTT for {}",
+                        cam_config.name
+                    )
+                })?;
+            }
+            Discoveries::Disk => {
+                let config_data = DiscoverySensor {
+                    // Common across all potential features
+                    device: device.clone(),
+                    availability: availability.clone(),
+
+                    // Identifiers
+                    name: format!("{} SD Card Free", friendly_name.as_str()),
+                    unique_id: format!("neolink_{}_disk_free", cam_config.name),
+                    icon: Some("mdi:sd".to_string()),
+
+                    // Camera specific
+                    state_topic: format!("neolink/{}/status/disk/0/free", cam_config.name),
+                    state_class: "measurement".to_string(),
+                    unit_of_measurement: "%".to_string(),
+                };
+
+                // Each feature needs to be individually registered
+                mqtt.send_message_with_root_topic(
+                    &format!(
+                        "{}/sensor/{}",
+                        discovery_config.topic, &config_data.unique_id
+                    ),
+                    "config",
+                    &serde_json::to_string(&config_data)
+                        .with_context(|| "Cound not serialise discovery disk config into json")?,
+                    true,
+                )
+                .await
+                .with_context(|| {
+                    format!(
+                        "Failed to publish disk auto-discover data on over MQTT for {}",
+                        cam_config.name
+                    )
+                })?;
+            }
+            Discoveries::SoundAlarm => {
+                let config_data = DiscoveryBinarySensor {
+                    // Common across all potential features
+                    device: device.clone(),
+                    availability: availability.clone(),
+
+                    // Identifiers
+                    name: format!("{} Sound", friendly_name.as_str()),
+                    unique_id: format!("neolink_{}_sound", cam_config.name),
+                    icon: Some("mdi:ear-hearing".to_string()),
+
+                    // Switch specific
+                    state_topic: format!("neolink/{}/status/sound", cam_config.name),
+                    payload_off: "off".to_string(),
+                    payload_on: "on".to_string(),
+                };
+
+                // Each feature needs to be individually registered
+                mqtt.send_message_with_root_topic(
+                    &format!(
+                        "{}/binary_sensor/{}",
+                        discovery_config.topic, &config_data.unique_id
+                    ),
+                    "config",
+                    &serde_json::to_string(&config_data)
+                        .with_context(|| "Cound not serialise discovery sound config into json")?,
+                    true,
+                )
+                .await
+                .with_context(|| {
+                    format!(
+                        "Failed to publish sound auto-discover data on over MQTT for {}",
+                        cam_config.name
+                    )
+                })?;
+
+                let config_data = DiscoverySwitch {
+                    // Common across all potential features
+                    device: device.clone(),
+                    availability: availability.clone(),
+
+                    // Identifiers
+                    name: format!("{} Sound Detection", friendly_name.as_str()),
+                    unique_id: format!("neolink_{}_sound_detection", cam_config.name),
+                    icon: Some("mdi:microphone".to_string()),
+
+                    // Switch specific
+                    command_topic: format!("neolink/{}/control/sound", cam_config.name),
+                    payload_off: "off".to_string(),
+                    payload_on: "on".to_string(),
+                    state_topic: None,
+                    state_off: None,
+                    state_on: None,
+                };
+
+                mqtt.send_message_with_root_topic(
+                    &format!(
+                        "{}/switch/{}",
+                        discovery_config.topic, &config_data.unique_id
+                    ),
+                    "config",
+                    &serde_json::to_string(&config_data).with_context(|| {
+                        "Cound not serialise discovery sound detection config into json"
+                    })?,
+                    true,
+                )
+                .await
+                .with_context(|| {
+                    format!(
+                        "Failed to publish sound detection auto-discover data on over MQTT for {}",
+                        cam_config.name
+                    )
+                })?;
+            }
         }
     }
 
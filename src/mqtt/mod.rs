@@ -17,20 +17,39 @@
 //! - `/control/ptz` [up|down|left|right|in|out] (amount) Control the PTZ movements, amount defaults to 32.0
 //! - `/control/ptz/preset` [id] Move the camera to a known preset
 //! - `/control/ptz/assign` [id] [name] Assign the current ptz position to an ID and name
+//! - `/control/sound [on|off]` Turns the sound detection of the camera on/off
+//! - `/control/sound/sensitivity` [1-100] Set how easily a sound raises the alarm
+//! - `/control/sound/schedule` [always] Detect sounds every hour of the week
+//! - `/control/talk` [file|base64] Queue an audio file for the camera to play,
+//!   files are only read from the `talk_dir` of the `[mqtt]` config
 //!
 //! Status Messages:
 //!
 //! `/status offline` Sent when the neolink goes offline this is a LastWill message
 //! `/status disconnected` Sent when the camera goes offline
 //! `/status/battery` Sent in reply to a `/query/battery`
+//! `/status/disk` Sent in reply to a `/query/disk`
+//! `/status/disk/{number}/free` The free space of each SD card in %, also
+//!    `free_mb` and `state`, sent every `disk_update` ms
 //! `/status/pir` Sent in reply to a `/query/pir`
 //! `/status/ptz/preset` Sent in reply to a `/query/ptz/preset`
+//! `/status/sound` `on` when the sound detection of the camera hears something
+//! `/status/sound/config` Sent in reply to a `/query/sound`
+//! `/status/time_drift` The seconds that the camera's clock is ahead of ours,
+//!    sent on each check when `[cameras.time_sync]` is set
+//! `/status/audio_level` The RMS level in dB of the sound that the camera
+//!    hears, sent every `report_interval` when `[cameras.audio_level]` is set
+//! `/status/loud_noise` `on` while the sound is over the `audio_level` threshold
+//! `/status/talk` `queued`, `playing`, `finished` or `failed` as the
+//!    audio from `/control/talk` is played
 //!
 //! Query Messages:
 //!
 //! `/query/battery` Request that the camera reports its battery level
+//! `/query/disk` Request that the camera reports its SD cards
 //! `/query/pir` Request that the camera reports its pir status
 //! `/query/ptz/preset` Request that the camera reports the PTZ presets
+//! `/query/sound` Request that the camera reports its sound detection settings
 //! `/query/preview` Request that the camera post a base64 encoded jpeg
 //!    of the stream to `/status/preview`
 //!
@@ -60,9 +79,15 @@
 //! `credentials` are the username and password required to identify with the mqtt server
 //!
 use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
-use std::collections::{HashMap, HashSet};
+use std::{
+    collections::{HashMap, HashSet},
+    path::Path,
+};
 use tokio::{
-    sync::mpsc::channel as mpsc,
+    sync::{
+        mpsc::{channel as mpsc, Sender as MpscSender},
+        watch::Receiver as WatchReceiver,
+    },
     task::JoinSet,
     time::{interval, sleep, Duration, MissedTickBehavior},
 };
@@ -70,37 +95,42 @@ use tokio_stream::{wrappers::IntervalStream, StreamExt};
 use tokio_util::sync::CancellationToken;
 use validator::Validate;
 
-use neolink_core::bc_protocol::{Direction as BcDirection, LightState};
+use neolink_core::bc_protocol::{AudioPlay, Direction as BcDirection, LightState};
 
 mod cmdline;
 mod discovery;
 mod mqttc;
+mod talk;
 
 use crate::{
-    common::{MdState, NeoInstance, NeoReactor},
-    config::Config,
+    common::{MdState, NeoInstance, NeoReactor, SoundState, VisitorState},
+    config::{write_config, Config},
     AnyResult,
 };
 use anyhow::{anyhow, Context, Result};
 pub(crate) use cmdline::Opt;
 pub(crate) use discovery::Discoveries;
 use log::*;
+pub(crate) use mqttc::MqttInstance;
 use mqttc::{Mqtt, MqttReplyRef};
 
 use self::{
     discovery::enable_discovery,
-    mqttc::{MqttInstance, MqttReply},
+    mqttc::MqttReply,
+    talk::{parse_talk_audio, TalkAudio, TALK_QUEUE_LEN},
 };
 
 /// Entry point for the mqtt subcommand
 ///
 /// Opt is the command line options
-pub(crate) async fn main(_: Opt, reactor: NeoReactor) -> Result<()> {
+/// `config_path` is where config changes made over MQTT are written
+/// if `persist_config` is enabled
+pub(crate) async fn main(_: Opt, reactor: NeoReactor, config_path: &Path) -> Result<()> {
     let mut set = tokio::task::JoinSet::new();
     let global_cancel = CancellationToken::new();
     let cancel_drop = global_cancel.clone().drop_guard();
     let config = reactor.config().await?;
-    let mqtt = Mqtt::new(config.clone()).await;
+    let mqtt = Mqtt::new(config.clone(), reactor.services()).await;
 
     // Startup and stop cameras as they are added/removed to the config
     let thread_cancel = global_cancel.clone();
@@ -131,6 +161,7 @@ pub(crate) async fn main(_: Opt, reactor: NeoReactor) -> Result<()> {
                             let thread_global_cancel = thread_cancel2.clone();
                             let thread_reactor2 = thread_reactor.clone();
                             let mqtt_instance = thread_instance.subscribe(name).await?;
+                            let global_config = thread_config.clone();
                             let name = name.clone();
                             set.spawn(async move {
                                 loop {
@@ -143,7 +174,7 @@ pub(crate) async fn main(_: Opt, reactor: NeoReactor) -> Result<()> {
                                         _ = local_cancel.cancelled() => {
                                             AnyResult::Ok(())
                                         },
-                                        v = listen_on_camera(camera, mqtt_instance) => {
+                                        v = listen_on_camera(camera, mqtt_instance, global_config.clone()) => {
                                             v
                                         },
                                     };
@@ -167,6 +198,17 @@ pub(crate) async fn main(_: Opt, reactor: NeoReactor) -> Result<()> {
         }
     });
 
+    // This thread runs the rules so that they can publish to MQTT
+    let thread_instance = mqtt.subscribe("").await?;
+    let thread_reactor = reactor.clone();
+    let thread_cancel = global_cancel.clone();
+    set.spawn(async move {
+        tokio::select! {
+            _ = thread_cancel.cancelled() => AnyResult::Ok(()),
+            v = crate::common::run_rules(thread_reactor, Some(thread_instance)) => v,
+        }
+    });
+
     // This threads prints the config
     let mut thread_config = config.clone();
     let thread_instance = mqtt.subscribe("").await?;
@@ -193,6 +235,7 @@ pub(crate) async fn main(_: Opt, reactor: NeoReactor) -> Result<()> {
 
     // This threads checks for config changes on the mqtt
     let thread_config = config.clone();
+    let thread_config_path = config_path.to_path_buf();
     let mut thread_instance = mqtt.subscribe("").await?;
     let thread_reactor = reactor.clone();
     let thread_cancel = global_cancel.clone();
@@ -214,6 +257,15 @@ pub(crate) async fn main(_: Opt, reactor: NeoReactor) -> Result<()> {
                         let curr_config = thread_config.borrow().clone();
                         let mut config = config?;
 
+                        if let Err(e) = config.resolve_mqtt_secrets(&curr_config).with_context(|| {
+                            format!("Failed to resolve the secrets in the MQTT {:?} config file", msg.topic)
+                        }) {
+                            thread_instance
+                                .send_message("config/status", &format!("{:?}", e), false)
+                                .await?;
+                            continue;
+                        }
+
                         // Fill in skipped passwords
                         if let (Some(mqtt), Some(curr_mqtt)) = (config.mqtt.as_mut(), curr_config.mqtt.as_ref()) {
                             if mqtt.credentials.is_none() {
@@ -225,6 +277,8 @@ pub(crate) async fn main(_: Opt, reactor: NeoReactor) -> Result<()> {
                             if mqtt.client_auth.is_none() {
                                 mqtt.client_auth = curr_mqtt.client_auth.clone();
                             }
+                            // Only the config file chooses the files that control/talk may play
+                            mqtt.talk_dir = curr_mqtt.talk_dir.clone();
                         }
                         for cam in config.cameras.iter_mut() {
                             let name = cam.name.clone();
@@ -260,11 +314,30 @@ pub(crate) async fn main(_: Opt, reactor: NeoReactor) -> Result<()> {
                             continue;
                         }
 
-                        let result = thread_reactor.update_config(config).await;
+                        // Only the file on disk can turn on writing to it, not the MQTT config
+                        let persist = match Config::load(&thread_config_path, false) {
+                            Ok(disk) => disk.mqtt.is_some_and(|mqtt| mqtt.persist_config),
+                            Err(e) => {
+                                log::debug!("Not saving the config: {e:?}");
+                                false
+                            }
+                        };
+                        let result = thread_reactor.update_config(config.clone()).await;
                         thread_instance
                             .send_message("config/status", &format!("{:?}", result), false)
                             .await?;
                         log::info!("Updated config");
+
+                        if result.is_ok() && persist {
+                            if let Err(e) = write_config(&thread_config_path, &config) {
+                                log::error!("Could not save the config: {e:?}");
+                                thread_instance
+                                    .send_message("config/status", &format!("{:?}", e), false)
+                                    .await?;
+                            } else {
+                                log::info!("Saved config to {:?}", thread_config_path);
+                            }
+                        }
                     }
                 }
                 AnyResult::Ok(())
@@ -283,7 +356,11 @@ pub(crate) async fn main(_: Opt, reactor: NeoReactor) -> Result<()> {
     Ok(())
 }
 
-async fn listen_on_camera(camera: NeoInstance, mqtt_instance: MqttInstance) -> Result<()> {
+async fn listen_on_camera(
+    camera: NeoInstance,
+    mqtt_instance: MqttInstance,
+    global_config: WatchReceiver<Config>,
+) -> Result<()> {
     let mut watch_config = camera.config().await?;
     let camera_name = watch_config.borrow().name.clone();
     let mut config;
@@ -330,6 +407,10 @@ async fn listen_on_camera(camera: NeoInstance, mqtt_instance: MqttInstance) -> R
 
                 let camera_motion = camera.clone();
                 let mqtt_motion = mqtt_instance.resubscribe().await?;
+                let camera_visitor = camera.clone();
+                let mqtt_visitor = mqtt_instance.resubscribe().await?;
+                let camera_sound = camera.clone();
+                let mqtt_sound = mqtt_instance.resubscribe().await?;
 
                 #[cfg(feature = "pushnoti")]
                 let camera_pn = camera.clone();
@@ -341,10 +422,22 @@ async fn listen_on_camera(camera: NeoInstance, mqtt_instance: MqttInstance) -> R
 
                 let camera_battery = camera.clone();
                 let mqtt_battery = mqtt_instance.resubscribe().await?;
+                let camera_disk = camera.clone();
+                let mqtt_disk = mqtt_instance.resubscribe().await?;
 
                 let camera_floodlight_tasks = camera.clone();
                 let mqtt_floodlight_tasks = mqtt_instance.resubscribe().await?;
 
+                let camera_time = camera.clone();
+                let mqtt_time = mqtt_instance.resubscribe().await?;
+
+                let camera_audio = camera.clone();
+                let mqtt_audio = mqtt_instance.resubscribe().await?;
+
+                let camera_talk = camera.clone();
+                let mqtt_talk = mqtt_instance.resubscribe().await?;
+                let (talk_tx, mut talk_rx) = mpsc(TALK_QUEUE_LEN);
+
                 tokio::select! {
                     _ = cancel.cancelled() => AnyResult::Ok(()),
                     // Handles incomming requests
@@ -357,12 +450,14 @@ async fn listen_on_camera(camera: NeoInstance, mqtt_instance: MqttInstance) -> R
                                     let mqtt_msg = mqtt_msg.resubscribe().await?;
                                     let camera_msg = camera_msg.clone();
                                     let tx = tx.clone();
+                                    let talk_tx = talk_tx.clone();
+                                    let talk_dir = global_config.borrow().mqtt.as_ref().and_then(|mqtt| mqtt.talk_dir.clone());
                                     let cancel_msg = cancel_msg.clone();
                                     set_msg.spawn(async move {
                                         tokio::select!{
                                             _ = cancel_msg.cancelled() => AnyResult::Ok(()),
                                             v = async {
-                                                let res = handle_mqtt_message(msg, &mqtt_msg, &camera_msg).await;
+                                                let res = handle_mqtt_message(msg, &mqtt_msg, &camera_msg, &talk_tx, talk_dir.as_deref()).await;
                                                 if res.is_err() {
                                                     tx.send(res).await?;
                                                 }
@@ -454,7 +549,7 @@ async fn listen_on_camera(camera: NeoInstance, mqtt_instance: MqttInstance) -> R
                         let mut md = camera_motion.motion().await?;
                         loop {
                             let v = async {
-                                md.wait_for(|state| matches!(state, MdState::Start(_))).await.with_context(|| {
+                                md.wait_for(|state| matches!(state, MdState::Start(..))).await.with_context(|| {
                                     format!("{}: MdStart Watch Dropped", camera_name)
                                 })?;
                                 mqtt_motion.send_message("status/motion", "on", true).await.with_context(|| {
@@ -476,6 +571,44 @@ async fn listen_on_camera(camera: NeoInstance, mqtt_instance: MqttInstance) -> R
                             }?;
                         }
                     }, if config.enable_motion => v,
+                    // Handle the visitors of a doorbell
+                    v = async {
+                        let mut visitor = camera_visitor.visitor().await?;
+                        loop {
+                            visitor.changed().await.with_context(|| {
+                                format!("{}: Visitor Watch Dropped", camera_name)
+                            })?;
+                            let state = visitor.borrow_and_update().clone();
+                            // Not retained as a ring is an event, which also stops
+                            // home assistant triggering again when it reconnects
+                            let message = match state {
+                                VisitorState::Start(_) => "on",
+                                VisitorState::Stop(_) => "off",
+                                VisitorState::Unknown => continue,
+                            };
+                            mqtt_visitor.send_message("status/visitor", message, false).await.with_context(|| {
+                                format!("{}: Failed to publish the visitor", camera_name)
+                            })?;
+                        }
+                    }, if config.enable_visitor => v,
+                    // Handle the sound detection of the camera
+                    v = async {
+                        let mut sound = camera_sound.sound().await?;
+                        loop {
+                            sound.changed().await.with_context(|| {
+                                format!("{}: Sound Watch Dropped", camera_name)
+                            })?;
+                            let state = sound.borrow_and_update().clone();
+                            let message = match state {
+                                SoundState::Start(_) => "on",
+                                SoundState::Stop(_) => "off",
+                                SoundState::Unknown => continue,
+                            };
+                            mqtt_sound.send_message("status/sound", message, true).await.with_context(|| {
+                                format!("{}: Failed to publish the sound", camera_name)
+                            })?;
+                        }
+                    }, if config.enable_sound => v,
                     // Handle the SNAP (image preview)
                     v = async {
                         let mut wait = IntervalStream::new({
@@ -563,6 +696,49 @@ async fn listen_on_camera(camera: NeoInstance, mqtt_instance: MqttInstance) -> R
                         }?;
                         AnyResult::Ok(())
                     }, if config.enable_battery => v,
+                    // Handle the disk publish
+                    v = async {
+                        let mut wait = IntervalStream::new({
+                            let mut i = interval(Duration::from_millis(config.disk_update));
+                            i.set_missed_tick_behavior(MissedTickBehavior::Skip);
+                            i
+                        });
+
+                        while wait.next().await.is_some() {
+                            let xml = camera_disk.run_passive_task(|cam| {
+                                Box::pin(async move {
+                                    let xml = cam.get_hdd_info_list().await?;
+                                    AnyResult::Ok(xml)
+                                })
+                            }).await;
+                            let xml = match xml {
+                                Err(e) => match e.downcast::<neolink_core::Error>() {
+                                    Ok(neolink_core::Error::MissingAbility{..}) | Ok(neolink_core::Error::CameraServiceUnavailable{..}) => {
+                                        log::debug!("Disks not supported");
+                                        futures::future::pending().await
+                                    },
+                                    Ok(e) => Err(e.into()),
+                                    Err(e) => Err(e),
+                                }
+                                n => n,
+                            }?;
+                            for hdd_info in xml.hdd_info.iter() {
+                                for (topic, message) in [
+                                    ("free", format!("{:.1}", hdd_info.free_percent())),
+                                    ("free_mb", format!("{}", hdd_info.remain_size)),
+                                    ("state", hdd_info.state().as_str().to_string()),
+                                ] {
+                                    mqtt_disk
+                                        .send_message(&format!("status/disk/{}/{}", hdd_info.number, topic), &message, true)
+                                        .await
+                                        .with_context(|| {
+                                            format!("{}: Failed to publish disk", camera_name)
+                                        })?;
+                                }
+                            }
+                        }
+                        AnyResult::Ok(())
+                    }, if config.enable_disk => v,
                     // Handle the push notification messages
                     v = async {
                         #[cfg(feature = "pushnoti")]
@@ -630,6 +806,63 @@ async fn listen_on_camera(camera: NeoInstance, mqtt_instance: MqttInstance) -> R
                         }
                         AnyResult::Ok(())
                     }, if config.enable_floodlight => v,
+                    // Handle the time drift publish, this is only measured with `[cameras.time_sync]`
+                    v = async {
+                        let mut drift = camera_time.time_drift().await?;
+                        loop {
+                            let measured = *drift.wait_for(|drift| drift.is_some()).await.with_context(|| {
+                                format!("{}: Time Drift Watch Dropped", camera_name)
+                            })?;
+                            if let Some(measured) = measured {
+                                mqtt_time.send_message("status/time_drift", &format!("{}", measured), true).await.with_context(|| {
+                                    format!("{}: Failed to publish time drift", camera_name)
+                                })?;
+                            }
+                            drift.changed().await?;
+                        }
+                    } => v,
+                    // Handle the audio level publish, this is only measured with `[cameras.audio_level]`
+                    v = async {
+                        let mut audio_level = camera_audio.audio_level().await?;
+                        let mut loud = None;
+                        loop {
+                            let state = *audio_level.wait_for(|state| state.is_some()).await.with_context(|| {
+                                format!("{}: Audio Level Watch Dropped", camera_name)
+                            })?;
+                            if let Some(state) = state {
+                                mqtt_audio.send_message("status/audio_level", &format!("{:.1}", state.level.rms), true).await.with_context(|| {
+                                    format!("{}: Failed to publish audio level", camera_name)
+                                })?;
+                                if loud != Some(state.loud) {
+                                    loud = Some(state.loud);
+                                    let message = if state.loud { "on" } else { "off" };
+                                    mqtt_audio.send_message("status/loud_noise", message, true).await.with_context(|| {
+                                        format!("{}: Failed to publish loud noise", camera_name)
+                                    })?;
+                                }
+                            }
+                            audio_level.changed().await?;
+                        }
+                    } => v,
+                    // Play the audio from `control/talk` one at a time
+                    v = async {
+                        while let Some(talk) = talk_rx.recv().await {
+                            mqtt_talk.send_message("status/talk", "playing", true).await.with_context(|| {
+                                format!("{}: Failed to publish talk playing", camera_name)
+                            })?;
+                            let status = match talk.play(&camera_talk).await {
+                                Ok(()) => "finished",
+                                Err(e) => {
+                                    error!("{}: Failed to talk: {:?}", camera_name, e);
+                                    "failed"
+                                }
+                            };
+                            mqtt_talk.send_message("status/talk", status, true).await.with_context(|| {
+                                format!("{}: Failed to publish talk {}", camera_name, status)
+                            })?;
+                        }
+                        AnyResult::Ok(())
+                    } => v,
                 }?;
                 AnyResult::Ok(())
             } => v,
@@ -645,6 +878,8 @@ async fn handle_mqtt_message(
     msg: MqttReply,
     mqtt: &MqttInstance,
     camera: &NeoInstance,
+    talk_queue: &MpscSender<TalkAudio>,
+    talk_dir: Option<&Path>,
 ) -> Result<()> {
     match msg.as_ref() {
         MqttReplyRef {
@@ -1067,6 +1302,99 @@ async fn handle_mqtt_message(
                 .await
                 .with_context(|| "Failed to publish pir off")?;
         }
+        MqttReplyRef {
+            topic: "control/sound",
+            message: "on",
+        } => {
+            let res = camera
+                .run_task(|cam| {
+                    Box::pin(async move {
+                        cam.sound_alarm_set(true).await?;
+                        AnyResult::Ok(())
+                    })
+                })
+                .await;
+            let reply = if let Err(e) = res {
+                error!("Failed to turn on the sound detection: {:?}", e);
+                format!("FAIL: {e:?}")
+            } else {
+                "OK".to_string()
+            };
+            mqtt.send_message("control/sound", &reply, false)
+                .await
+                .with_context(|| "Failed to publish sound on")?;
+        }
+        MqttReplyRef {
+            topic: "control/sound",
+            message: "off",
+        } => {
+            let res = camera
+                .run_task(|cam| {
+                    Box::pin(async move {
+                        cam.sound_alarm_set(false).await?;
+                        AnyResult::Ok(())
+                    })
+                })
+                .await;
+            let reply = if let Err(e) = res {
+                error!("Failed to turn off the sound detection: {:?}", e);
+                format!("FAIL: {e:?}")
+            } else {
+                "OK".to_string()
+            };
+            mqtt.send_message("control/sound", &reply, false)
+                .await
+                .with_context(|| "Failed to publish sound off")?;
+        }
+        MqttReplyRef {
+            topic: "control/sound/schedule",
+            message: "always",
+        } => {
+            let res = camera
+                .run_task(|cam| {
+                    Box::pin(async move {
+                        cam.sound_alarm_on_always().await?;
+                        AnyResult::Ok(())
+                    })
+                })
+                .await;
+            let reply = if let Err(e) = res {
+                error!("Failed to set the sound detection schedule: {:?}", e);
+                format!("FAIL: {e:?}")
+            } else {
+                "OK".to_string()
+            };
+            mqtt.send_message("control/sound/schedule", &reply, false)
+                .await
+                .with_context(|| "Failed to publish sound schedule")?;
+        }
+        MqttReplyRef {
+            topic: "control/sound/sensitivity",
+            message,
+        } => {
+            let reply = match message.trim().parse::<u8>() {
+                Ok(sensitivity) if (1..=100).contains(&sensitivity) => {
+                    if let Err(e) = camera
+                        .run_task(|cam| {
+                            Box::pin(async move {
+                                cam.sound_alarm_sensitivity(sensitivity).await?;
+                                AnyResult::Ok(())
+                            })
+                        })
+                        .await
+                    {
+                        error!("Failed to set the sound sensitivity: {:?}", e);
+                        format!("FAIL: {e:?}")
+                    } else {
+                        "OK".to_string()
+                    }
+                }
+                _ => format!("FAIL: Expected a sensitivity from 1 to 100 not {message:?}"),
+            };
+            mqtt.send_message("control/sound/sensitivity", &reply, false)
+                .await
+                .with_context(|| "Failed to publish sound sensitivity")?;
+        }
         MqttReplyRef {
             topic: "control/wakeup",
             message,
@@ -1163,6 +1491,57 @@ async fn handle_mqtt_message(
                 .await
                 .with_context(|| "Failed to publish siren")?;
         }
+        MqttReplyRef {
+            topic: "control/audio",
+            message,
+        } => {
+            let reply = match parse_audio_play(message) {
+                Ok(play) => {
+                    if let Err(e) = camera
+                        .run_task(|cam| {
+                            Box::pin(async move {
+                                cam.play_audio(play).await?;
+                                AnyResult::Ok(())
+                            })
+                        })
+                        .await
+                    {
+                        error!("Failed to play the audio: {:?}", e);
+                        format!("FAIL: {e:?}")
+                    } else {
+                        "OK".to_string()
+                    }
+                }
+                Err(e) => format!("FAIL: {e}"),
+            };
+
+            mqtt.send_message("control/audio", &reply, false)
+                .await
+                .with_context(|| "Failed to publish audio")?;
+        }
+        MqttReplyRef {
+            topic: "control/talk",
+            message,
+        } => {
+            let reply = match parse_talk_audio(message, talk_dir).await {
+                Ok(talk) => match talk_queue.try_send(talk) {
+                    Ok(()) => {
+                        mqtt.send_message("status/talk", "queued", true)
+                            .await
+                            .with_context(|| "Failed to publish talk queued")?;
+                        "OK".to_string()
+                    }
+                    Err(_) => format!(
+                        "FAIL: There are already {TALK_QUEUE_LEN} audio files waiting to play"
+                    ),
+                },
+                Err(e) => format!("FAIL: {e}"),
+            };
+
+            mqtt.send_message("control/talk", &reply, false)
+                .await
+                .with_context(|| "Failed to publish talk")?;
+        }
         MqttReplyRef {
             topic: "query/battery",
             ..
@@ -1210,6 +1589,53 @@ async fn handle_mqtt_message(
                 .await
                 .with_context(|| "Failed to publish battery query")?;
         }
+        MqttReplyRef {
+            topic: "query/disk",
+            ..
+        } => {
+            let res = camera
+                .run_task(|cam| {
+                    Box::pin(async move {
+                        let xml = cam.get_hdd_info_list().await?;
+                        AnyResult::Ok(xml)
+                    })
+                })
+                .await;
+            let reply = match res {
+                Err(e) => {
+                    error!("Failed to get disk xml: {:?}", e);
+                    "FAIL"
+                }
+                Ok(xml) => {
+                    let ser_xml = {
+                        let mut buf = bytes::BytesMut::new();
+                        quick_xml::se::to_writer(&mut buf, &xml).map(|_| buf.to_vec())
+                    };
+                    match ser_xml {
+                        Ok(bytes) => match String::from_utf8(bytes) {
+                            Ok(str) => {
+                                mqtt.send_message("status/disk", &str, false)
+                                    .await
+                                    .with_context(|| "Failed to publish disk info")?;
+                                "OK"
+                            }
+                            Err(_) => {
+                                error!("Failed to encode disk status");
+                                "FAIL"
+                            }
+                        },
+                        Err(_) => {
+                            error!("Failed to serialise disk status");
+                            "FAIL"
+                        }
+                    }
+                }
+            }
+            .to_string();
+            mqtt.send_message("query/disk", &reply, false)
+                .await
+                .with_context(|| "Failed to publish disk query")?;
+        }
         MqttReplyRef {
             topic: "query/pir", ..
         } => {
@@ -1256,6 +1682,53 @@ async fn handle_mqtt_message(
                 .await
                 .with_context(|| "Failed to publish pir query")?;
         }
+        MqttReplyRef {
+            topic: "query/sound",
+            ..
+        } => {
+            let res = camera
+                .run_task(|cam| {
+                    Box::pin(async move {
+                        let xml = cam.get_audio_task().await?;
+                        AnyResult::Ok(xml)
+                    })
+                })
+                .await;
+            let reply = match res {
+                Err(e) => {
+                    error!("Failed to get sound detection xml: {:?}", e);
+                    "FAIL"
+                }
+                Ok(xml) => {
+                    let ser_xml = {
+                        let mut buf = bytes::BytesMut::new();
+                        quick_xml::se::to_writer(&mut buf, &xml).map(|_| buf.to_vec())
+                    };
+                    match ser_xml {
+                        Ok(bytes) => match String::from_utf8(bytes) {
+                            Ok(str) => {
+                                mqtt.send_message("status/sound/config", &str, false)
+                                    .await
+                                    .with_context(|| "Failed to publish sound detection info")?;
+                                "OK"
+                            }
+                            Err(_) => {
+                                error!("Failed to encode sound detection status");
+                                "FAIL"
+                            }
+                        },
+                        Err(_) => {
+                            error!("Failed to serialise sound detection status");
+                            "FAIL"
+                        }
+                    }
+                }
+            }
+            .to_string();
+            mqtt.send_message("query/sound", &reply, false)
+                .await
+                .with_context(|| "Failed to publish sound query")?;
+        }
         MqttReplyRef {
             topic: "query/ptz/preset",
             ..
@@ -1342,3 +1815,22 @@ async fn handle_mqtt_message(
     }
     Ok(())
 }
+
+/// The `control/audio` message: `on` to play until `off`, a number of times
+/// such as `3` or a number of seconds such as `30s`
+fn parse_audio_play(message: &str) -> AnyResult<AudioPlay> {
+    let message = message.trim().to_lowercase();
+    Ok(match message.as_str() {
+        "on" => AudioPlay::Continuous,
+        "off" | "stop" => AudioPlay::Stop,
+        seconds if seconds.ends_with('s') => AudioPlay::Seconds(
+            seconds
+                .trim_end_matches('s')
+                .parse()
+                .with_context(|| format!("Could not parse {seconds:?} as seconds"))?,
+        ),
+        times => AudioPlay::Times(times.parse().with_context(|| {
+            format!("Expected on, off, a number or seconds such as 30s not {times:?}")
+        })?),
+    })
+}
@@ -1,4 +1,5 @@
 use crate::{
+    common::{ServiceState, Services},
     config::{Config, MqttServerConfig},
     AnyResult,
 };
@@ -31,7 +32,7 @@ pub(crate) struct Mqtt {
 }
 
 impl Mqtt {
-    pub(crate) async fn new(config: WatchReceiver<Config>) -> Self {
+    pub(crate) async fn new(config: WatchReceiver<Config>, services: Services) -> Self {
         let (incoming_tx, _) = broadcast::<MqttReply>(100);
         let (outgoing_tx, mut outgoing_rx) = mpsc::<MqttRequest>(100);
         let cancel = CancellationToken::new();
@@ -54,16 +55,19 @@ impl Mqtt {
                         continue;
                     }
                     v = async {
+                        services.set("mqtt", ServiceState::Starting);
                         let mut backend = MqttBackend {
                             incomming_tx: thread_incoming_tx.clone(),
                             outgoing_rx: &mut outgoing_rx,
                             outgoing_tx: thread_outgoing_tx.clone(),
                             config: mqtt_config.as_ref().unwrap(),
                             cancel: CancellationToken::new(),
+                            services: services.clone(),
                         };
                         backend.run().await
                     }, if mqtt_config.is_some() => {
                         if let Err(e) = &v {
+                            services.set("mqtt", ServiceState::Failed(e.to_string()));
                             log::error!("MQTT Client Connection Failed: {:?}", e);
                             sleep(Duration::from_secs(2)).await;
                             continue;
@@ -120,6 +124,7 @@ struct MqttBackend<'a> {
     outgoing_tx: MpscSender<MqttRequest>,
     config: &'a MqttServerConfig,
     cancel: CancellationToken,
+    services: Services,
 }
 
 impl<'a> MqttBackend<'a> {
@@ -285,6 +290,7 @@ impl<'a> MqttBackend<'a> {
                     let incomming_tx = self.incomming_tx.clone();
                     let cancel = self.cancel.clone();
                     let thread_cancel = loop_cancel.clone();
+                    let services = self.services.clone();
                     tokio::task::spawn(async move {
                         tokio::select!{
                             _ = cancel.cancelled() => AnyResult::Ok(()),
@@ -293,6 +299,7 @@ impl<'a> MqttBackend<'a> {
                                 match notification {
                                     Event::Incoming(Incoming::ConnAck(connected)) => {
                                         if ConnectReturnCode::Success == connected.code {
+                                            services.set("mqtt", ServiceState::Running);
                                             // Publish connected now that we are online
                                             client
                                             .publish(
@@ -0,0 +1,186 @@
+//! The audio of the `control/talk` messages
+//!
+//! Anyone who can publish to `control/talk` chooses what is read, so local
+//! files are only played from the `talk_dir` of the `[mqtt]` config and
+//! everything is limited to [`MAX_TALK_SIZE`]
+use anyhow::{anyhow, Result};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use std::path::{Component, Path, PathBuf};
+
+use crate::common::NeoInstance;
+
+/// The most audio files from `control/talk` that can wait to be played
+pub(super) const TALK_QUEUE_LEN: usize = 10;
+
+/// The largest audio file that `control/talk` will play
+pub(super) const MAX_TALK_SIZE: u64 = 16 * 1024 * 1024;
+
+/// The audio of a `control/talk` message
+#[derive(Debug, PartialEq, Eq)]
+pub(super) enum TalkAudio {
+    File(PathBuf),
+    Bytes(Vec<u8>),
+}
+
+impl TalkAudio {
+    pub(super) async fn play(&self, camera: &NeoInstance) -> Result<()> {
+        match self {
+            TalkAudio::File(path) => crate::talk::talk_from_file(camera, path, 1.0).await,
+            TalkAudio::Bytes(bytes) => crate::talk::talk_from_bytes(camera, bytes, 1.0).await,
+        }
+    }
+}
+
+/// The `control/talk` message: the name of a file in the `talk_dir` or a
+/// base64 encoded audio file such as a WAV or MP3
+pub(super) async fn parse_talk_audio(message: &str, talk_dir: Option<&Path>) -> Result<TalkAudio> {
+    let message = message.trim();
+    if let Some(talk_dir) = talk_dir {
+        if let Some(path) = talk_file(talk_dir, message).await? {
+            return Ok(TalkAudio::File(path));
+        }
+    }
+
+    if message.len() as u64 > MAX_TALK_SIZE / 3 * 4 + 4 {
+        return Err(too_large());
+    }
+    match BASE64.decode(message) {
+        Ok(bytes) => Ok(TalkAudio::Bytes(bytes)),
+        Err(_) if Path::new(message).is_absolute() || message.contains("..") => Err(anyhow!(
+            "Only the files in the talk_dir of the [mqtt] config can be played, not {message:?}"
+        )),
+        Err(_) if talk_dir.is_none() => Err(anyhow!(
+            "Expected a base64 encoded audio file. Set the talk_dir of the [mqtt] config to play local files"
+        )),
+        Err(_) => Err(anyhow!(
+            "Expected a base64 encoded audio file or the name of a file in the talk_dir"
+        )),
+    }
+}
+
+/// The path of the file `name` inside the `talk_dir`
+///
+/// None if there is no such file. Names that would leave the `talk_dir`
+/// are never a file
+async fn talk_file(talk_dir: &Path, name: &str) -> Result<Option<PathBuf>> {
+    let name = Path::new(name);
+    if name.as_os_str().is_empty()
+        || !name
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)))
+    {
+        return Ok(None);
+    }
+    let Ok(talk_dir) = tokio::fs::canonicalize(talk_dir).await else {
+        return Ok(None);
+    };
+    // A symlink could still point outside of it
+    let Ok(path) = tokio::fs::canonicalize(talk_dir.join(name)).await else {
+        return Ok(None);
+    };
+    if !path.starts_with(&talk_dir) {
+        return Ok(None);
+    }
+    match tokio::fs::metadata(&path).await {
+        Ok(meta) if meta.is_file() && meta.len() > MAX_TALK_SIZE => Err(too_large()),
+        Ok(meta) if meta.is_file() => Ok(Some(path)),
+        _ => Ok(None),
+    }
+}
+
+fn too_large() -> anyhow::Error {
+    anyhow!(
+        "The audio is larger than {} MiB",
+        MAX_TALK_SIZE / 1024 / 1024
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[tokio::test]
+    async fn test_talk_dir() {
+        let root = tempdir().unwrap();
+        let talk_dir = root.path().join("talk");
+        fs::create_dir_all(talk_dir.join("warnings")).unwrap();
+        fs::write(talk_dir.join("hello.wav"), b"RIFF").unwrap();
+        fs::write(talk_dir.join("warnings/leave.wav"), b"RIFF").unwrap();
+        fs::write(root.path().join("secret.txt"), b"secret").unwrap();
+        let talk_dir = Some(talk_dir.as_path());
+
+        let file = |name: &str| {
+            TalkAudio::File(fs::canonicalize(root.path().join("talk").join(name)).unwrap())
+        };
+        assert_eq!(
+            parse_talk_audio("hello.wav", talk_dir).await.unwrap(),
+            file("hello.wav")
+        );
+        assert_eq!(
+            parse_talk_audio(" warnings/leave.wav\n", talk_dir)
+                .await
+                .unwrap(),
+            file("warnings/leave.wav")
+        );
+
+        let secret = root.path().join("secret.txt");
+        for name in [
+            "../secret.txt".to_string(),
+            "warnings/../../secret.txt".to_string(),
+            secret.to_string_lossy().to_string(),
+        ] {
+            assert!(
+                parse_talk_audio(&name, talk_dir).await.is_err(),
+                "{:?} was accepted",
+                name
+            );
+        }
+        // Without a talk_dir no file can be played
+        assert!(parse_talk_audio(&secret.to_string_lossy(), None)
+            .await
+            .is_err());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_talk_dir_symlink() {
+        let root = tempdir().unwrap();
+        let talk_dir = root.path().join("talk");
+        fs::create_dir(&talk_dir).unwrap();
+        fs::write(root.path().join("secret.txt"), b"secret").unwrap();
+        std::os::unix::fs::symlink(root.path().join("secret.txt"), talk_dir.join("link.wav"))
+            .unwrap();
+        assert!(parse_talk_audio("link.wav", Some(&talk_dir)).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_talk_size() {
+        let root = tempdir().unwrap();
+        let file = fs::File::create(root.path().join("large.wav")).unwrap();
+        file.set_len(MAX_TALK_SIZE + 1).unwrap();
+        let err = parse_talk_audio("large.wav", Some(root.path()))
+            .await
+            .unwrap_err();
+        assert_eq!(err.to_string(), "The audio is larger than 16 MiB");
+
+        let inline = "A".repeat(MAX_TALK_SIZE as usize / 3 * 4 + 8);
+        assert!(parse_talk_audio(&inline, None).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_inline() {
+        assert_eq!(
+            parse_talk_audio("UklGRg==", None).await.unwrap(),
+            TalkAudio::Bytes(b"RIFF".to_vec())
+        );
+        // Nothing is downloaded
+        let root = tempdir().unwrap();
+        assert!(
+            parse_talk_audio("https://example.com/hello.mp3", Some(root.path()))
+                .await
+                .is_err()
+        );
+    }
+}
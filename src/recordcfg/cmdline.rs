@@ -0,0 +1,72 @@
+use anyhow::{anyhow, Result};
+use clap::{Parser, ValueEnum};
+
+fn onoff_parse(src: &str) -> Result<bool> {
+    match src {
+        "true" | "on" | "yes" => Ok(true),
+        "false" | "off" | "no" => Ok(false),
+        _ => Err(anyhow!(
+            "Could not understand {}, check your input, should be true/false, on/off or yes/no",
+            src
+        )),
+    }
+}
+
+/// The record-config command will control the recordings that the camera
+/// makes to its SD card
+#[derive(Parser, Debug)]
+pub struct Opt {
+    /// The name of the camera. Must be a name in the config
+    pub camera: String,
+    /// The action to perform
+    #[command(subcommand)]
+    pub cmd: RecordAction,
+}
+
+#[derive(Parser, Debug)]
+pub enum RecordAction {
+    /// Get the current record config and schedule
+    Get,
+    /// Turn the recording ON keeping its schedule
+    On,
+    /// Turn the recording OFF keeping its schedule
+    Off,
+    /// Change the record config, anything not given is kept
+    Set {
+        /// Overwrite the oldest recordings when the disk is full
+        #[arg(long, value_parser = onoff_parse, action = clap::ArgAction::Set, value_name = "on|off")]
+        overwrite: Option<bool>,
+        /// Seconds to record before the motion starts
+        #[arg(long)]
+        pre_record: Option<u32>,
+        /// Seconds to keep recording after the motion stops
+        #[arg(long)]
+        post_record: Option<u32>,
+        /// The length of each recorded file in minutes
+        #[arg(long)]
+        package_time: Option<u32>,
+        /// The stream to record, only on cameras that report it
+        #[arg(long, value_enum)]
+        stream: Option<RecordStream>,
+    },
+    /// Turn the recording ON for every hour of the week
+    Schedule {
+        /// What makes the camera record
+        #[arg(value_enum)]
+        trigger: Trigger,
+    },
+}
+
+#[derive(Parser, Debug, Clone, Copy, ValueEnum)]
+pub enum RecordStream {
+    Main,
+    Sub,
+}
+
+#[derive(Parser, Debug, Clone, Copy, ValueEnum)]
+pub enum Trigger {
+    /// Record when there is motion
+    Motion,
+    /// Record all the time
+    Continuous,
+}
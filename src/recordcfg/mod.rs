@@ -0,0 +1,146 @@
+///
+/// # Neolink Record Config
+///
+/// This module handles the recordings that the camera makes to its SD card
+/// such as the pre and post record times, overwriting and the schedule
+///
+///
+/// # Usage
+///
+/// ```bash
+/// # To get the current settings
+/// neolink record-config --config=config.toml CameraName get
+/// # To turn the recording on or off
+/// neolink record-config --config=config.toml CameraName on
+/// # To change the pre and post record times
+/// neolink record-config --config=config.toml CameraName set --pre-record 5 --post-record 30
+/// # To record all the time instead of on motion
+/// neolink record-config --config=config.toml CameraName schedule continuous
+/// ```
+///
+use anyhow::{Context, Result};
+use neolink_core::bc_protocol::RecordTrigger;
+
+mod cmdline;
+
+use crate::common::NeoReactor;
+pub(crate) use cmdline::*;
+
+/// Entry point for the record-config subcommand
+///
+/// Opt is the command line options
+pub(crate) async fn main(opt: Opt, reactor: NeoReactor) -> Result<()> {
+    let camera = reactor.get(&opt.camera).await?;
+
+    match opt.cmd {
+        RecordAction::Get => {
+            let (record_cfg, record) = camera
+                .run_task(|cam| {
+                    Box::pin(async move {
+                        let record_cfg = cam
+                            .get_record_cfg()
+                            .await
+                            .context("Unable to get camera record config")?;
+                        let record = cam
+                            .get_record()
+                            .await
+                            .context("Unable to get camera record schedule")?;
+                        Ok((record_cfg, record))
+                    })
+                })
+                .await?;
+            for ser in [
+                {
+                    let mut buf = bytes::BytesMut::new();
+                    quick_xml::se::to_writer(&mut buf, &record_cfg).map(|_| buf.to_vec())
+                },
+                {
+                    let mut buf = bytes::BytesMut::new();
+                    quick_xml::se::to_writer(&mut buf, &record).map(|_| buf.to_vec())
+                },
+            ] {
+                let ser =
+                    String::from_utf8(ser.expect("Should Ser the struct")).expect("Should be UTF8");
+                println!("{}", ser);
+            }
+        }
+        RecordAction::On | RecordAction::Off => {
+            let on = matches!(opt.cmd, RecordAction::On);
+            camera
+                .run_task(|cam| {
+                    Box::pin(async move {
+                        cam.record_set(on)
+                            .await
+                            .context("Unable to set camera record state")
+                    })
+                })
+                .await?;
+        }
+        RecordAction::Set {
+            overwrite,
+            pre_record,
+            post_record,
+            package_time,
+            stream,
+        } => {
+            let name = opt.camera.clone();
+            camera
+                .run_task(|cam| {
+                    let name = name.clone();
+                    Box::pin(async move {
+                        let mut record_cfg = cam
+                            .get_record_cfg()
+                            .await
+                            .context("Unable to get camera record config")?;
+                        if let Some(overwrite) = overwrite {
+                            record_cfg.cycle = overwrite.into();
+                        }
+                        if let Some(pre_record) = pre_record {
+                            record_cfg.pre_record_time = pre_record;
+                        }
+                        if let Some(post_record) = post_record {
+                            record_cfg.record_delay_time = post_record;
+                        }
+                        if let Some(package_time) = package_time {
+                            record_cfg.package_time = package_time;
+                        }
+                        if let Some(stream) = stream {
+                            if record_cfg.stream_type.is_none() {
+                                log::warn!(
+                                    "{name}: The camera does not report the recorded stream, it may ignore it"
+                                );
+                            }
+                            record_cfg.stream_type = Some(
+                                match stream {
+                                    RecordStream::Main => "mainStream",
+                                    RecordStream::Sub => "subStream",
+                                }
+                                .to_string(),
+                            );
+                        }
+                        cam.set_record_cfg(record_cfg)
+                            .await
+                            .context("Unable to set camera record config")
+                    })
+                })
+                .await?;
+        }
+        RecordAction::Schedule { trigger } => {
+            let trigger = match trigger {
+                Trigger::Motion => RecordTrigger::Motion,
+                Trigger::Continuous => RecordTrigger::Continuous,
+            };
+            camera
+                .run_task(|cam| {
+                    Box::pin(async move {
+                        cam.record_on_always(trigger)
+                            .await
+                            .context("Unable to set camera record schedule")
+                    })
+                })
+                .await?;
+        }
+    }
+
+    Ok(())
+}
@@ -5,6 +5,7 @@ use anyhow::{anyhow, Context, Result};
 use gstreamer::{prelude::*, Bin, Caps, Element, ElementFactory, FlowError, GhostPad};
 use gstreamer_app::{AppSrc, AppSrcCallbacks, AppStreamType};
 use neolink_core::{
+    bc::xml::TalkConfig,
     bc_protocol::StreamKind,
     bcmedia::model::{
         BcMedia, BcMediaIframe, BcMediaInfoV1, BcMediaInfoV2, BcMediaPframe, VideoType,
@@ -12,7 +13,12 @@ use neolink_core::{
 };
 use tokio::{sync::mpsc::channel as mpsc, task::JoinHandle};
 
-use crate::{common::NeoInstance, rtsp::gst::NeoMediaFactory, AnyResult};
+use crate::{
+    common::NeoInstance,
+    rtsp::gst::{backchannel_of, NeoMediaFactory},
+    talk::talk_from_backchannel,
+    AnyResult,
+};
 
 #[derive(Clone, Debug)]
 pub enum AudioType {
@@ -153,6 +159,7 @@ enum ClientMsg {
 pub(super) async fn make_factory(
     camera: NeoInstance,
     stream: StreamKind,
+    talk_config: Option<TalkConfig>,
 ) -> AnyResult<(NeoMediaFactory, JoinHandle<AnyResult<()>>)> {
     let (client_tx, mut client_rx) = mpsc(100);
     // Create the task that creates the pipelines
@@ -165,6 +172,7 @@ pub(super) async fn make_factory(
                     log::debug!("New client for {name}::{stream}");
                     let camera = camera.clone();
                     let name = name.clone();
+                    let talk_config = talk_config.clone();
                     tokio::task::spawn(async move {
                         clear_bin(&element)?;
                         log::trace!("{name}::{stream}: Starting camera");
@@ -236,6 +244,18 @@ pub(super) async fn make_factory(
                             );
                         }
 
+                        // Talk through the camera if the client was given a backchannel
+                        if let Some(talk_config) = talk_config {
+                            let bin = element
+                                .clone()
+                                .dynamic_cast::<Bin>()
+                                .map_err(|_| anyhow!("Media source's element should be a bin"))?;
+                            if backchannel_of(&bin).is_some() {
+                                log::info!("{name}::{stream}: Talking from the RTSP backchannel");
+                                talk_from_backchannel(&camera, talk_config, &bin)?;
+                            }
+                        }
+
                         log::trace!("{name}::{stream}: Sending pipeline to gstreamer");
                         // Send the pipeline back to the factory so it can start
                         let _ = reply.send(element);
@@ -458,9 +478,13 @@ fn clear_bin(bin: &Element) -> Result<()> {
         .clone()
         .dynamic_cast::<Bin>()
         .map_err(|_| anyhow!("Media source's element should be a bin"))?;
-    // Clear the autogenerated ones
+    // Clear the autogenerated ones but keep the backchannel that the ONVIF factory
+    // added for the client
+    let backchannel = backchannel_of(&bin);
     for element in bin.iterate_elements().into_iter().flatten() {
-        bin.remove(&element)?;
+        if Some(&element) != backchannel.as_ref() {
+            bin.remove(&element)?;
+        }
     }
 
     Ok(())
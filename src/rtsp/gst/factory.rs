@@ -5,25 +5,33 @@
 
 use super::AnyResult;
 use gstreamer::glib::object_subclass;
-use gstreamer::Element;
 use gstreamer::{
     glib::{self, Object},
     Structure,
 };
+use gstreamer::{Bin, Element};
 use gstreamer_rtsp::RTSPUrl;
 use gstreamer_rtsp_server::prelude::*;
 use gstreamer_rtsp_server::subclass::prelude::*;
-use gstreamer_rtsp_server::RTSPMediaFactory;
 use gstreamer_rtsp_server::RTSPTransportMode;
-use gstreamer_rtsp_server::{RTSP_PERM_MEDIA_FACTORY_ACCESS, RTSP_PERM_MEDIA_FACTORY_CONSTRUCT};
+use gstreamer_rtsp_server::{RTSPContext, RTSPMediaFactory, RTSPOnvifMediaFactory};
+use gstreamer_rtsp_server::{
+    RTSP_PERM_MEDIA_FACTORY_ACCESS, RTSP_PERM_MEDIA_FACTORY_CONSTRUCT,
+    RTSP_TOKEN_MEDIA_FACTORY_ROLE,
+};
 use log::*;
 use std::collections::HashSet;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex as StdMutex};
 use tokio::sync::Mutex;
 
+/// The name that the ONVIF factory gives to the entry of the backchannel
+const BACKCHANNEL_DEPAY: &str = "depay_backchannel";
+
 glib::wrapper! {
     /// The wrapped RTSPMediaFactory
-    pub(crate) struct NeoMediaFactory(ObjectSubclass<NeoMediaFactoryImpl>) @extends RTSPMediaFactory;
+    ///
+    /// It is an ONVIF factory so that it can add an audio backchannel
+    pub(crate) struct NeoMediaFactory(ObjectSubclass<NeoMediaFactoryImpl>) @extends RTSPOnvifMediaFactory, RTSPMediaFactory;
 }
 
 impl Default for NeoMediaFactory {
@@ -92,6 +100,31 @@ impl NeoMediaFactory {
             );
         }
     }
+
+    /// Offer an ONVIF audio backchannel built from the gstreamer `launch`
+    ///
+    /// It must contain an element named `depay_backchannel` that takes the RTP
+    /// from the client. Only the `talk_roles` get the backchannel, everyone else
+    /// gets the media without it
+    pub(crate) fn set_backchannel<T: AsRef<str>>(&self, launch: &str, talk_roles: &HashSet<T>) {
+        self.imp()
+            .talk_roles
+            .lock()
+            .unwrap()
+            .extend(talk_roles.iter().map(|role| role.as_ref().to_string()));
+        self.set_backchannel_launch(Some(launch));
+    }
+}
+
+/// The child of the bin that holds the backchannel, if the client asked for one
+pub(crate) fn backchannel_of(bin: &Bin) -> Option<Element> {
+    bin.children().into_iter().find(|child| {
+        child
+            .clone()
+            .dynamic_cast::<Bin>()
+            .map(|child| child.by_name(BACKCHANNEL_DEPAY).is_some())
+            .unwrap_or(false)
+    })
 }
 
 unsafe impl Send for NeoMediaFactory {}
@@ -100,6 +133,7 @@ unsafe impl Sync for NeoMediaFactory {}
 pub(crate) struct NeoMediaFactoryImpl {
     #[allow(clippy::type_complexity)]
     call_back: Arc<Mutex<Option<Arc<dyn Fn(Element) -> AnyResult<Option<Element>> + Send + Sync>>>>,
+    talk_roles: StdMutex<HashSet<String>>,
 }
 
 impl Default for NeoMediaFactoryImpl {
@@ -108,6 +142,7 @@ impl Default for NeoMediaFactoryImpl {
         // Prepare thread that sends data into the appsrcs
         Self {
             call_back: Arc::new(Mutex::new(None)),
+            talk_roles: Default::default(),
         }
     }
 }
@@ -119,6 +154,28 @@ impl NeoMediaFactoryImpl {
     {
         self.call_back.lock().await.replace(Arc::new(callback));
     }
+    /// If the client of the current request may talk
+    ///
+    /// This is checked before the media is built so that clients who may not
+    /// talk never get the backchannel
+    fn client_may_talk(&self) -> bool {
+        let role = RTSPContext::with_current_context(|ctx| {
+            ctx.token()
+                .and_then(|token| token.string(RTSP_TOKEN_MEDIA_FACTORY_ROLE))
+        })
+        .flatten();
+        let permitted = role
+            .as_ref()
+            .map(|role| self.talk_roles.lock().unwrap().contains(role))
+            .unwrap_or(false);
+        if permitted {
+            log::debug!("Offering the backchannel to {:?}", role);
+        } else {
+            log::info!("{:?} is not permitted to talk, no backchannel", role);
+        }
+        permitted
+    }
+
     fn build_pipeline(&self, media: Element) -> AnyResult<Option<Element>> {
         match self.call_back.blocking_lock().as_ref() {
             Some(call) => {
@@ -143,10 +200,15 @@ impl RTSPMediaFactoryImpl for NeoMediaFactoryImpl {
             .and_then(|orig| self.build_pipeline(orig).expect("Could not build pipeline"))
     }
 }
+impl RTSPOnvifMediaFactoryImpl for NeoMediaFactoryImpl {
+    fn has_backchannel_support(&self) -> bool {
+        self.parent_has_backchannel_support() && self.client_may_talk()
+    }
+}
 
 #[object_subclass]
 impl ObjectSubclass for NeoMediaFactoryImpl {
     const NAME: &'static str = "NeoMediaFactory";
     type Type = super::NeoMediaFactory;
-    type ParentType = RTSPMediaFactory;
+    type ParentType = RTSPOnvifMediaFactory;
 }
@@ -13,7 +13,8 @@ use gstreamer_rtsp_server::{
     gio::{TlsAuthenticationMode, TlsCertificate},
     prelude::*,
     subclass::prelude::*,
-    RTSPAuth, RTSPFilterResult, RTSPServer, RTSPToken, RTSP_TOKEN_MEDIA_FACTORY_ROLE,
+    RTSPAuth, RTSPFilterResult, RTSPOnvifServer, RTSPServer, RTSPToken,
+    RTSP_TOKEN_MEDIA_FACTORY_ROLE,
 };
 use log::*;
 use std::{
@@ -30,7 +31,9 @@ use tokio_util::sync::CancellationToken;
 
 glib::wrapper! {
     /// The wrapped RTSPServer
-    pub(crate) struct NeoRtspServer(ObjectSubclass<NeoRtspServerImpl>) @extends RTSPServer;
+    ///
+    /// It is an ONVIF server so that clients can ask for the audio backchannel
+    pub(crate) struct NeoRtspServer(ObjectSubclass<NeoRtspServerImpl>) @extends RTSPOnvifServer, RTSPServer;
 }
 
 impl Default for NeoRtspServer {
@@ -174,12 +177,13 @@ pub(crate) struct NeoRtspServerImpl {
 
 impl ObjectImpl for NeoRtspServerImpl {}
 impl RTSPServerImpl for NeoRtspServerImpl {}
+impl RTSPOnvifServerImpl for NeoRtspServerImpl {}
 
 #[object_subclass]
 impl ObjectSubclass for NeoRtspServerImpl {
     const NAME: &'static str = "NeoRtspServer";
     type Type = NeoRtspServer;
-    type ParentType = RTSPServer;
+    type ParentType = RTSPOnvifServer;
 }
 
 impl NeoRtspServerImpl {
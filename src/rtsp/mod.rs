@@ -20,6 +20,9 @@
 ///
 /// `rtsp://my.ip.address:8554/Garage/subStream`
 ///
+/// Clients that support the ONVIF backchannel can talk through
+/// the camera when they are in the `talk_users` of the camera
+///
 /// # Usage
 ///
 /// To start the subcommand use the following in a shell.
@@ -72,7 +75,7 @@ mod factory;
 mod gst;
 mod stream;
 
-use crate::common::{NeoInstance, NeoReactor};
+use crate::common::{NeoInstance, NeoReactor, ServiceState};
 use factory::*;
 use stream::*;
 
@@ -202,7 +205,13 @@ pub(crate) async fn main(_opt: Opt, reactor: NeoReactor) -> Result<()> {
 
     let bind_addr = rtsp_config.bind_addr.clone();
     let bind_port = rtsp_config.bind_port;
-    rtsp.run(&bind_addr, bind_port).await?;
+    let services = reactor.services();
+    services.set("rtsp", ServiceState::Starting);
+    if let Err(e) = rtsp.run(&bind_addr, bind_port).await {
+        services.set("rtsp", ServiceState::Failed(e.to_string()));
+        return Err(e);
+    }
+    services.set("rtsp", ServiceState::Running);
     let thread_rtsp = rtsp.clone();
     set.spawn(async move { thread_rtsp.join().await });
 
@@ -294,6 +303,7 @@ async fn camera_main(camera: NeoInstance, rtsp: &NeoRtspServer) -> Result<()> {
     loop {
         let prev_stream_config = camera_config.borrow_and_update().stream;
         let prev_stream_users = camera_config.borrow().permitted_users.clone();
+        let prev_talk_users = camera_config.borrow().talk_users.clone();
         let active_streams = prev_stream_config
             .as_stream_kinds()
             .drain(..)
@@ -303,7 +313,7 @@ async fn camera_main(camera: NeoInstance, rtsp: &NeoRtspServer) -> Result<()> {
 
         // This select is for changes to camera_config.stream
         break tokio::select! {
-            v = camera_config.wait_for(|config| config.stream != prev_stream_config || config.permitted_users != prev_stream_users || config.use_splash != use_splash) => {
+            v = camera_config.wait_for(|config| config.stream != prev_stream_config || config.permitted_users != prev_stream_users || config.talk_users != prev_talk_users || config.use_splash != use_splash) => {
                 if let Err(e) = v {
                     AnyResult::Err(e.into())
                 } else {
@@ -327,6 +337,12 @@ async fn camera_main(camera: NeoInstance, rtsp: &NeoRtspServer) -> Result<()> {
                     // The user didn't specify permitted_users, and there are none defined anyway
                     None => ["anonymous".to_string()].iter().cloned().collect(),
                 };
+                // Only those that can watch can talk, "anyone" means all of them
+                let talk_users: HashSet<String> = match &prev_talk_users {
+                    Some(t) if t.iter().any(|u| u == "anyone") => permitted_users.clone(),
+                    Some(t) => t.iter().filter(|u| permitted_users.contains(*u)).cloned().collect(),
+                    None => Default::default(),
+                };
 
                 // Create the dummy factory
                 let dummy_factory = make_dummy_factory(use_splash, splash_pattern).await?;
@@ -363,7 +379,7 @@ async fn camera_main(camera: NeoInstance, rtsp: &NeoRtspServer) -> Result<()> {
                         log::debug!("{}: Preparing at {}", name, paths.join(", "));
 
                         supported_streams_1.wait_for(|ss| ss.contains(&StreamKind::Main)).await?;
-                        stream_main(camera.clone(), StreamKind::Main, rtsp, &permitted_users, &paths).await
+                        stream_main(camera.clone(), StreamKind::Main, rtsp, &permitted_users, &talk_users, &paths).await
                     }, if active_streams.contains(&StreamKind::Main) => v,
                     v = async {
                         let name = camera.config().await?.borrow().name.clone();
@@ -398,7 +414,7 @@ async fn camera_main(camera: NeoInstance, rtsp: &NeoRtspServer) -> Result<()> {
 
                         supported_streams_2.wait_for(|ss| ss.contains(&StreamKind::Sub)).await?;
 
-                        stream_main(camera.clone(), StreamKind::Sub, rtsp, &permitted_users, &paths).await
+                        stream_main(camera.clone(), StreamKind::Sub, rtsp, &permitted_users, &talk_users, &paths).await
                     }, if active_streams.contains(&StreamKind::Sub) => v,
                     v = async {
                         let name = camera.config().await?.borrow().name.clone();
@@ -431,7 +447,7 @@ async fn camera_main(camera: NeoInstance, rtsp: &NeoRtspServer) -> Result<()> {
                         log::debug!("{}: Preparing at {}", name, paths.join(", "));
 
                         supported_streams_3.wait_for(|ss| ss.contains(&StreamKind::Extern)).await?;
-                        stream_main(camera.clone(), StreamKind::Extern, rtsp, &permitted_users, &paths).await
+                        stream_main(camera.clone(), StreamKind::Extern, rtsp, &permitted_users, &talk_users, &paths).await
                     }, if active_streams.contains(&StreamKind::Extern) => v,
                     else => {
                         // all disabled just wait here until config is changed
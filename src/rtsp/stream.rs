@@ -2,7 +2,11 @@ use anyhow::anyhow;
 use gstreamer_rtsp_server::prelude::*;
 use std::collections::HashSet;
 
-use crate::{common::NeoInstance, AnyResult};
+use crate::{
+    common::NeoInstance,
+    talk::{backchannel_launch, talk_config},
+    AnyResult,
+};
 use neolink_core::bc_protocol::StreamKind;
 
 use super::{factory::*, gst::NeoRtspServer};
@@ -13,17 +17,34 @@ pub(crate) async fn stream_main(
     stream: StreamKind,
     rtsp: &NeoRtspServer,
     users: &HashSet<String>,
+    talk_users: &HashSet<String>,
     paths: &[String],
 ) -> AnyResult<()> {
     let name = camera.config().await?.borrow().name.clone();
+    // Offer the backchannel when someone may talk and the camera can
+    let talk_config = if talk_users.is_empty() {
+        None
+    } else {
+        match talk_config(&camera).await {
+            Ok(talk_config) => Some(talk_config),
+            Err(e) => {
+                log::warn!("{name}: No RTSP backchannel: {e:?}");
+                None
+            }
+        }
+    };
+    let backchannel_launch = talk_config.as_ref().map(backchannel_launch).transpose()?;
     // Create the factory and connect the stream
     let mounts = rtsp
         .mount_points()
         .ok_or(anyhow!("RTSP server lacks mount point"))?;
     // Create the factory
-    let (factory, thread) = make_factory(camera, stream).await?;
+    let (factory, thread) = make_factory(camera, stream, talk_config).await?;
 
     factory.add_permitted_roles(users);
+    if let Some(launch) = backchannel_launch {
+        factory.set_backchannel(&launch, talk_users);
+    }
 
     for path in paths.iter() {
         log::debug!("Path: {}", path);
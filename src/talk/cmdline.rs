@@ -1,14 +1,20 @@
-use clap::Parser;
+use clap::{ArgGroup, Parser};
 use std::path::PathBuf;
 use std::str::FromStr;
 
 /// The talk command will send audio for the camera to say
 ///
-/// This data should be encoded in a way that gstreamer can understand.
-/// This should be ok with most common formats.
+/// Uncompressed WAV files are played by neolink itself. Other formats
+/// should be encoded in a way that gstreamer can understand, which
+/// should be ok with most common formats.
 ///
 /// `gst-launch` can be used to prepare this data
 #[derive(Parser, Debug)]
+#[command(group(
+    ArgGroup::new("source")
+        .required(true)
+        .args(["file_path", "microphone", "url", "stdin"]),
+))]
 pub struct Opt {
     /// The name of the camera to talk through. Must be a name in the config
     pub camera: String,
@@ -27,6 +33,21 @@ pub struct Opt {
         conflicts_with = "file_path"
     )]
     pub input_src: String,
+    /// Stream live audio from a URL such as rtsp://, rtp:// or udp://
+    ///
+    /// It plays until neolink is stopped, reconnecting whenever the stream ends
+    /// or sends no audio for 15s
+    #[arg(short, long)]
+    pub url: Option<String>,
+    /// Stream raw signed 16 bit little endian PCM from stdin until it is closed
+    #[arg(long)]
+    pub stdin: bool,
+    /// The sample rate of the PCM on stdin
+    #[arg(long, default_value = "16000")]
+    pub stdin_rate: u32,
+    /// The number of channels of the PCM on stdin
+    #[arg(long, default_value = "1")]
+    pub stdin_channels: u8,
     /// Use to change the volume of the input
     #[arg(short, long, default_value = "1.0")]
     pub volume: f32,
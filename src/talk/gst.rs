@@ -1,33 +1,134 @@
 use crate::AnyResult;
 use anyhow::{anyhow, Context, Result};
-use crossbeam_channel::{bounded, Receiver, Sender};
+use crossbeam_channel::{bounded, Receiver, RecvTimeoutError, Sender};
 use gstreamer::{
     element_error, parse::launch_full, prelude::*, Caps, ClockTime, FlowError, FlowSuccess,
     MessageView, ParseFlags, Pipeline, ResourceError, State,
 };
 use gstreamer_app::{AppSink, AppSinkCallbacks};
+use neolink_core::bc_protocol::TalkEncoding;
+use std::time::Duration;
 use tokio::task::JoinSet;
 
+use super::LiveSource;
+
 use byte_slice_cast::*;
 
+/// The encoded audio of the gstreamer `input_src`
+///
+/// With a `no_data_timeout` the pipeline is stopped when no audio comes for
+/// that long, which ends the audio as if the source had ended
 #[allow(clippy::type_complexity)]
 pub(super) fn from_input(
     input_src: &str,
     volume: f32,
+    encoding: TalkEncoding,
     block_align: u16,
     sample_rate: u16,
+    no_data_timeout: Option<Duration>,
 ) -> Result<(JoinSet<AnyResult<()>>, Receiver<Vec<u8>>)> {
-    let pipeline = create_pipeline(input_src, volume, block_align, sample_rate)?;
-    input(pipeline)
+    let pipeline = create_pipeline(input_src, volume, encoding, block_align, sample_rate)?;
+    input(pipeline, no_data_timeout)
+}
+
+/// The gstreamer source of a live audio stream
+pub(super) fn live_source(source: &LiveSource) -> String {
+    match source {
+        LiveSource::Url(url) => format!("urisourcebin uri={:?}", url),
+        LiveSource::Stdin { rate, channels } => format!(
+            "fdsrc fd=0 \
+            ! rawaudioparse format=pcm pcm-format=s16le sample-rate={} num-channels={}",
+            rate, channels
+        ),
+    }
+}
+
+/// The name of the appsink at the end of the backchannel
+pub(super) const BACKCHANNEL_SINK: &str = "backchannel_sink";
+
+/// A gstreamer launch for an ONVIF backchannel
+///
+/// It takes PCMU over RTP from the client and encodes it for the camera
+pub(super) fn backchannel_launch(
+    encoding: TalkEncoding,
+    block_align: u16,
+    sample_rate: u16,
+) -> String {
+    format!(
+        "capsfilter name=depay_backchannel \
+        caps=\"application/x-rtp,media=audio,payload=0,clock-rate=8000,encoding-name=PCMU\" \
+        ! rtppcmudepay \
+        ! mulawdec \
+        ! audioconvert \
+        ! audioresample \
+        ! audio/x-raw,rate={},channels=1 \
+        ! queue \
+        ! {} \
+        ! appsink name={} async=false",
+        sample_rate,
+        encoder(encoding, block_align),
+        BACKCHANNEL_SINK
+    )
+}
+
+/// Receive the encoded audio from the `appsink` of a backchannel
+pub(super) fn from_backchannel(
+    appsink: &AppSink,
+    encoding: TalkEncoding,
+    block_align: u16,
+    sample_rate: u16,
+) -> Receiver<Vec<u8>> {
+    let (tx, rx) = bounded(30);
+    appsink.set_caps(Some(&encoded_caps(encoding, block_align, sample_rate)));
+    set_data_channel(appsink, tx);
+    rx
 }
 
 #[allow(clippy::type_complexity)]
-fn input(pipeline: Pipeline) -> Result<(JoinSet<AnyResult<()>>, Receiver<Vec<u8>>)> {
+fn input(
+    pipeline: Pipeline,
+    no_data_timeout: Option<Duration>,
+) -> Result<(JoinSet<AnyResult<()>>, Receiver<Vec<u8>>)> {
     let appsink = get_sink(&pipeline)?;
     let (tx, rx) = bounded(30);
     let mut set = JoinSet::<AnyResult<()>>::new();
     set_data_channel(&appsink, tx);
 
+    let rx = match no_data_timeout {
+        Some(timeout) => {
+            let (relay_tx, relay_rx) = bounded(30);
+            // Weak so that the audio still ends when the pipeline is dropped
+            let relay_pipeline = pipeline.downgrade();
+            set.spawn_blocking(move || {
+                loop {
+                    match rx.recv_timeout(timeout) {
+                        Ok(data) => {
+                            if relay_tx.send(data).is_err() {
+                                break;
+                            }
+                        }
+                        Err(RecvTimeoutError::Timeout) => {
+                            // The bus stops the pipeline on an error, which
+                            // also ends the reads that are stuck
+                            if let Some(pipeline) = relay_pipeline.upgrade() {
+                                element_error!(
+                                    pipeline,
+                                    ResourceError::Read,
+                                    ("No audio was received for {:?}", timeout)
+                                );
+                            }
+                            break;
+                        }
+                        Err(RecvTimeoutError::Disconnected) => break,
+                    }
+                }
+                AnyResult::Ok(())
+            });
+            relay_rx
+        }
+        None => rx,
+    };
+
     set.spawn_blocking(move || {
         let _ = start_pipeline(pipeline);
         AnyResult::Ok(())
@@ -47,13 +148,12 @@ fn start_pipeline(pipeline: Pipeline) -> Result<()> {
         match msg.view() {
             MessageView::Eos(..) => break,
             MessageView::Error(err) => {
-                pipeline
-                    .set_state(State::Null)
-                    .context("Error in gstreamer when setting state to Null")?;
+                // Stop here so that the talk ends and live sources can reconnect
                 log::warn!(
                     "Error from gstreamer when setting the play state {:?} setting to Null instead",
                     err
                 );
+                break;
             }
             _ => (),
         }
@@ -117,7 +217,7 @@ fn set_data_channel(appsink: &AppSink, tx: Sender<Vec<u8>>) {
                     element_error!(
                         appsink,
                         ResourceError::Failed,
-                        ("Failed to interprete buffer as u8 ADPCM")
+                        ("Failed to interprete buffer as u8 audio")
                     );
 
                     FlowError::Error
@@ -135,6 +235,7 @@ fn set_data_channel(appsink: &AppSink, tx: Sender<Vec<u8>>) {
 fn create_pipeline(
     source: &str,
     volume: f32,
+    encoding: TalkEncoding,
     block_align: u16,
     sample_rate: u16,
 ) -> Result<Pipeline> {
@@ -149,9 +250,12 @@ fn create_pipeline(
         ! audio/x-raw,rate={},channels=1 \
         ! volume volume={:.2} \
         ! queue  \
-        ! adpcmenc blockalign={} layout=dvi \
+        ! {} \
         ! appsink name=thesink",
-        source, sample_rate, volume, block_align
+        source,
+        sample_rate,
+        volume,
+        encoder(encoding, block_align)
     );
 
     log::info!("{}", launch_str);
@@ -171,14 +275,33 @@ fn create_pipeline(
     // provide the format we request.
     // This can be set after linking the two objects, because format negotiation between
     // both elements will happen during pre-rolling of the pipeline.
-    appsink.set_caps(Some(
-        &Caps::builder("audio/x-adpcm")
+    appsink.set_caps(Some(&encoded_caps(encoding, block_align, sample_rate)));
+
+    Ok(pipeline)
+}
+
+/// The gstreamer elements that encode raw audio for the camera
+fn encoder(encoding: TalkEncoding, block_align: u16) -> String {
+    match encoding {
+        TalkEncoding::Adpcm => format!("adpcmenc blockalign={} layout=dvi", block_align),
+        TalkEncoding::Aac => "avenc_aac ! aacparse".to_string(),
+    }
+}
+
+/// The caps of the audio that the camera expects
+fn encoded_caps(encoding: TalkEncoding, block_align: u16, sample_rate: u16) -> Caps {
+    match encoding {
+        TalkEncoding::Adpcm => Caps::builder("audio/x-adpcm")
             .field("layout", "dvi")
             .field("block_align", block_align as i32)
             .field("channels", 1i32)
             .field("rate", sample_rate as i32)
             .build(),
-    ));
-
-    Ok(pipeline)
+        TalkEncoding::Aac => Caps::builder("audio/mpeg")
+            .field("mpegversion", 4i32)
+            .field("stream-format", "adts")
+            .field("channels", 1i32)
+            .field("rate", sample_rate as i32)
+            .build(),
+    }
 }
@@ -1,23 +1,34 @@
 ///
 /// # Neolink Talk
 ///
-/// This module can be used to send adpcm data for the camera to play
+/// This module can be used to send audio for the camera to play
 ///
-/// The adpcm data needs to be in DVI-4 layout
+/// The encoding is picked from the talk abilities of the camera, either
+/// adpcm or aac. WAV files are encoded to adpcm by neolink. Other files, the
+/// microphone and cameras that talk in aac need gstreamer
 ///
 /// # Usage
 ///
 /// ```bash
-/// neolink talk --config=config.toml --adpcm-file=data.adpcm --sample-rate=16000 --block-size=512 CameraName
+/// neolink talk --config=config.toml --file-path=message.wav CameraName
+/// neolink talk --config=config.toml --microphone CameraName
+/// neolink talk --config=config.toml --url=rtsp://intercom/audio CameraName
+/// intercom | neolink talk --config=config.toml --stdin CameraName
 /// ```
 ///
 use anyhow::{anyhow, Context, Result};
-use neolink_core::bc::xml::TalkConfig;
+use neolink_core::{
+    audio::{AdpcmEncoder, Wav},
+    bc::xml::TalkConfig,
+    bc_protocol::TalkEncoding,
+};
+use std::path::Path;
 
 mod cmdline;
+#[cfg(feature = "gstreamer")]
 mod gst;
 
-use crate::common::NeoReactor;
+use crate::common::{NeoInstance, NeoReactor};
 pub(crate) use cmdline::Opt;
 
 /// Entry point for the talk subcommand
@@ -25,6 +36,184 @@ pub(crate) use cmdline::Opt;
 /// Opt is the command line options
 pub(crate) async fn main(opt: Opt, reactor: NeoReactor) -> Result<()> {
     let camera = reactor.get(&opt.camera).await?;
+
+    if let Some(path) = &opt.file_path {
+        talk_from_file(&camera, path, opt.volume)
+            .await
+            .with_context(|| format!("Failed to talk with the file: {:?}", path))
+    } else if opt.microphone {
+        talk_from_microphone(&camera, &opt.input_src, opt.volume)
+            .await
+            .context("Failed to talk using the microphone")
+    } else if let Some(url) = &opt.url {
+        talk_from_live(&camera, LiveSource::Url(url.clone()), opt.volume)
+            .await
+            .with_context(|| format!("Failed to talk from {:?}", url))
+    } else {
+        let source = LiveSource::Stdin {
+            rate: opt.stdin_rate,
+            channels: opt.stdin_channels,
+        };
+        talk_from_live(&camera, source, opt.volume)
+            .await
+            .context("Failed to talk from stdin")
+    }
+}
+
+/// A source of live audio for the camera to play
+#[cfg_attr(not(feature = "gstreamer"), allow(dead_code))]
+enum LiveSource {
+    /// A stream such as rtsp://, rtp:// or udp:// which is reconnected when it ends
+    Url(String),
+    /// Signed 16 bit little endian PCM on stdin that plays until it is closed
+    Stdin { rate: u32, channels: u8 },
+}
+
+/// Play an audio file on the camera until it ends
+///
+/// WAV files are encoded here when the camera talks in adpcm, everything
+/// else needs gstreamer
+pub(crate) async fn talk_from_file(camera: &NeoInstance, path: &Path, volume: f32) -> Result<()> {
+    let bytes = tokio::fs::read(path)
+        .await
+        .with_context(|| format!("Could not read {:?}", path))?;
+    talk_from_audio(camera, &bytes, Some(path), volume).await
+}
+
+/// Play an audio file that is held in memory on the camera until it ends
+///
+/// This is the same as [`talk_from_file`] but anything other than WAV is
+/// written to a temporary file for gstreamer to read
+pub(crate) async fn talk_from_bytes(camera: &NeoInstance, bytes: &[u8], volume: f32) -> Result<()> {
+    talk_from_audio(camera, bytes, None, volume).await
+}
+
+async fn talk_from_audio(
+    camera: &NeoInstance,
+    bytes: &[u8],
+    path: Option<&Path>,
+    volume: f32,
+) -> Result<()> {
+    let talk_config = talk_config(camera).await?;
+    match (talk_encoding(&talk_config)?, Wav::read(bytes)) {
+        (TalkEncoding::Adpcm, Ok(wav)) => talk_from_wav(camera, talk_config, wav, volume).await,
+        (TalkEncoding::Adpcm, Err(e)) => {
+            let why = anyhow!(e)
+                .context("Only uncompressed WAV files can be played without the gstreamer feature");
+            talk_from_other_file(camera, talk_config, bytes, path, volume, why).await
+        }
+        (encoding, _) => {
+            let why = anyhow!(
+                "The camera talks in {:?} which needs the gstreamer feature",
+                encoding
+            );
+            talk_from_other_file(camera, talk_config, bytes, path, volume, why).await
+        }
+    }
+}
+
+#[cfg(feature = "gstreamer")]
+async fn talk_from_other_file(
+    camera: &NeoInstance,
+    talk_config: TalkConfig,
+    bytes: &[u8],
+    path: Option<&Path>,
+    volume: f32,
+    why: anyhow::Error,
+) -> Result<()> {
+    log::debug!("Playing {:?} with gstreamer: {why:#}", path);
+    // gstreamer reads from a file so audio that is only in memory is saved first
+    let (path, is_temp) = match path {
+        Some(path) => (path.to_path_buf(), false),
+        None => {
+            let path = std::env::temp_dir().join(format!("neolink-talk-{}", uuid::Uuid::new_v4()));
+            tokio::fs::write(&path, bytes)
+                .await
+                .with_context(|| format!("Could not write the audio to {:?}", path))?;
+            (path, true)
+        }
+    };
+    let result = talk_from_input(
+        camera,
+        talk_config,
+        &format!("filesrc location={:?}", path.display().to_string()),
+        volume,
+        None,
+    )
+    .await;
+    if is_temp {
+        let _ = tokio::fs::remove_file(&path).await;
+    }
+    result
+}
+
+#[cfg(not(feature = "gstreamer"))]
+async fn talk_from_other_file(
+    _camera: &NeoInstance,
+    _talk_config: TalkConfig,
+    _bytes: &[u8],
+    _path: Option<&Path>,
+    _volume: f32,
+    why: anyhow::Error,
+) -> Result<()> {
+    Err(why)
+}
+
+#[cfg(feature = "gstreamer")]
+async fn talk_from_microphone(camera: &NeoInstance, input_src: &str, volume: f32) -> Result<()> {
+    let talk_config = talk_config(camera).await?;
+    talk_from_input(camera, talk_config, input_src, volume, None).await
+}
+
+#[cfg(not(feature = "gstreamer"))]
+async fn talk_from_microphone(_camera: &NeoInstance, _input_src: &str, _volume: f32) -> Result<()> {
+    Err(anyhow!(
+        "The microphone needs neolink to be built with the gstreamer feature"
+    ))
+}
+
+/// How long to wait before reconnecting to a live audio stream
+#[cfg(feature = "gstreamer")]
+const LIVE_RECONNECT_DELAY: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// How long a live audio stream can send nothing before it is reconnected
+#[cfg(feature = "gstreamer")]
+const LIVE_NO_DATA_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(15);
+
+/// Play a live audio source on the camera
+///
+/// URLs are played until neolink stops, reconnecting whenever the stream ends,
+/// fails or sends nothing for [`LIVE_NO_DATA_TIMEOUT`], and stdin is played
+/// until it is closed
+#[cfg(feature = "gstreamer")]
+async fn talk_from_live(camera: &NeoInstance, source: LiveSource, volume: f32) -> Result<()> {
+    let talk_config = talk_config(camera).await?;
+    let input_src = gst::live_source(&source);
+    match source {
+        LiveSource::Url(url) => loop {
+            let no_data = Some(LIVE_NO_DATA_TIMEOUT);
+            match talk_from_input(camera, talk_config.clone(), &input_src, volume, no_data).await {
+                Ok(()) => log::info!("The stream from {:?} ended, reconnecting", url),
+                Err(e) => log::warn!("The stream from {:?} failed, reconnecting: {:?}", url, e),
+            }
+            tokio::time::sleep(LIVE_RECONNECT_DELAY).await;
+        },
+        LiveSource::Stdin { .. } => {
+            talk_from_input(camera, talk_config, &input_src, volume, None).await
+        }
+    }
+}
+
+#[cfg(not(feature = "gstreamer"))]
+async fn talk_from_live(_camera: &NeoInstance, _source: LiveSource, _volume: f32) -> Result<()> {
+    Err(anyhow!(
+        "Live audio needs neolink to be built with the gstreamer feature"
+    ))
+}
+
+/// The talk config of the camera, from the first of its talk abilities in an
+/// encoding that neolink can send
+pub(crate) async fn talk_config(camera: &NeoInstance) -> Result<TalkConfig> {
     let config = camera.config().await?.borrow().clone();
     let name = config.name.clone();
 
@@ -38,53 +227,92 @@ pub(crate) async fn main(opt: Opt, reactor: NeoReactor) -> Result<()> {
         .await
         .with_context(|| format!("Camera {} does not support talk", name))?;
 
-    if talk_ability.duplex_list.is_empty()
-        || talk_ability.audio_stream_mode_list.is_empty()
-        || talk_ability.audio_config_list.is_empty()
-    {
-        return Err(anyhow!("Camera {} does not support talk", name));
-    }
+    talk_ability.talk_config(config.channel_id).ok_or_else(|| {
+        let advertised = talk_ability
+            .audio_config_list
+            .iter()
+            .map(|list| list.audio_config.audio_type.as_str())
+            .collect::<Vec<_>>();
+        anyhow!(
+            "Camera {} does not support talk in a known encoding, it advertises: {:?}",
+            name,
+            advertised
+        )
+    })
+}
 
-    // Just copy that data from the first talk ability in the config have never seen more
-    // than one ability
-    let config_id = 0;
-
-    let talk_config = TalkConfig {
-        channel_id: config.channel_id,
-        duplex: talk_ability.duplex_list[config_id].duplex.clone(),
-        audio_stream_mode: talk_ability.audio_stream_mode_list[config_id]
-            .audio_stream_mode
-            .clone(),
-        audio_config: talk_ability.audio_config_list[config_id]
-            .audio_config
-            .clone(),
-        ..Default::default()
-    };
+fn talk_encoding(talk_config: &TalkConfig) -> Result<TalkEncoding> {
+    TalkEncoding::of(&talk_config.audio_config).ok_or_else(|| {
+        anyhow!(
+            "Unknown talk encoding {:?}",
+            talk_config.audio_config.audio_type
+        )
+    })
+}
 
-    let block_size = (talk_config.audio_config.length_per_encoder / 2) + 4;
+/// Play the `wav` on the camera until it ends, without gstreamer
+async fn talk_from_wav(
+    camera: &NeoInstance,
+    talk_config: TalkConfig,
+    wav: Wav,
+    volume: f32,
+) -> Result<()> {
+    let mut encoder = AdpcmEncoder::from_talk_config(&talk_config)
+        .context("The camera does not support talk with adpcm")?;
     let sample_rate = talk_config.audio_config.sample_rate;
-    if block_size == 0 || sample_rate == 0 {
-        return Err(anyhow!(
-            "The camera {} does not support talk with adpcm",
-            name
-        ));
+    if sample_rate == 0 {
+        return Err(anyhow!("The camera does not support talk with adpcm"));
     }
 
-    let (mut set, rx) = match (&opt.file_path, &opt.microphone) {
-        (Some(path), false) => gst::from_input(
-            &format!(
-                "filesrc location={}",
-                path.to_str().expect("File path not UTF8 complient")
-            ),
-            opt.volume,
-            block_size,
-            sample_rate,
-        )
-        .with_context(|| format!("Failed to setup gst with the file: {:?}", path))?,
-        (None, true) => gst::from_input(&opt.input_src, opt.volume, block_size, sample_rate)
-            .context("Failed to setup gst using the microphone")?,
-        _ => unreachable!(),
-    };
+    let wav = wav.resampled(sample_rate as u32);
+    let samples = wav
+        .samples
+        .iter()
+        .map(|sample| (*sample as f32 * volume).clamp(i16::MIN as f32, i16::MAX as f32) as i16)
+        .collect::<Vec<_>>();
+    let mut adpcm = encoder.encode(&samples);
+    adpcm.extend(encoder.finish());
+
+    let (tx, rx) = crossbeam_channel::unbounded();
+    for block in adpcm.chunks(encoder.block_len()) {
+        tx.send(block.to_vec())?;
+    }
+    drop(tx);
+
+    camera
+        .run_task(|cam| {
+            let rx = rx.clone();
+            let talk_config = talk_config.clone();
+            Box::pin(async move {
+                cam.talk_stream(rx, talk_config).await?;
+                Ok(())
+            })
+        })
+        .await
+        .context("Talk stream ended early")
+}
+
+/// Play the audio from the gstreamer `input_src` on the camera until it ends
+///
+/// With a `no_data_timeout` it also ends when the input sends nothing for that long
+#[cfg(feature = "gstreamer")]
+pub(crate) async fn talk_from_input(
+    camera: &NeoInstance,
+    talk_config: TalkConfig,
+    input_src: &str,
+    volume: f32,
+    no_data_timeout: Option<std::time::Duration>,
+) -> Result<()> {
+    let (encoding, block_size, sample_rate) = gst_params(&talk_config)?;
+    let (mut set, rx) = gst::from_input(
+        input_src,
+        volume,
+        encoding,
+        block_size,
+        sample_rate,
+        no_data_timeout,
+    )
+    .with_context(|| format!("Failed to setup gst with {input_src:?}"))?;
 
     camera
         .run_task(|cam| {
@@ -103,3 +331,65 @@ pub(crate) async fn main(opt: Opt, reactor: NeoReactor) -> Result<()> {
 
     Ok(())
 }
+
+/// The encoding, block align and sample rate that gstreamer encodes to
+#[cfg(feature = "gstreamer")]
+fn gst_params(talk_config: &TalkConfig) -> Result<(TalkEncoding, u16, u16)> {
+    let encoding = talk_encoding(talk_config)?;
+    let sample_rate = talk_config.audio_config.sample_rate;
+    let block_size = (talk_config.audio_config.length_per_encoder / 2) + 4;
+    if sample_rate == 0 || (encoding == TalkEncoding::Adpcm && block_size == 4) {
+        return Err(anyhow!(
+            "The camera does not support talk with {:?}",
+            encoding
+        ));
+    }
+    Ok((encoding, block_size, sample_rate))
+}
+
+/// The gstreamer launch of an ONVIF backchannel that encodes the audio from
+/// RTSP clients for the camera
+#[cfg(feature = "gstreamer")]
+pub(crate) fn backchannel_launch(talk_config: &TalkConfig) -> Result<String> {
+    let (encoding, block_size, sample_rate) = gst_params(talk_config)?;
+    Ok(gst::backchannel_launch(encoding, block_size, sample_rate))
+}
+
+/// Play the audio of an RTSP backchannel on the camera until the client leaves
+///
+/// `bin` is the media of the client, nothing is played if it has no backchannel
+#[cfg(feature = "gstreamer")]
+pub(crate) fn talk_from_backchannel(
+    camera: &NeoInstance,
+    talk_config: TalkConfig,
+    bin: &gstreamer::Bin,
+) -> Result<()> {
+    use gstreamer::prelude::*;
+
+    let Some(appsink) = bin.by_name(gst::BACKCHANNEL_SINK) else {
+        return Ok(());
+    };
+    let appsink = appsink
+        .dynamic_cast::<gstreamer_app::AppSink>()
+        .map_err(|_| anyhow!("The backchannel sink should be an appsink"))?;
+    let (encoding, block_size, sample_rate) = gst_params(&talk_config)?;
+    let rx = gst::from_backchannel(&appsink, encoding, block_size, sample_rate);
+
+    let camera = camera.clone();
+    tokio::task::spawn(async move {
+        let result = camera
+            .run_task(|cam| {
+                let rx = rx.clone();
+                let talk_config = talk_config.clone();
+                Box::pin(async move {
+                    cam.talk_stream(rx, talk_config).await?;
+                    Ok(())
+                })
+            })
+            .await;
+        if let Err(e) = result {
+            log::warn!("Talk from the backchannel ended early: {e:?}");
+        }
+    });
+    Ok(())
+}